@@ -0,0 +1,112 @@
+//! Lexer and parser throughput benchmarks, run with `cargo bench`.
+//!
+//! Uses a plain `harness = false` main rather than criterion so it builds on
+//! stable without extra dependencies. Each case is warmed up, then timed over
+//! several samples and the median is reported.
+
+use c_comp_rs::ast;
+use c_comp_rs::lexer::Lexer;
+use c_comp_rs::parser::Parser;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const WARMUP: usize = 3;
+const SAMPLES: usize = 15;
+
+/// Many short statements in a handful of functions
+fn flat_source(statements: usize) -> String {
+    let mut source = String::new();
+    for f in 0..statements / 1000 {
+        source.push_str(&format!("int f{}(int a, int b) {{\n", f));
+        for i in 0..1000 {
+            source.push_str(&format!("    int v{} = a * {} + b / 2 - {};\n", i, i, i));
+        }
+        source.push_str("    return a == b;\n}\n");
+    }
+    source
+}
+
+/// A few functions each returning one deeply parenthesised expression
+fn nested_source(functions: usize, depth: usize) -> String {
+    let mut source = String::new();
+    for f in 0..functions {
+        source.push_str(&format!("int g{}(void) {{\n    return ", f));
+        for _ in 0..depth {
+            source.push_str("(1 + ");
+        }
+        source.push('1');
+        for _ in 0..depth {
+            source.push(')');
+        }
+        source.push_str(";\n}\n");
+    }
+    source
+}
+
+fn median<F: FnMut()>(mut f: F) -> Duration {
+    for _ in 0..WARMUP {
+        f();
+    }
+    let mut times: Vec<Duration> = (0..SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    times[SAMPLES / 2]
+}
+
+fn report(name: &str, unit: &str, count: usize, time: Duration) {
+    let per_sec = count as f64 / time.as_secs_f64();
+    println!(
+        "{:<16} {:>9} {:<6} {:>10.3} ms  {:>12.0} {}/sec",
+        name,
+        count,
+        unit,
+        time.as_secs_f64() * 1000.0,
+        per_sec,
+        unit
+    );
+}
+
+/// Enough for `nested_source`, which runs on the main thread's larger stack
+const NESTING_LIMIT: usize = 1_000;
+
+fn bench(name: &str, source: &str) {
+    let mut lexer = Lexer::from_string(source);
+    let tokens = lexer.tokenise().expect("benchmark source should lex");
+    let token_count = tokens.len();
+    // the nested case deliberately goes past the default depth limit
+    let mut parser = Parser::new(tokens).max_depth(NESTING_LIMIT);
+    let program = parser.parse().expect("benchmark source should parse");
+    let node_count: usize = program.iter().map(ast::count_nodes).sum();
+
+    let lex_time = median(|| {
+        let mut lexer = Lexer::from_string(black_box(source));
+        black_box(lexer.tokenise().unwrap().len());
+    });
+    report(&format!("{}/lex", name), "tokens", token_count, lex_time);
+
+    let parse_time = median(|| {
+        let mut parser = Parser::new(black_box(tokens)).max_depth(NESTING_LIMIT);
+        black_box(parser.parse().unwrap());
+    });
+    report(&format!("{}/parse", name), "nodes", node_count, parse_time);
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`; any other argument is treated as a filter
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    let cases = [
+        ("flat", flat_source(20_000)),
+        ("nested", nested_source(100, 500)),
+    ];
+
+    for (name, source) in &cases {
+        if filter.as_ref().is_none_or(|f| name.contains(f.as_str())) {
+            bench(name, source);
+        }
+    }
+}
@@ -0,0 +1,1703 @@
+//! Semantic analyses run over a parsed program, reported as diagnostics
+
+use crate::ast;
+use crate::diagnostic::Diagnostic;
+use crate::eval::{self, Enumerators, Value};
+use crate::layout::Layouts;
+use crate::lexer::Token;
+use crate::lexer::{LanguageStandard, TokenType};
+use crate::parser::{Designator, Expr, ExprKind, Initialiser, Statement};
+use crate::target::TargetConfig;
+use crate::typeck::TypeChecker;
+use crate::types::Type;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Runs every analysis over `program`:
+/// * type checking, which also reports undeclared identifiers
+/// * non-void functions that can reach their end without returning
+/// * statements that follow a `return`, `goto`, `break` or `continue`
+/// * `break`/`continue` outside of a loop (or switch, for `break`)
+/// * `return` outside of a function
+/// * local variables and parameters that are never read
+/// * `case` labels that aren't integer constants or repeat within a switch
+/// * enumerators given a value that isn't an integer constant
+/// * `goto`s to labels that don't exist, and labels defined more than once
+/// * `=` used as the condition of an `if` or loop, likely meaning `==`
+/// * functions declared without a return type, and variables declared with a
+///   storage class but no type, which default to `int`
+/// * static assertions whose condition is zero or isn't an integer constant
+/// * integer division by a constant zero
+/// * variables and members assigned to themselves, as in `x = x`
+/// * functions defined more than once
+/// * `static` functions that can't be reached from `main` or any function
+///   other translation units could call
+pub fn check(program: &[Statement]) -> Vec<Diagnostic> {
+    check_for_target(program, &TargetConfig::default())
+}
+
+/// [`check`], folding constant expressions as they would be on `target`. The
+/// diagnostics of every analysis are sorted together into source order.
+pub fn check_for_target(program: &[Statement], target: &TargetConfig) -> Vec<Diagnostic> {
+    check_with(program, target, LanguageStandard::default())
+}
+
+/// [`check_for_target`], type checking by the rules of the C `standard`
+pub fn check_with(
+    program: &[Statement],
+    target: &TargetConfig,
+    standard: LanguageStandard,
+) -> Vec<Diagnostic> {
+    check_timed(program, target, standard, |_, _| ())
+}
+
+/// [`check_with`], calling `time` with the name of each analysis and how long
+/// it took, in the order they ran
+pub fn check_timed(
+    program: &[Statement],
+    target: &TargetConfig,
+    standard: LanguageStandard,
+    mut time: impl FnMut(&'static str, Duration),
+) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    timed(&mut time, "types", || {
+        let mut checker = TypeChecker::for_target(*target).standard(standard);
+        if let Err(errors) = checker.check(program) {
+            diagnostics.extend(
+                errors
+                    .iter()
+                    .map(|e| Diagnostic::error(e.line(), e.to_string())),
+            );
+        }
+    });
+    timed(&mut time, "duplicate-functions", || {
+        if let Err(duplicates) = function_table(program) {
+            diagnostics.extend(duplicates);
+        }
+    });
+    let graph = timed(&mut time, "call-graph", || call_graph(program));
+    timed(&mut time, "unused-functions", || {
+        unused_functions(program, &graph, &mut diagnostics)
+    });
+    let mut enumerators = Enumerators::default();
+    let mut layouts = Layouts::default();
+    timed(&mut time, "constants", || {
+        for statement in program {
+            define_constants(
+                statement,
+                target,
+                &mut enumerators,
+                &mut layouts,
+                &mut diagnostics,
+            );
+        }
+    });
+    let volatile = timed(&mut time, "volatile-names", || volatile_names(program));
+
+    // each of these looks at one top-level statement at a time
+    let mut each = |analysis, check: &mut dyn FnMut(&Statement, &mut Vec<Diagnostic>)| {
+        timed(&mut time, analysis, || {
+            for statement in program {
+                check(statement, &mut diagnostics);
+            }
+        })
+    };
+    each("missing-return", &mut missing_return);
+    each("dead-code", &mut dead_code);
+    each("loop-control", &mut |s, d| loop_control(s, false, false, d));
+    each("stray-return", &mut stray_return);
+    each("unused-variables", &mut unused_variables);
+    each("switch-cases", &mut |s, d| {
+        switch_cases(s, false, target, &enumerators, &layouts, d)
+    });
+    each("goto-labels", &mut goto_labels);
+    each("assignment-in-condition", &mut assignment_in_condition);
+    each("no-effect", &mut |s, d| no_effect(s, &volatile, d));
+    each("implicit-int", &mut implicit_int);
+    each("static-assertions", &mut |s, d| {
+        static_assertions(s, target, &enumerators, &layouts, d)
+    });
+    each("division-by-zero", &mut |s, d| {
+        division_by_zero(s, target, &enumerators, &layouts, d)
+    });
+    each("self-assignment", &mut |s, d| {
+        self_assignment(s, &volatile, d)
+    });
+
+    diagnostics.sort();
+    diagnostics
+}
+
+/// Runs `run`, passing how long it took to `time` under the name `analysis`
+fn timed<T>(
+    time: &mut impl FnMut(&'static str, Duration),
+    analysis: &'static str,
+    run: impl FnOnce() -> T,
+) -> T {
+    let start = Instant::now();
+    let result = run();
+    time(analysis, start.elapsed());
+    result
+}
+
+/// Warnings for calls to `printf`, `fprintf`, `sprintf` and `scanf` whose
+/// literal format string doesn't match the arguments after it, in number or
+/// kind. Not one of the analyses [`check`] runs, as a program may well define
+/// functions of those names that take other formats.
+pub fn format_warnings(program: &[Statement]) -> Vec<Diagnostic> {
+    let mut checker = TypeChecker::new().lint_formats(true);
+    // type errors are for `check` to report
+    let _ = checker.check(program);
+    let mut warnings = checker.warnings().to_vec();
+    warnings.sort();
+    warnings
+}
+
+/// Whether control can never run past the end of `stmt`. `break` and
+/// `continue` only count when `loop_control` is set.
+fn diverges(stmt: &Statement, loop_control: bool) -> bool {
+    match stmt {
+        Statement::Return { .. } | Statement::Goto { .. } => true,
+        Statement::Break(_) | Statement::Continue(_) => loop_control,
+        Statement::Labeled { body, .. } => diverges(body, loop_control),
+        Statement::Block(body) => body.iter().any(|s| diverges(s, loop_control)),
+        Statement::If {
+            then_branch,
+            else_branch: Some(else_branch),
+            ..
+        } => diverges(then_branch, loop_control) && diverges(else_branch, loop_control),
+        _ => false,
+    }
+}
+
+fn missing_return(stmt: &Statement, diagnostics: &mut Vec<Diagnostic>) {
+    let Statement::Function {
+        return_type,
+        name,
+        body,
+        specifiers,
+        ..
+    } = stmt
+    else {
+        return;
+    };
+
+    // falling off the end of `main` returns 0, and a `_Noreturn` function
+    // never gets to its end
+    if *return_type == Type::Void || name.literal == "main" || specifiers.is_noreturn {
+        return;
+    }
+    if !body.iter().any(|s| diverges(s, false)) {
+        diagnostics.push(Diagnostic::warning(
+            name.line,
+            format!(
+                "control reaches end of non-void function '{}'",
+                name.literal
+            ),
+        ));
+    }
+}
+
+/// Statements directly nested inside `stmt`
+fn children<'s, 'a>(stmt: &'s Statement<'a>) -> Vec<&'s Statement<'a>> {
+    match stmt {
+        Statement::Function { body, .. } | Statement::Block(body) => body.iter().collect(),
+        Statement::If {
+            then_branch,
+            else_branch,
+            ..
+        } => std::iter::once(then_branch.as_ref())
+            .chain(else_branch.as_deref())
+            .collect(),
+        Statement::For {
+            initialiser, body, ..
+        } => initialiser
+            .iter()
+            .chain(std::iter::once(body.as_ref()))
+            .collect(),
+        Statement::While { body, .. }
+        | Statement::Switch { body, .. }
+        | Statement::Case { body, .. }
+        | Statement::Default { body, .. }
+        | Statement::Labeled { body, .. } => vec![body],
+        Statement::Expression(_)
+        | Statement::Return { .. }
+        | Statement::VarDecl { .. }
+        | Statement::Struct { .. }
+        | Statement::Enum { .. }
+        | Statement::Prototype { .. }
+        | Statement::StaticAssert { .. }
+        | Statement::Break(_)
+        | Statement::Continue(_)
+        | Statement::Goto { .. } => vec![],
+    }
+}
+
+fn dead_code(stmt: &Statement, diagnostics: &mut Vec<Diagnostic>) {
+    if let Statement::Function { body, .. } | Statement::Block(body) = stmt
+        && let Some(i) = body.iter().position(|s| diverges(s, true))
+        // a `case`, `default` or `goto` label can still be jumped to
+        && let Some(unreachable) = body.get(i + 1).filter(|s| {
+            !matches!(
+                s,
+                Statement::Case { .. } | Statement::Default { .. } | Statement::Labeled { .. }
+            )
+        })
+    {
+        // literal-only statements have no line, so fall back to what ended the block
+        let line = unreachable.line().or(body[i].line()).unwrap_or_default();
+        diagnostics.push(Diagnostic::warning(line, "unreachable code"));
+    }
+    for child in children(stmt) {
+        dead_code(child, diagnostics);
+    }
+}
+
+fn loop_control(
+    stmt: &Statement,
+    in_loop: bool,
+    in_switch: bool,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let (in_loop, in_switch) = match stmt {
+        Statement::Break(keyword) if !in_loop && !in_switch => {
+            diagnostics.push(Diagnostic::error(
+                keyword.line,
+                "'break' statement not in loop or switch",
+            ));
+            return;
+        }
+        Statement::Continue(keyword) if !in_loop => {
+            diagnostics.push(Diagnostic::error(
+                keyword.line,
+                "'continue' statement not in loop",
+            ));
+            return;
+        }
+        Statement::Function { .. } => (false, false),
+        Statement::While { .. } | Statement::For { .. } => (true, in_switch),
+        Statement::Switch { .. } => (in_loop, true),
+        _ => (in_loop, in_switch),
+    };
+    for child in children(stmt) {
+        loop_control(child, in_loop, in_switch, diagnostics);
+    }
+}
+
+/// Flags a `return` at file scope. Every function body is in scope of its
+/// function, so only file-scope statements outside of one are searched.
+fn stray_return(stmt: &Statement, diagnostics: &mut Vec<Diagnostic>) {
+    match stmt {
+        Statement::Function { .. } => (),
+        Statement::Return { keyword, .. } => diagnostics.push(Diagnostic::error(
+            keyword.line,
+            "'return' statement not in function",
+        )),
+        _ => {
+            for child in children(stmt) {
+                stray_return(child, diagnostics);
+            }
+        }
+    }
+}
+
+/// Warns about an `if`, `while` or `for` whose condition is a plain `=`
+/// assignment, as `==` was probably meant. An extra pair of parentheses, as
+/// in `if ((x = next()))`, marks the assignment as intended.
+fn assignment_in_condition(stmt: &Statement, diagnostics: &mut Vec<Diagnostic>) {
+    if let Statement::If {
+        keyword, condition, ..
+    }
+    | Statement::While {
+        keyword, condition, ..
+    }
+    | Statement::For {
+        keyword,
+        condition: Some(condition),
+        ..
+    } = stmt
+        && let ExprKind::Assign { operator, .. } = &condition.kind
+        && operator.token_type == TokenType::Equal
+    {
+        diagnostics.push(Diagnostic::warning(
+            condition.line().unwrap_or(keyword.line),
+            "suggest parentheses around assignment used as truth value",
+        ));
+    }
+    for child in children(stmt) {
+        assignment_in_condition(child, diagnostics);
+    }
+}
+
+/// Warns about an expression statement that does nothing, as in `a + b;`,
+/// where an assignment or call was probably meant. A read of any of the
+/// `volatile` names counts as doing something, and a cast to `void` marks a
+/// value as thrown away on purpose. The last statement of a statement
+/// expression is its value, so is left alone.
+fn no_effect(stmt: &Statement, volatile: &HashSet<&str>, diagnostics: &mut Vec<Diagnostic>) {
+    if let Statement::Expression(expr) = stmt
+        && !expr.has_side_effects()
+        && !reads_any(expr, volatile)
+        && !matches!(&expr.kind, ExprKind::Cast { ty, .. } if *ty.unqualified() == Type::Void)
+        // made only of literals, which leaves no line to report
+        && let Some(line) = expr.line()
+    {
+        diagnostics.push(Diagnostic::warning(line, "statement has no effect"));
+    }
+    let (statements, exprs) = ast::statement_children(stmt);
+    for child in statements {
+        no_effect(child, volatile, diagnostics);
+    }
+    for expr in exprs {
+        no_effect_inside(expr, volatile, diagnostics);
+    }
+}
+
+/// [`no_effect`] for the statement expressions inside `expr`
+fn no_effect_inside(expr: &Expr, volatile: &HashSet<&str>, diagnostics: &mut Vec<Diagnostic>) {
+    if let [rest @ .., last] = ast::expr_statements(expr) {
+        for child in rest {
+            no_effect(child, volatile, diagnostics);
+        }
+        if let Statement::Expression(value) = last {
+            no_effect_inside(value, volatile, diagnostics);
+        } else {
+            no_effect(last, volatile, diagnostics);
+        }
+    }
+    for child in ast::expr_children(expr) {
+        no_effect_inside(child, volatile, diagnostics);
+    }
+}
+
+/// Names declared `volatile` anywhere in `program`, as a parameter or
+/// variable in any scope
+fn volatile_names<'a>(program: &[Statement<'a>]) -> HashSet<&'a str> {
+    fn declare<'a>(stmt: &Statement<'a>, names: &mut HashSet<&'a str>) {
+        match stmt {
+            Statement::VarDecl { ty, name, .. } if ty.is_volatile() => {
+                let name: &'a Token<'a> = name;
+                names.insert(&name.literal);
+            }
+            Statement::Function { params, .. } | Statement::Prototype { params, .. } => {
+                for param in params.iter().filter(|p| p.ty.is_volatile()) {
+                    let name: &'a Token<'a> = param.name;
+                    names.insert(&name.literal);
+                }
+            }
+            _ => (),
+        }
+        let (statements, exprs) = ast::statement_children(stmt);
+        for child in statements
+            .into_iter()
+            .chain(exprs.into_iter().flat_map(ast::expr_statements))
+        {
+            declare(child, names);
+        }
+    }
+
+    let mut names = HashSet::new();
+    for stmt in program {
+        declare(stmt, &mut names);
+    }
+    names
+}
+
+/// Whether any identifier in `expr` is one of `names`
+fn reads_any(expr: &Expr, names: &HashSet<&str>) -> bool {
+    match &expr.kind {
+        ExprKind::Identifier(name) => names.contains(&*name.literal),
+        _ => ast::expr_children(expr)
+            .into_iter()
+            .any(|child| reads_any(child, names)),
+    }
+}
+
+fn implicit_int(stmt: &Statement, diagnostics: &mut Vec<Diagnostic>) {
+    match stmt {
+        Statement::Function {
+            name,
+            implicit_int: true,
+            ..
+        } => diagnostics.push(Diagnostic::warning(
+            name.line,
+            format!("return type of '{}' defaults to 'int'", name.literal),
+        )),
+        Statement::VarDecl {
+            name,
+            implicit_int: true,
+            ..
+        } => diagnostics.push(Diagnostic::warning(
+            name.line,
+            format!("type of '{}' defaults to 'int'", name.literal),
+        )),
+        _ => (),
+    }
+    for child in ast::statement_children(stmt).0 {
+        implicit_int(child, diagnostics);
+    }
+}
+
+/// Warns about the locals and parameters of a function that are never read.
+/// Parameters named with a leading `_` are exempt, for functions that have to
+/// match a signature but don't need every argument, and so is anything
+/// `volatile`, since its stores are observable even if it's never read.
+fn unused_variables(stmt: &Statement, diagnostics: &mut Vec<Diagnostic>) {
+    let Statement::Function { params, body, .. } = stmt else {
+        return;
+    };
+
+    let params = params
+        .iter()
+        .map(|p| Local {
+            name: p.name,
+            is_parameter: true,
+            used: p.name.literal.starts_with('_') || p.ty.is_volatile(),
+        })
+        .collect();
+    // the parameters share a scope with the outermost block of the body
+    let mut usage = Usage {
+        scopes: vec![params],
+        diagnostics,
+    };
+    for statement in body {
+        usage.statement(statement);
+    }
+    usage.pop_scope();
+}
+
+/// Variable in scope while looking for unused ones
+struct Local<'a> {
+    name: &'a Token<'a>,
+    is_parameter: bool,
+    used: bool,
+}
+
+/// Tracks which variables in scope have been read
+struct Usage<'d, 'a> {
+    scopes: Vec<Vec<Local<'a>>>,
+    diagnostics: &'d mut Vec<Diagnostic>,
+}
+
+impl<'d, 'a> Usage<'d, 'a> {
+    fn statement(&mut self, stmt: &Statement<'a>) {
+        match stmt {
+            Statement::Expression(expr)
+            | Statement::StaticAssert {
+                condition: expr, ..
+            } => self.expr(expr),
+            Statement::Block(body) => {
+                self.scopes.push(vec![]);
+                for statement in body {
+                    self.statement(statement);
+                }
+                self.pop_scope();
+            }
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.expr(condition);
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            Statement::While {
+                condition, body, ..
+            }
+            | Statement::Switch {
+                condition, body, ..
+            }
+            | Statement::Case {
+                value: condition,
+                body,
+                ..
+            } => {
+                self.expr(condition);
+                self.statement(body);
+            }
+            Statement::For {
+                initialiser,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                self.scopes.push(vec![]);
+                for statement in initialiser {
+                    self.statement(statement);
+                }
+                for expr in condition.iter().chain(increment) {
+                    self.expr(expr);
+                }
+                self.statement(body);
+                self.pop_scope();
+            }
+            Statement::Default { body, .. } | Statement::Labeled { body, .. } => {
+                self.statement(body)
+            }
+            Statement::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            Statement::VarDecl {
+                ty,
+                name,
+                initialiser,
+                ..
+            } => {
+                if let Some(init) = initialiser {
+                    self.initialiser(init);
+                }
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.push(Local {
+                        name,
+                        is_parameter: false,
+                        used: ty.is_volatile(),
+                    });
+                }
+            }
+            Statement::Struct { .. }
+            | Statement::Enum { .. }
+            | Statement::Function { .. }
+            | Statement::Prototype { .. }
+            | Statement::Break(_)
+            | Statement::Continue(_)
+            | Statement::Goto { .. } => (),
+        }
+    }
+
+    fn initialiser(&mut self, init: &Initialiser<'a>) {
+        match init {
+            Initialiser::Expr(expr) => self.expr(expr),
+            Initialiser::List { items, .. } => {
+                for item in items {
+                    if let Some(Designator::Index(index)) = &item.designator {
+                        self.expr(index);
+                    }
+                    self.initialiser(&item.value);
+                }
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr<'a>) {
+        match &expr.kind {
+            ExprKind::Identifier(name) => self.read(&name.literal),
+            // a plain assignment writes its target without reading it
+            ExprKind::Assign {
+                target,
+                operator,
+                value,
+            } => {
+                let writes_only = operator.token_type == TokenType::Equal
+                    && matches!(target.kind, ExprKind::Identifier(_));
+                if !writes_only {
+                    self.expr(target);
+                }
+                self.expr(value);
+            }
+            ExprKind::Binary { left, right, .. } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            ExprKind::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.expr(condition);
+                self.expr(then_branch);
+                self.expr(else_branch);
+            }
+            ExprKind::Unary { right: inner, .. }
+            | ExprKind::Cast { expr: inner, .. }
+            | ExprKind::Member { object: inner, .. }
+            | ExprKind::Grouping(inner) => self.expr(inner),
+            ExprKind::Call {
+                callee, arguments, ..
+            } => {
+                self.expr(callee);
+                for argument in arguments {
+                    self.expr(argument);
+                }
+            }
+            ExprKind::Generic {
+                controlling,
+                assocs,
+                ..
+            } => {
+                self.expr(controlling);
+                for (_, expr) in assocs {
+                    self.expr(expr);
+                }
+            }
+            ExprKind::StmtExpr(body) => {
+                self.scopes.push(vec![]);
+                for statement in body {
+                    self.statement(statement);
+                }
+                self.pop_scope();
+            }
+            ExprKind::Literal(_) | ExprKind::SizeOf { .. } => (),
+        }
+    }
+
+    /// Marks the innermost variable called `name` as used
+    fn read(&mut self, name: &str) {
+        if let Some(local) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.iter_mut().rev().find(|l| l.name.literal == name))
+        {
+            local.used = true;
+        }
+    }
+
+    /// Leaves the innermost scope, warning about anything it declared that
+    /// was never read
+    fn pop_scope(&mut self) {
+        for local in self.scopes.pop().into_iter().flatten() {
+            if local.used {
+                continue;
+            }
+            let kind = if local.is_parameter {
+                "parameter"
+            } else {
+                "variable"
+            };
+            self.diagnostics.push(Diagnostic::warning(
+                local.name.line,
+                format!("unused {} '{}'", kind, local.name.literal),
+            ));
+        }
+    }
+}
+
+/// Folds the enumerators of every `enum` in `stmt`, reporting any whose value
+/// isn't an integer constant, and lays out every structure and union for
+/// `sizeof`. One that can't be laid out is left undefined, so its size isn't
+/// constant. Scopes aren't tracked, so either can be used anywhere after it's
+/// defined.
+fn define_constants<'a>(
+    stmt: &Statement<'a>,
+    target: &TargetConfig,
+    enumerators: &mut Enumerators<'a>,
+    layouts: &mut Layouts<'a>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if let Statement::Struct {
+        name,
+        members,
+        is_union,
+    } = stmt
+    {
+        let _ = layouts.define(&name.literal, members, *is_union, target, enumerators);
+    }
+    if let Statement::Enum {
+        enumerators: constants,
+        ..
+    } = stmt
+    {
+        for (name, _) in enumerators.define(constants, target, layouts) {
+            diagnostics.push(Diagnostic::error(
+                name.line,
+                format!(
+                    "enumerator value for '{}' is not an integer constant",
+                    name.literal
+                ),
+            ));
+        }
+    }
+    for child in children(stmt) {
+        define_constants(child, target, enumerators, layouts, diagnostics);
+    }
+}
+
+/// Folds the condition of every static assertion in `stmt`, reporting its
+/// message if it's zero
+fn static_assertions(
+    stmt: &Statement,
+    target: &TargetConfig,
+    enumerators: &Enumerators,
+    layouts: &Layouts,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if let Statement::StaticAssert {
+        keyword,
+        condition,
+        message,
+    } = stmt
+    {
+        match eval::evaluate_int(condition, target, enumerators, layouts) {
+            Ok(0) => diagnostics.push(Diagnostic::error(
+                keyword.line,
+                format!("static assertion failed: {:?}", message),
+            )),
+            Ok(_) => (),
+            Err(_) => diagnostics.push(Diagnostic::error(
+                keyword.line,
+                "expression in static assertion is not an integer constant expression",
+            )),
+        }
+    }
+    for child in children(stmt) {
+        static_assertions(child, target, enumerators, layouts, diagnostics);
+    }
+}
+
+/// Warns of every `/` or `/=` in `stmt` whose divisor folds to the integer
+/// zero, which is undefined behaviour when reached
+fn division_by_zero(
+    stmt: &Statement,
+    target: &TargetConfig,
+    enumerators: &Enumerators,
+    layouts: &Layouts,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let (statements, exprs) = ast::statement_children(stmt);
+    for expr in exprs {
+        divisions_by_zero(expr, target, enumerators, layouts, diagnostics);
+    }
+    for child in statements {
+        division_by_zero(child, target, enumerators, layouts, diagnostics);
+    }
+}
+
+fn divisions_by_zero(
+    expr: &Expr,
+    target: &TargetConfig,
+    enumerators: &Enumerators,
+    layouts: &Layouts,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if let ExprKind::Binary {
+        operator,
+        right: divisor,
+        ..
+    }
+    | ExprKind::Assign {
+        operator,
+        value: divisor,
+        ..
+    } = &expr.kind
+        && matches!(
+            operator.token_type,
+            TokenType::Slash | TokenType::SlashEqual
+        )
+        && eval::evaluate(divisor, target, enumerators, layouts) == Ok(Value::Int(0))
+    {
+        diagnostics
+            .push(Diagnostic::warning(operator.line, "division by zero").at_col(operator.col));
+    }
+    for child in ast::expr_children(expr) {
+        divisions_by_zero(child, target, enumerators, layouts, diagnostics);
+    }
+    for stmt in ast::expr_statements(expr) {
+        division_by_zero(stmt, target, enumerators, layouts, diagnostics);
+    }
+}
+
+/// Warns about assignments of a variable or member to itself, which do
+/// nothing unless it's `volatile`
+fn self_assignment(stmt: &Statement, volatile: &HashSet<&str>, diagnostics: &mut Vec<Diagnostic>) {
+    let (statements, exprs) = ast::statement_children(stmt);
+    for expr in exprs {
+        self_assignments(expr, volatile, diagnostics);
+    }
+    for child in statements {
+        self_assignment(child, volatile, diagnostics);
+    }
+}
+
+fn self_assignments(expr: &Expr, volatile: &HashSet<&str>, diagnostics: &mut Vec<Diagnostic>) {
+    if let ExprKind::Assign {
+        target,
+        operator,
+        value,
+    } = &expr.kind
+        && operator.token_type == TokenType::Equal
+        && let Some(name) = same_lvalue(target, value)
+        && !reads_any(target, volatile)
+    {
+        diagnostics.push(
+            Diagnostic::warning(operator.line, format!("'{}' is assigned to itself", name))
+                .at_col(operator.col),
+        );
+    }
+    for child in ast::expr_children(expr) {
+        self_assignments(child, volatile, diagnostics);
+    }
+    for stmt in ast::expr_statements(expr) {
+        self_assignment(stmt, volatile, diagnostics);
+    }
+}
+
+/// How `a` is written, if it's the same l-value as `b`: a variable, or a chain
+/// of members of one, ignoring parentheses. Made only of names, it can't have
+/// side effects.
+fn same_lvalue(a: &Expr, b: &Expr) -> Option<String> {
+    match (&a.kind, &b.kind) {
+        (ExprKind::Grouping(a), _) => same_lvalue(a, b),
+        (_, ExprKind::Grouping(b)) => same_lvalue(a, b),
+        (ExprKind::Identifier(a), ExprKind::Identifier(b)) if a.literal == b.literal => {
+            Some(a.literal.to_string())
+        }
+        (
+            ExprKind::Member {
+                object: a_object,
+                operator: a_operator,
+                member: a_member,
+            },
+            ExprKind::Member {
+                object: b_object,
+                operator: b_operator,
+                member: b_member,
+            },
+        ) if a_operator.token_type == b_operator.token_type
+            && a_member.literal == b_member.literal =>
+        {
+            let object = same_lvalue(a_object, b_object)?;
+            Some(format!(
+                "{}{}{}",
+                object, a_operator.token_type, a_member.literal
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Checks every `case` label folds to an integer constant that is unique
+/// within its `switch`
+fn switch_cases(
+    stmt: &Statement,
+    in_switch: bool,
+    target: &TargetConfig,
+    enumerators: &Enumerators,
+    layouts: &Layouts,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match stmt {
+        Statement::Switch { body, .. } => {
+            let mut labels = vec![];
+            collect_cases(body, &mut labels);
+
+            let mut seen = HashMap::new();
+            for (keyword, value) in labels {
+                match eval::evaluate_int(value, target, enumerators, layouts) {
+                    Ok(value) => {
+                        if let Some(first) = seen.insert(value, keyword.line) {
+                            diagnostics.push(Diagnostic::error(
+                                keyword.line,
+                                format!(
+                                    "duplicate case value '{}', previously used on line {}",
+                                    value, first
+                                ),
+                            ));
+                        }
+                    }
+                    Err(_) => diagnostics.push(Diagnostic::error(
+                        keyword.line,
+                        "case label does not reduce to an integer constant",
+                    )),
+                }
+            }
+        }
+        Statement::Case { keyword, .. } | Statement::Default { keyword, .. } if !in_switch => {
+            diagnostics.push(Diagnostic::error(
+                keyword.line,
+                format!("'{}' label not within a switch statement", keyword.literal),
+            ));
+        }
+        _ => (),
+    }
+
+    let in_switch = match stmt {
+        Statement::Function { .. } => false,
+        Statement::Switch { .. } => true,
+        _ => in_switch,
+    };
+    for child in children(stmt) {
+        switch_cases(child, in_switch, target, enumerators, layouts, diagnostics);
+    }
+}
+
+/// Gathers the `case` labels belonging to one switch body, skipping any
+/// nested switches which own their own labels
+fn collect_cases<'s, 'a>(stmt: &'s Statement<'a>, labels: &mut Vec<(&'a Token<'a>, &'s Expr<'a>)>) {
+    if let Statement::Case { keyword, value, .. } = stmt {
+        labels.push((keyword, value));
+    }
+    if matches!(stmt, Statement::Switch { .. }) {
+        return;
+    }
+    for child in children(stmt) {
+        collect_cases(child, labels);
+    }
+}
+
+/// Checks every `goto` in a function jumps to a label defined in that
+/// function, and that no label is defined twice. Labels have function scope,
+/// so it doesn't matter which block either is in.
+fn goto_labels(stmt: &Statement, diagnostics: &mut Vec<Diagnostic>) {
+    let Statement::Function { body, .. } = stmt else {
+        return;
+    };
+
+    let mut labels = vec![];
+    let mut gotos = vec![];
+    for statement in body {
+        collect_labels(statement, &mut labels, &mut gotos);
+    }
+    for (i, label) in labels.iter().enumerate() {
+        if labels[..i].iter().any(|l| l.literal == label.literal) {
+            diagnostics.push(Diagnostic::error(
+                label.line,
+                format!("duplicate label '{}'", label.literal),
+            ));
+        }
+    }
+    for label in gotos {
+        if !labels.iter().any(|l| l.literal == label.literal) {
+            diagnostics.push(Diagnostic::error(
+                label.line,
+                format!("label '{}' not declared", label.literal),
+            ));
+        }
+    }
+}
+
+/// Gathers the labels defined in `stmt` and those its `goto`s jump to
+fn collect_labels<'a>(
+    stmt: &Statement<'a>,
+    labels: &mut Vec<&'a Token<'a>>,
+    gotos: &mut Vec<&'a Token<'a>>,
+) {
+    match stmt {
+        Statement::Labeled { label, .. } => labels.push(label),
+        Statement::Goto { label, .. } => gotos.push(label),
+        _ => (),
+    }
+    for child in children(stmt) {
+        collect_labels(child, labels, gotos);
+    }
+}
+
+/// Definition of each function in `program`, by name. Prototypes don't count,
+/// so a function may be declared any number of times, but defining one again
+/// is an error for each definition after the first.
+pub fn function_table<'s, 'a>(
+    program: &'s [Statement<'a>],
+) -> Result<HashMap<&'a str, &'s Statement<'a>>, Vec<Diagnostic>> {
+    let mut functions = HashMap::new();
+    let mut duplicates = vec![];
+    for stmt in program {
+        let Statement::Function { name, .. } = stmt else {
+            continue;
+        };
+        let name: &'a Token<'a> = name;
+        match functions.entry(&*name.literal) {
+            Entry::Vacant(entry) => {
+                entry.insert(stmt);
+            }
+            Entry::Occupied(entry) => {
+                let Statement::Function { name: first, .. } = entry.get() else {
+                    unreachable!("only functions are inserted");
+                };
+                duplicates.push(Diagnostic::error(
+                    name.line,
+                    format!(
+                        "redefinition of function '{}', first defined on line {}",
+                        name.literal, first.line
+                    ),
+                ));
+            }
+        }
+    }
+    if duplicates.is_empty() {
+        Ok(functions)
+    } else {
+        Err(duplicates)
+    }
+}
+
+/// Callee recorded in the call graph for a call through a function pointer,
+/// which could be to any function
+pub const INDIRECT_CALL: &str = "<indirect>";
+
+/// Functions each function defined in `program` calls, by name. A call to an
+/// identifier, maybe parenthesised, that isn't a parameter or local variable
+/// is to the function of that name, defined or not, and any other call is an
+/// [`INDIRECT_CALL`]. A function defined more than once has the calls of
+/// every definition.
+pub fn call_graph<'a>(program: &[Statement<'a>]) -> HashMap<&'a str, HashSet<&'a str>> {
+    let mut graph: HashMap<&'a str, HashSet<&'a str>> = HashMap::new();
+    for stmt in program {
+        let Statement::Function { name, .. } = stmt else {
+            continue;
+        };
+        let name: &'a Token<'a> = name;
+        let callees = function_calls(stmt).into_iter().map(|callee| match callee {
+            Some(callee) => &*callee.literal,
+            None => INDIRECT_CALL,
+        });
+        graph.entry(&name.literal).or_default().extend(callees);
+    }
+    graph
+}
+
+/// Every call in `function`, as the identifier of the function called by
+/// name, or `None` for an indirect call. See [`call_graph`] for which calls
+/// are by name.
+pub(crate) fn function_calls<'a>(function: &Statement<'a>) -> Vec<Option<&'a Token<'a>>> {
+    let mut locals = HashSet::new();
+    if let Statement::Function { params, .. } = function {
+        for param in params {
+            let name: &'a Token<'a> = param.name;
+            locals.insert(&*name.literal);
+        }
+    }
+    local_names(function, &mut locals);
+    let mut calls = vec![];
+    statement_calls(function, &locals, &mut calls);
+    calls
+}
+
+/// Groups of functions in `graph` that call each other, directly or through
+/// the others, so can recurse: a function that calls itself, or functions
+/// that each reach every other in the group. Each group is its names in
+/// alphabetical order, and groups are in order of their first name. Only calls
+/// to functions defined in the graph count, so an [`INDIRECT_CALL`] never
+/// closes a cycle.
+pub fn call_cycles<'a>(graph: &HashMap<&'a str, HashSet<&'a str>>) -> Vec<Vec<&'a str>> {
+    let mut search = CycleSearch {
+        graph,
+        index: HashMap::new(),
+        low: HashMap::new(),
+        stack: vec![],
+        cycles: vec![],
+    };
+    let mut names: Vec<_> = graph.keys().copied().collect();
+    names.sort();
+    for name in names {
+        if !search.index.contains_key(name) {
+            search.visit(name);
+        }
+    }
+    let mut cycles = search.cycles;
+    for cycle in &mut cycles {
+        cycle.sort();
+    }
+    cycles.sort();
+    cycles
+}
+
+/// Tarjan's search for strongly connected components, keeping those that
+/// contain a cycle
+struct CycleSearch<'g, 'a> {
+    graph: &'g HashMap<&'a str, HashSet<&'a str>>,
+    /// Order each function was first visited in
+    index: HashMap<&'a str, usize>,
+    /// Earliest visited function reachable from each, still on the stack
+    low: HashMap<&'a str, usize>,
+    stack: Vec<&'a str>,
+    cycles: Vec<Vec<&'a str>>,
+}
+
+impl<'a> CycleSearch<'_, 'a> {
+    fn visit(&mut self, name: &'a str) {
+        let index = self.index.len();
+        self.index.insert(name, index);
+        self.low.insert(name, index);
+        self.stack.push(name);
+
+        let calls_itself = self.graph[name].contains(name);
+        for &callee in &self.graph[name] {
+            if !self.graph.contains_key(callee) {
+                continue;
+            }
+            if !self.index.contains_key(callee) {
+                self.visit(callee);
+                let low = self.low[name].min(self.low[callee]);
+                self.low.insert(name, low);
+            } else if self.stack.contains(&callee) {
+                let low = self.low[name].min(self.index[callee]);
+                self.low.insert(name, low);
+            }
+        }
+
+        if self.low[name] == index {
+            let start = self
+                .stack
+                .iter()
+                .rposition(|&n| n == name)
+                .expect("visited function is on the stack");
+            let component = self.stack.split_off(start);
+            if component.len() > 1 || calls_itself {
+                self.cycles.push(component);
+            }
+        }
+    }
+}
+
+/// Warns about each `static` function that no call path reaches from a root:
+/// `main`, or any function without `static`, which could be called from
+/// another translation unit. Any declaration being `static` makes a function
+/// so. A function only used through a pointer counts as unused.
+fn unused_functions(
+    program: &[Statement],
+    graph: &HashMap<&str, HashSet<&str>>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut pending: Vec<_> = graph
+        .keys()
+        .copied()
+        .filter(|&name| name == "main" || !is_static(program, name))
+        .collect();
+    let mut reached: HashSet<_> = pending.iter().copied().collect();
+    while let Some(caller) = pending.pop() {
+        for &callee in graph.get(caller).into_iter().flatten() {
+            if reached.insert(callee) {
+                pending.push(callee);
+            }
+        }
+    }
+    for stmt in program {
+        if let Statement::Function { name, .. } = stmt
+            && !reached.contains(&*name.literal)
+        {
+            diagnostics.push(Diagnostic::warning(
+                name.line,
+                format!("unused function '{}'", name.literal),
+            ));
+        }
+    }
+}
+
+/// Whether any declaration of the function `name` in `program` is `static`
+pub(crate) fn is_static(program: &[Statement], name: &str) -> bool {
+    program.iter().any(|stmt| {
+        matches!(
+            stmt,
+            Statement::Function { name: n, specifiers, .. }
+            | Statement::Prototype { name: n, specifiers, .. }
+                if n.literal == name && specifiers.is_static
+        )
+    })
+}
+
+/// Names of the variables declared anywhere in `stmt`, whatever their scope
+fn local_names<'a>(stmt: &Statement<'a>, names: &mut HashSet<&'a str>) {
+    if let Statement::VarDecl { name, .. } = stmt {
+        let name: &'a Token<'a> = name;
+        names.insert(&name.literal);
+    }
+    let (statements, exprs) = ast::statement_children(stmt);
+    for child in statements
+        .into_iter()
+        .chain(exprs.into_iter().flat_map(ast::expr_statements))
+    {
+        local_names(child, names);
+    }
+}
+
+fn statement_calls<'a>(
+    stmt: &Statement<'a>,
+    locals: &HashSet<&'a str>,
+    callees: &mut Vec<Option<&'a Token<'a>>>,
+) {
+    let (statements, exprs) = ast::statement_children(stmt);
+    for expr in exprs {
+        expr_calls(expr, locals, callees);
+    }
+    for child in statements {
+        statement_calls(child, locals, callees);
+    }
+}
+
+fn expr_calls<'a>(
+    expr: &Expr<'a>,
+    locals: &HashSet<&'a str>,
+    callees: &mut Vec<Option<&'a Token<'a>>>,
+) {
+    if let ExprKind::Call { callee, .. } = &expr.kind {
+        let mut callee = callee.as_ref();
+        while let ExprKind::Grouping(inner) = &callee.kind {
+            callee = inner;
+        }
+        match &callee.kind {
+            ExprKind::Identifier(name) if !locals.contains(&*name.literal) => {
+                callees.push(Some(name))
+            }
+            _ => callees.push(None),
+        }
+    }
+    for child in ast::expr_children(expr) {
+        expr_calls(child, locals, callees);
+    }
+    for stmt in ast::expr_statements(expr) {
+        statement_calls(stmt, locals, callees);
+    }
+}
+
+#[cfg(test)]
+mod analysis_tests {
+    use super::*;
+    use crate::diagnostic::Severity;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn diagnostics(source: &str) -> Vec<Diagnostic> {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        check(&program)
+    }
+
+    #[test]
+    fn format_string_must_match_arguments() {
+        let source = "int printf(const char* format, ...);\nint main(void) {\n  \
+                      char* name = \"x\";\n  printf(\"%s is %d\\n\", name, 3);\n  \
+                      printf(\"%d%%\\n\", name);\n  return 0;\n}";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        assert_eq!(
+            format_warnings(&program),
+            [Diagnostic::warning(
+                5,
+                "format '%d' expects an integer, but argument 2 has type 'char*'"
+            )]
+        );
+        // an optional lint, so not part of the usual checks
+        assert_eq!(check(&program), []);
+    }
+
+    #[test]
+    fn flags_self_assignment() {
+        let source = "struct s { int a; struct s* next; };\nstruct s f(void);\n\
+                      int g(int x, int y, struct s s, volatile int v) {\n  \
+                      x = x;\n  x = y;\n  s.a = (s).a;\n  s.next->a = s.next->a;\n  \
+                      f().a = f().a;\n  v = v;\n  x += x;\n  y = (x = x);\n  return x + y;\n}";
+        let warnings: Vec<_> = diagnostics(source)
+            .into_iter()
+            .filter(|d| d.message.ends_with("is assigned to itself"))
+            .map(|d| (d.line, d.message))
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                (4, "'x' is assigned to itself".to_string()),
+                (6, "'s.a' is assigned to itself".to_string()),
+                (7, "'s.next->a' is assigned to itself".to_string()),
+                (11, "'x' is assigned to itself".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_statements_with_no_effect() {
+        let source = "int f(void);\nint g(int a, int b, volatile int v) {\n  a + b;\n  f();\n  \
+                      a += 1;\n  v;\n  (void)b;\n  a == b;\n  -a;\n  \
+                      return ({ f(); a; b; });\n}";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens)
+            .allow_gnu_extensions(true)
+            .parse()
+            .expect("Should parse");
+        let lines: Vec<_> = check(&program)
+            .iter()
+            .filter(|d| d.message == "statement has no effect")
+            .map(|d| d.line)
+            .collect();
+        // `a;` in the statement expression, but not `b;`, its value
+        assert_eq!(lines, [3, 8, 9, 10]);
+    }
+
+    #[test]
+    fn clean_program_has_no_diagnostics() {
+        let source = "int f(int x) {\n  if (x) { return 1; } else return 2;\n}\n\
+                      int main(void) {\n  while (1) { if (0) break; continue; }\n}";
+        assert_eq!(diagnostics(source), vec![]);
+    }
+
+    #[test]
+    fn noreturn_function_need_not_return() {
+        let source = "void abort(void);\n_Noreturn int fail(void) {\n  abort();\n}";
+        assert_eq!(diagnostics(source), vec![]);
+    }
+
+    #[test]
+    fn diagnostics_are_in_source_order() {
+        // type checking runs first but finds the later problem
+        let source = "int main(void) {\n  int unused;\n  return 1 / 0 + y;\n}";
+        assert_eq!(
+            diagnostics(source),
+            vec![
+                Diagnostic::warning(2, "unused variable 'unused'"),
+                Diagnostic::error(3, "use of undeclared identifier 'y'"),
+                Diagnostic::warning(3, "division by zero").at_col(12),
+            ]
+        );
+
+        let mut found = vec![
+            Diagnostic::warning(1, "b"),
+            Diagnostic::warning(1, "a"),
+            Diagnostic::error(1, "c"),
+        ];
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                Diagnostic::error(1, "c"),
+                Diagnostic::warning(1, "a"),
+                Diagnostic::warning(1, "b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_missing_return() {
+        let found = diagnostics("int f(int x) {\n  if (x) return 1;\n}");
+        assert_eq!(
+            found,
+            vec![Diagnostic::warning(
+                1,
+                "control reaches end of non-void function 'f'"
+            )]
+        );
+    }
+
+    #[test]
+    fn detects_code_after_return() {
+        let found = diagnostics("int main(int x) {\n  return x;\n  x = 1;\n}");
+        assert_eq!(found, vec![Diagnostic::warning(3, "unreachable code")]);
+    }
+
+    #[test]
+    fn detects_break_outside_loop() {
+        let found = diagnostics("int main(void) {\n  while (1) break;\n  continue;\n}");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].severity, Severity::Error);
+        assert_eq!(found[0].line, 3);
+    }
+
+    #[test]
+    fn detects_return_outside_function() {
+        let found = diagnostics("int x;\nreturn 0;\n{ return x; }");
+        assert_eq!(
+            found,
+            vec![
+                Diagnostic::error(2, "'return' statement not in function"),
+                Diagnostic::error(3, "'return' statement not in function"),
+            ]
+        );
+
+        assert_eq!(diagnostics("int main(void) { { return 0; } }"), vec![]);
+    }
+
+    #[test]
+    fn detects_unused_variables() {
+        let source = "int main(int count, int _spare) {\n  int x = 1;\n  int y;\n  y = 2;\n  \
+                      int z;\n  z = 3;\n  return z;\n}";
+        assert_eq!(
+            diagnostics(source),
+            vec![
+                Diagnostic::warning(1, "unused parameter 'count'"),
+                Diagnostic::warning(2, "unused variable 'x'"),
+                Diagnostic::warning(3, "unused variable 'y'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn used_variables_are_not_flagged() {
+        let source = "int main(int n) {\n  int total = 0;\n  \
+                      for (int i = 0; i < n; i += 1) { int x = i; total += x; }\n  \
+                      return total;\n}";
+        assert_eq!(diagnostics(source), vec![]);
+    }
+
+    #[test]
+    fn volatile_store_is_a_use() {
+        let source = "int main(volatile int flag) {\n  volatile int status;\n  \
+                      status = 1;\n  flag = 0;\n  return 0;\n}";
+        assert_eq!(diagnostics(source), vec![]);
+    }
+
+    #[test]
+    fn switch_cases_may_break() {
+        let source = "int main(int x) {\n  switch (x) {\n    case 1 + 1: return 2;\n    case 3:\n    \
+                      default: break;\n  }\n  return 0;\n}";
+        assert_eq!(diagnostics(source), vec![]);
+    }
+
+    #[test]
+    fn detects_duplicate_case_values() {
+        let source = "int main(int x) {\n  switch (x) {\n    case 2: break;\n    case 1 + 1: break;\n  \
+                      }\n  return 0;\n}";
+        assert_eq!(
+            diagnostics(source),
+            vec![Diagnostic::error(
+                4,
+                "duplicate case value '2', previously used on line 3"
+            )]
+        );
+    }
+
+    #[test]
+    fn detects_non_constant_case() {
+        let source = "int main(int x) {\n  switch (x) {\n    case x: break;\n    \
+                      case 1: switch (x) { case 1: break; }\n  }\n  return 0;\n}";
+        // the nested switch owns its own `case 1`, so isn't a duplicate
+        assert_eq!(
+            diagnostics(source),
+            vec![Diagnostic::error(
+                3,
+                "case label does not reduce to an integer constant"
+            )]
+        );
+    }
+
+    #[test]
+    fn enumerators_are_constant_case_labels() {
+        let source = "enum colour { RED, GREEN, BLUE = 1.5 };\n\
+                      int main(int x) {\n  switch (x) {\n    case RED: break;\n    \
+                      case GREEN: break;\n    case 0: break;\n  }\n  return 0;\n}";
+        assert_eq!(
+            diagnostics(source),
+            vec![
+                Diagnostic::error(1, "enumerator value for 'BLUE' is not an integer constant"),
+                Diagnostic::error(6, "duplicate case value '0', previously used on line 4"),
+            ]
+        );
+    }
+
+    #[test]
+    fn goto_to_defined_label() {
+        // the label after the `goto` is reachable, and may be in another block
+        let source = "int main(int x) {\n  if (x) goto end;\n  { goto end; }\nend:\n  return x;\n}";
+        assert_eq!(diagnostics(source), vec![]);
+    }
+
+    #[test]
+    fn detects_goto_to_undeclared_label() {
+        let source = "void f(void) {\n  goto missing;\n}\nvoid g(void) {\nmissing:\n  return;\n}";
+        let found = diagnostics(source);
+        // the label in `g` doesn't count for `f`
+        assert_eq!(
+            found,
+            vec![Diagnostic::error(2, "label 'missing' not declared")]
+        );
+    }
+
+    #[test]
+    fn detects_duplicate_label() {
+        let source =
+            "void f(int x) {\n  {\nagain:\n    if (x) goto again;\n  }\nagain:\n  return;\n}";
+        let found = diagnostics(source);
+        assert_eq!(found, vec![Diagnostic::error(6, "duplicate label 'again'")]);
+    }
+
+    #[test]
+    fn function_table_maps_names_to_definitions() {
+        let source = "int f(void);\nint f(void) { return 1; }\nint g(int x) { return f() + x; }";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let table = function_table(&program).expect("Should have no duplicates");
+
+        let mut names: Vec<_> = table.keys().copied().collect();
+        names.sort();
+        assert_eq!(names, ["f", "g"]);
+        // the definition, not the prototype before it
+        assert!(std::ptr::eq(table["f"], &program[1]));
+        assert!(matches!(table["g"], Statement::Function { params, .. } if params.len() == 1));
+    }
+
+    #[test]
+    fn detects_duplicate_function_definition() {
+        let source = "int f(void) { return 1; }\nint g(void) { return 2; }\n\
+                      int f(void) { return 3; }\nint f(void) { return 4; }";
+        let error = "redefinition of function 'f', first defined on line 1";
+        assert_eq!(
+            diagnostics(source),
+            vec![Diagnostic::error(3, error), Diagnostic::error(4, error)]
+        );
+    }
+
+    #[test]
+    fn call_graph_records_callees() {
+        let source = "int b(int fp) { return fp() + (b)(fp); }\n\
+                      int a(void) { int n = ({ int m = b(1); m; }); return b(n); }\n\
+                      int main(int a) { return a + c(); }\n\
+                      int leaf(void) { return 0; }";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens)
+            .allow_gnu_extensions(true)
+            .parse()
+            .expect("Should parse");
+        let graph = call_graph(&program);
+
+        let callees = |name: &str| {
+            let mut callees: Vec<_> = graph[name].iter().copied().collect();
+            callees.sort();
+            callees
+        };
+        assert_eq!(graph.len(), 4);
+        assert_eq!(callees("b"), [INDIRECT_CALL, "b"]);
+        assert_eq!(callees("a"), ["b"]);
+        // `c` isn't defined, but it's still a call by name
+        assert_eq!(callees("main"), ["c"]);
+        assert_eq!(callees("leaf"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn detects_unused_static_function() {
+        let source = "static int helper(void) { return 1; }\n\
+                      static int used(void) { return 2; }\n\
+                      static int recursive(int n) { return recursive(n); }\n\
+                      int api(void) { return used(); }\n\
+                      int main(void) { return 0; }";
+        assert_eq!(
+            diagnostics(source),
+            vec![
+                Diagnostic::warning(1, "unused function 'helper'"),
+                Diagnostic::warning(3, "unused function 'recursive'"),
+            ]
+        );
+
+        // reached from `main` through another static function
+        let source = "static int leaf(void);\nstatic int mid(void) { return leaf(); }\n\
+                      int leaf(void) { return 1; }\nint main(void) { return mid(); }";
+        assert_eq!(diagnostics(source), vec![]);
+    }
+
+    #[test]
+    fn call_cycles_find_recursion() {
+        let cycles = |source: &str| {
+            let mut lexer = Lexer::from_string(source);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            let program = Parser::new(tokens).parse().expect("Should parse");
+            call_cycles(&call_graph(&program))
+                .into_iter()
+                .map(|cycle| cycle.join(" "))
+                .collect::<Vec<_>>()
+        };
+        // direct recursion
+        assert_eq!(
+            cycles("int f(int n) { return n ? f(n - 1) : 0; }\nint main(void) { return f(3); }"),
+            ["f"]
+        );
+        // mutual recursion, with `c` reached from the cycle but not in it
+        let source = "int c(void) { return 0; }\nint b(void) { return a() + c(); }\n\
+                      int a(void) { return b(); }\nint main(void) { return a(); }";
+        assert_eq!(cycles(source), ["a b"]);
+        // acyclic, even calling the same function twice or an undefined one
+        let source = "int leaf(void) { return 0; }\nint mid(void) { return leaf() + leaf(); }\n\
+                      int main(void) { return mid() + leaf() + g(); }";
+        assert_eq!(cycles(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn detects_implicit_int() {
+        assert_eq!(
+            diagnostics("main() {}"),
+            vec![Diagnostic::warning(
+                1,
+                "return type of 'main' defaults to 'int'"
+            )]
+        );
+        assert_eq!(diagnostics("int main() {}"), vec![]);
+        assert_eq!(
+            diagnostics("static x;\nint main(void) {\n  register r = x;\n  return r;\n}"),
+            vec![
+                Diagnostic::warning(1, "type of 'x' defaults to 'int'"),
+                Diagnostic::warning(3, "type of 'r' defaults to 'int'"),
+            ]
+        );
+        assert_eq!(diagnostics("static int x;"), vec![]);
+    }
+
+    #[test]
+    fn checks_static_assertions() {
+        let source = "enum { N = 4 };\n_Static_assert(N * 2 == 8, \"eight\");\n\
+                      int main(void) {\n  static_assert(N > 4, \"too \" \"small\");\n  return 0;\n}";
+        assert_eq!(
+            diagnostics(source),
+            vec![Diagnostic::error(
+                4,
+                "static assertion failed: \"too small\""
+            )]
+        );
+    }
+
+    #[test]
+    fn static_assertion_needs_constant() {
+        let source = "int main(int x) {\n  _Static_assert(x, \"x\");\n  return 0;\n}";
+        assert_eq!(
+            diagnostics(source),
+            vec![Diagnostic::error(
+                2,
+                "expression in static assertion is not an integer constant expression"
+            )]
+        );
+    }
+
+    #[test]
+    fn static_assertion_takes_sizes() {
+        let source = "struct s { char c; int i; };\n\
+                      _Static_assert(sizeof(struct s) == 8, \"padded\");\n\
+                      _Static_assert(sizeof(char*) == 4, \"pointer\");";
+        assert_eq!(
+            diagnostics(source),
+            vec![Diagnostic::error(3, "static assertion failed: \"pointer\"")]
+        );
+    }
+
+    #[test]
+    fn detects_division_by_zero() {
+        let source = "int main(int x, int y) {\n  x = x / 0;\n  x /= 2 - 2;\n  \
+                      y = x / (2 - 2) + x / y;\n  return x / 0.0 + y;\n}";
+        assert_eq!(
+            diagnostics(source),
+            vec![
+                Diagnostic::warning(2, "division by zero").at_col(9),
+                Diagnostic::warning(3, "division by zero").at_col(5),
+                Diagnostic::warning(4, "division by zero").at_col(9),
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_assignment_in_condition() {
+        let source = "int main(int x) {\n  if (x = 0) return 1;\n  \
+                      while (x = 1) break;\n  for (; x = 2;) break;\n  return x;\n}";
+        let warning = "suggest parentheses around assignment used as truth value";
+        assert_eq!(
+            diagnostics(source),
+            vec![
+                Diagnostic::warning(2, warning),
+                Diagnostic::warning(3, warning),
+                Diagnostic::warning(4, warning),
+            ]
+        );
+    }
+
+    #[test]
+    fn parenthesised_assignment_or_comparison_in_condition() {
+        let source = "int main(int x) {\n  if ((x = 0)) return 1;\n  \
+                      if (x == 0) return 2;\n  return x;\n}";
+        assert_eq!(diagnostics(source), vec![]);
+    }
+}
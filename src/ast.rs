@@ -0,0 +1,224 @@
+//! Measurements of the syntax tree, for metrics and tests
+
+use crate::parser::{Designator, Expr, ExprKind, Initialiser, Statement};
+
+/// Number of statement and expression nodes in `stmt`, including itself
+pub fn count_nodes(stmt: &Statement) -> usize {
+    let (statements, exprs) = statement_children(stmt);
+    1 + statements.into_iter().map(count_nodes).sum::<usize>()
+        + exprs.into_iter().map(count_expr_nodes).sum::<usize>()
+}
+
+/// Number of statement and expression nodes on the longest path from `stmt`
+/// down to a leaf, so a statement with no children has depth 1
+pub fn max_depth(stmt: &Statement) -> usize {
+    let (statements, exprs) = statement_children(stmt);
+    let deepest = statements
+        .into_iter()
+        .map(max_depth)
+        .chain(exprs.into_iter().map(expr_depth))
+        .max();
+    1 + deepest.unwrap_or(0)
+}
+
+fn count_expr_nodes(expr: &Expr) -> usize {
+    1 + expr_children(expr)
+        .into_iter()
+        .map(count_expr_nodes)
+        .sum::<usize>()
+        + expr_statements(expr).iter().map(count_nodes).sum::<usize>()
+}
+
+fn expr_depth(expr: &Expr) -> usize {
+    let deepest = expr_children(expr)
+        .into_iter()
+        .map(expr_depth)
+        .chain(expr_statements(expr).iter().map(max_depth))
+        .max();
+    1 + deepest.unwrap_or(0)
+}
+
+/// Statements and expressions directly nested inside `stmt`
+pub(crate) fn statement_children<'s, 'a>(
+    stmt: &'s Statement<'a>,
+) -> (Vec<&'s Statement<'a>>, Vec<&'s Expr<'a>>) {
+    match stmt {
+        Statement::Expression(expr)
+        | Statement::StaticAssert {
+            condition: expr, ..
+        } => (vec![], vec![expr]),
+        Statement::Function { body, .. } | Statement::Block(body) => {
+            (body.iter().collect(), vec![])
+        }
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => (
+            std::iter::once(then_branch.as_ref())
+                .chain(else_branch.as_deref())
+                .collect(),
+            vec![condition],
+        ),
+        Statement::While {
+            condition, body, ..
+        }
+        | Statement::Switch {
+            condition, body, ..
+        }
+        | Statement::Case {
+            value: condition,
+            body,
+            ..
+        } => (vec![body], vec![condition]),
+        Statement::For {
+            initialiser,
+            condition,
+            increment,
+            body,
+            ..
+        } => (
+            initialiser
+                .iter()
+                .chain(std::iter::once(body.as_ref()))
+                .collect(),
+            condition.iter().chain(increment).collect(),
+        ),
+        Statement::Default { body, .. } | Statement::Labeled { body, .. } => (vec![body], vec![]),
+        Statement::Return { value, .. } => (vec![], value.iter().collect()),
+        Statement::VarDecl { initialiser, .. } => {
+            let mut exprs = vec![];
+            if let Some(init) = initialiser {
+                initialiser_exprs(init, &mut exprs);
+            }
+            (vec![], exprs)
+        }
+        Statement::Enum { enumerators, .. } => (
+            vec![],
+            enumerators
+                .iter()
+                .filter_map(|e| e.value.as_ref())
+                .collect(),
+        ),
+        Statement::Struct { members, .. } => (
+            vec![],
+            members.iter().filter_map(|m| m.width.as_ref()).collect(),
+        ),
+        Statement::Break(_)
+        | Statement::Continue(_)
+        | Statement::Goto { .. }
+        | Statement::Prototype { .. } => (vec![], vec![]),
+    }
+}
+
+/// The expressions of an initialiser, flattening any nested lists
+fn initialiser_exprs<'s, 'a>(init: &'s Initialiser<'a>, exprs: &mut Vec<&'s Expr<'a>>) {
+    match init {
+        Initialiser::Expr(expr) => exprs.push(expr),
+        Initialiser::List { items, .. } => {
+            for item in items {
+                if let Some(Designator::Index(index)) = &item.designator {
+                    exprs.push(index);
+                }
+                initialiser_exprs(&item.value, exprs);
+            }
+        }
+    }
+}
+
+/// Expressions directly nested inside `expr`
+pub(crate) fn expr_children<'e, 'a>(expr: &'e Expr<'a>) -> Vec<&'e Expr<'a>> {
+    match &expr.kind {
+        ExprKind::Binary { left, right, .. }
+        | ExprKind::Assign {
+            target: left,
+            value: right,
+            ..
+        } => vec![left, right],
+        ExprKind::Ternary {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => vec![condition, then_branch, else_branch],
+        ExprKind::Unary { right: inner, .. }
+        | ExprKind::Cast { expr: inner, .. }
+        | ExprKind::Member { object: inner, .. }
+        | ExprKind::Grouping(inner) => vec![inner],
+        ExprKind::Call {
+            callee, arguments, ..
+        } => std::iter::once(callee.as_ref()).chain(arguments).collect(),
+        ExprKind::Generic {
+            controlling,
+            assocs,
+            ..
+        } => std::iter::once(controlling.as_ref())
+            .chain(assocs.iter().map(|(_, expr)| expr))
+            .collect(),
+        ExprKind::Literal(_)
+        | ExprKind::Identifier(_)
+        | ExprKind::SizeOf { .. }
+        | ExprKind::StmtExpr(_) => vec![],
+    }
+}
+
+/// Statements directly nested inside `expr`, only found in a statement
+/// expression
+pub(crate) fn expr_statements<'e, 'a>(expr: &'e Expr<'a>) -> &'e [Statement<'a>] {
+    match &expr.kind {
+        ExprKind::StmtExpr(body) => body,
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod ast_tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    /// Node count and depth of the single declaration in `source`
+    fn measure(source: &str) -> (usize, usize) {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let [stmt] = program.as_slice() else {
+            panic!("Expected a single declaration");
+        };
+        (count_nodes(stmt), max_depth(stmt))
+    }
+
+    #[test]
+    fn measures_function_returning_sum() {
+        // Function > Return > Binary > Literal, plus the other literal
+        assert_eq!(measure("int main(void) { return 1 + 2; }"), (5, 4));
+    }
+
+    #[test]
+    fn measures_branches_and_nested_expressions() {
+        // Function > If > Block > Expression > Assign > Unary > Literal is the
+        // deepest path, alongside the condition, the target `y` and the `return`
+        let source = "void f(int x, int y) { if (x) { y = -1; } else return; }";
+        assert_eq!(measure(source), (10, 7));
+    }
+
+    #[test]
+    fn leaf_statement_has_depth_one() {
+        assert_eq!(measure("int x;"), (1, 1));
+        // the initialiser list itself isn't a node, only its expressions
+        assert_eq!(measure("int a = {1, {2}, [3] = 4};"), (5, 2));
+    }
+
+    #[test]
+    fn measures_inside_statement_expressions() {
+        let mut lexer = Lexer::from_string("int x = ({ int y = 1; y; });");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens)
+            .allow_gnu_extensions(true)
+            .parse()
+            .expect("Should parse");
+        // VarDecl > StmtExpr > VarDecl > Literal, plus the final `y;`
+        assert_eq!((count_nodes(&program[0]), max_depth(&program[0])), (6, 4));
+    }
+}
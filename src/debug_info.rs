@@ -0,0 +1,50 @@
+//! Comments mapping generated code back to the source it came from. There's
+//! no backend to emit assembly yet, so nothing writes these out, but they're
+//! what a `--debug-info` flag would put before each statement's instructions.
+
+use crate::parser::Statement;
+
+/// Comment naming the source line `stmt` starts on, along with the text of that
+/// line, e.g. `# line 5: return a + b;`. `None` when the statement has no line,
+/// as an empty block doesn't.
+pub fn line_comment(stmt: &Statement, source: &str) -> Option<String> {
+    let line = stmt.line()?;
+    let text = source.lines().nth(line.checked_sub(1)?)?;
+    Some(format!("# line {}: {}", line, text.trim()))
+}
+
+#[cfg(test)]
+mod debug_info_tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn each_statement_maps_to_its_line() {
+        let source =
+            "int add(int a, int b) {\n  int sum = a + b;\n  sum = sum * 2;\n\n  return sum;\n}";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let [function @ Statement::Function { body, .. }] = &program[..] else {
+            panic!("Expected a single function");
+        };
+        assert_eq!(
+            line_comment(function, source).as_deref(),
+            Some("# line 1: int add(int a, int b) {")
+        );
+        let comments: Vec<_> = body
+            .iter()
+            .filter_map(|stmt| line_comment(stmt, source))
+            .collect();
+        assert_eq!(
+            comments,
+            [
+                "# line 2: int sum = a + b;",
+                "# line 3: sum = sum * 2;",
+                "# line 5: return sum;",
+            ]
+        );
+        assert_eq!(line_comment(&Statement::Block(vec![]), source), None);
+    }
+}
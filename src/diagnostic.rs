@@ -0,0 +1,169 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::io::IsTerminal;
+
+/// Whether diagnostics are rendered with ANSI colour codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Colour {
+    #[default]
+    Plain,
+    Ansi,
+}
+
+impl Colour {
+    /// Colour for output to `stream`, only if it's a terminal that shows it
+    pub fn detect(stream: &impl IsTerminal) -> Self {
+        if stream.is_terminal() {
+            Colour::Ansi
+        } else {
+            Colour::Plain
+        }
+    }
+
+    /// `text` in the style of the SGR parameters `code`, e.g. `1;31` for bold
+    /// red, or as it is without colour
+    fn paint(self, text: &str, code: &str) -> String {
+        match self {
+            Colour::Plain => text.to_string(),
+            Colour::Ansi => format!("\x1b[{}m{}\x1b[0m", code, text),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// The label, bold and coloured by severity as other compilers do it
+    pub fn render(self, colour: Colour) -> String {
+        let code = match self {
+            Severity::Warning => "1;35",
+            Severity::Error => "1;31",
+        };
+        colour.paint(&self.to_string(), code)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found by one of the analyses, tied to the line it was found on.
+/// Diagnostics order by where they were found, so sorting them puts them in
+/// source order, with an error before a warning at the same place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub line: usize,
+    /// Column on `line`, where the analysis knows it
+    pub col: Option<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            line,
+            col: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            line,
+            col: None,
+            message: message.into(),
+        }
+    }
+
+    /// The same diagnostic pointing at column `col` of its line
+    pub fn at_col(self, col: usize) -> Self {
+        Self {
+            col: Some(col),
+            ..self
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// The diagnostic as it's displayed, after `path` and with its severity
+    /// coloured under `colour`
+    pub fn render(&self, path: &str, colour: Colour) -> String {
+        let position = match self.col {
+            Some(col) => format!("{}:{}", self.line, col),
+            None => self.line.to_string(),
+        };
+        format!(
+            "{}:{}: {}: {}",
+            path,
+            position,
+            self.severity.render(colour),
+            self.message
+        )
+    }
+}
+
+impl Ord for Diagnostic {
+    /// By line then column, one without a column first, then severity and
+    /// message
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.line, self.col)
+            .cmp(&(other.line, other.col))
+            .then_with(|| other.severity.cmp(&self.severity))
+            .then_with(|| self.message.cmp(&other.message))
+    }
+}
+
+impl PartialOrd for Diagnostic {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.col {
+            Some(col) => write!(f, "{}:{}: ", self.line, col)?,
+            None => write!(f, "{}: ", self.line)?,
+        }
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+#[cfg(test)]
+mod diagnostic_tests {
+    use super::*;
+
+    #[test]
+    fn colour_only_when_enabled() {
+        let error = Diagnostic::error(3, "'break' statement not in loop").at_col(5);
+        let warning = Diagnostic::warning(4, "unused variable 'x'");
+
+        let plain = error.render("a.c", Colour::Plain);
+        assert_eq!(plain, format!("a.c:{}", error));
+        assert!(!plain.contains('\x1b'));
+        assert!(!warning.render("a.c", Colour::Plain).contains('\x1b'));
+
+        assert_eq!(
+            error.render("a.c", Colour::Ansi),
+            "a.c:3:5: \x1b[1;31merror\x1b[0m: 'break' statement not in loop"
+        );
+        assert_eq!(
+            warning.render("a.c", Colour::Ansi),
+            "a.c:4: \x1b[1;35mwarning\x1b[0m: unused variable 'x'"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Compiles several source files, each into its own translation unit
+
+use crate::lexer::{Lexer, LexerError};
+use crate::owned::{self, OwnedStatement};
+use crate::parser::{Parser, ParserError};
+use core::fmt;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Error from compiling one of several files, tagged with the file it came
+/// from
+#[derive(Debug)]
+pub enum CompileError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Lex {
+        path: PathBuf,
+        errors: Vec<LexerError>,
+    },
+    Parse {
+        path: PathBuf,
+        error: ParserError,
+    },
+}
+
+impl CompileError {
+    /// The file the error was found in
+    pub fn path(&self) -> &Path {
+        match self {
+            CompileError::Io { path, .. }
+            | CompileError::Lex { path, .. }
+            | CompileError::Parse { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::Io { path, error } => {
+                write!(f, "Couldn't read '{}': {}", path.display(), error)
+            }
+            CompileError::Lex { path, errors } => {
+                let messages: Vec<_> = errors
+                    .iter()
+                    .map(|e| format!("{}: error: {}", path.display(), e))
+                    .collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+            CompileError::Parse { path, error } => {
+                write!(f, "{}: error: {}", path.display(), error)
+            }
+        }
+    }
+}
+
+impl Error for CompileError {}
+
+/// Syntax tree of one source file, owning its tokens so it outlives the
+/// source text
+pub struct TranslationUnit {
+    pub path: PathBuf,
+    pub program: Vec<OwnedStatement>,
+}
+
+/// Lexes and parses each of `paths` into a translation unit, in order,
+/// stopping at the first file with an error
+pub fn compile_files(paths: &[PathBuf]) -> Result<Vec<TranslationUnit>, CompileError> {
+    paths.iter().map(|path| compile_file(path)).collect()
+}
+
+fn compile_file(path: &Path) -> Result<TranslationUnit, CompileError> {
+    let source = fs::read(path).map_err(|error| CompileError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+
+    Ok(TranslationUnit {
+        path: path.to_path_buf(),
+        program: parse_source(&source, path)?,
+    })
+}
+
+/// Lexes and parses `source` into a tree that owns its tokens, tagging any
+/// error with `path`
+fn parse_source(source: &[u8], path: &Path) -> Result<Vec<OwnedStatement>, CompileError> {
+    let mut lexer = Lexer::from_bytes(source);
+    let tokens = lexer.tokenise().map_err(|errors| CompileError::Lex {
+        path: path.to_path_buf(),
+        errors,
+    })?;
+    let program = Parser::new(tokens)
+        .parse()
+        .map_err(|error| CompileError::Parse {
+            path: path.to_path_buf(),
+            error,
+        })?;
+    Ok(owned::to_owned(&program))
+}
+
+/// Syntax tree of a whole program that owns its tokens, so it can be parsed
+/// straight from a string with `source.parse::<Program>()`
+pub struct Program(pub Vec<OwnedStatement>);
+
+/// Path that errors from parsing a `Program` out of a string are tagged with
+const STRING_PATH: &str = "<string>";
+
+impl FromStr for Program {
+    type Err = CompileError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Parser::from_source(source).parse().map(Program)
+    }
+}
+
+/// Lexes and parses a string of source text in one go, for when the tokens
+/// aren't needed. The tree it gives owns its tokens, so nothing has to keep
+/// the lexer or token slice alive. Unlike a [`Parser`] it has no options,
+/// parsing as `Parser::new` does by default; lex the source and use a
+/// `Parser` for any others.
+///
+/// Made by [`Parser::from_source`].
+pub struct SourceParser<'s> {
+    source: &'s str,
+}
+
+impl<'s> SourceParser<'s> {
+    /// Lexes and parses the source into a syntax tree that owns its tokens,
+    /// so it outlives them. Errors are tagged with the path `<string>`.
+    pub fn parse(&self) -> Result<Vec<OwnedStatement>, CompileError> {
+        parse_source(self.source.as_bytes(), Path::new(STRING_PATH))
+    }
+}
+
+// Kept here rather than beside `Parser::new` so that the parser doesn't
+// depend on the driver, which already depends on it
+impl Parser<'_> {
+    /// Lexer and parser for `source` in one, so no tokens need lexing
+    /// beforehand. It's a [`SourceParser`], which parses with the default
+    /// settings, rather than a `Parser` with options to set.
+    pub fn from_source(source: &str) -> SourceParser<'_> {
+        SourceParser { source }
+    }
+}
+
+#[cfg(test)]
+mod driver_tests {
+    use super::*;
+    use crate::parser::Statement;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn compiles_each_file_to_a_unit() {
+        let dir = TempDir::with_files(
+            "units",
+            &[
+                ("a.c", "int f(void) { return 1; }\n"),
+                ("b.c", "int x;\nint main(void) { return x; }\n"),
+            ],
+        );
+        let paths = [dir.join("a.c"), dir.join("b.c")];
+
+        let units = compile_files(&paths).expect("Should compile");
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[0].path, paths[0]);
+        assert_eq!(units[0].program.len(), 1);
+        assert_eq!(units[1].path, paths[1]);
+        assert_eq!(units[1].program.len(), 2);
+    }
+
+    #[test]
+    fn error_names_its_file() {
+        let dir = TempDir::with_files(
+            "unit-error",
+            &[("good.c", "int x;\n"), ("bad.c", "int x\n")],
+        );
+        let paths = [dir.join("good.c"), dir.join("bad.c")];
+
+        let error = compile_files(&paths).err().expect("Should fail");
+        assert!(matches!(error, CompileError::Parse { .. }));
+        assert_eq!(error.path(), paths[1]);
+    }
+
+    #[test]
+    fn parses_program_from_string() {
+        let program: Program = "int main(void){return 0;}".parse().expect("Should parse");
+        assert!(matches!(
+            program.0.as_slice(),
+            [Statement::Function { name, .. }] if name.lexeme() == "main"
+        ));
+
+        let error = "int x".parse::<Program>().err().expect("Should fail");
+        assert!(matches!(error, CompileError::Parse { .. }));
+        assert_eq!(error.path(), Path::new(STRING_PATH));
+    }
+
+    #[test]
+    fn parser_lexes_its_own_source() {
+        let program = {
+            let source = String::from("int x;\nint main(void) { return x; }");
+            Parser::from_source(&source).parse().expect("Should parse")
+        };
+        // the tree owns its tokens, so outlives the source
+        assert!(matches!(
+            program.as_slice(),
+            [Statement::VarDecl { name, .. }, Statement::Function { .. }] if name.lexeme() == "x"
+        ));
+
+        let error = Parser::from_source("int x = @;").parse().err();
+        assert!(matches!(error, Some(CompileError::Lex { .. })));
+    }
+}
@@ -0,0 +1,623 @@
+//! Compile-time evaluation of constant expressions
+
+use crate::layout::Layouts;
+use crate::lexer::{Token, TokenType};
+use crate::parser::{Enumerator, Expr, ExprKind, Literal};
+use crate::target::TargetConfig;
+use crate::types::Type;
+use core::fmt;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::error::Error;
+
+#[derive(Debug, PartialEq)]
+pub enum EvalError {
+    /// The expression refers to something only known at run time
+    NotConstant {
+        line: Option<usize>,
+    },
+    /// The expression is constant but not of the kind needed, e.g. a string,
+    /// or a float where an integer constant is required
+    NotInteger {
+        line: Option<usize>,
+    },
+    DivisionByZero {
+        line: usize,
+    },
+    /// Size or layout needed of a type that has none, such as a structure
+    /// that's only been declared
+    IncompleteType {
+        ty: Type,
+    },
+    NoSuchMember {
+        ty: Type,
+        member: String,
+    },
+    /// Offset asked for of a bit-field, which needn't start on a byte
+    BitFieldOffset {
+        member: String,
+    },
+    /// Bit-field width that's negative or wider than its type
+    InvalidWidth {
+        line: Option<usize>,
+    },
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::NotConstant { .. } => write!(f, "expression is not a compile-time constant"),
+            EvalError::NotInteger { .. } => write!(f, "expression is not an integer constant"),
+            EvalError::DivisionByZero { .. } => {
+                write!(f, "division by zero in constant expression")
+            }
+            EvalError::IncompleteType { ty } => write!(f, "incomplete type '{}'", ty),
+            EvalError::NoSuchMember { ty, member } => {
+                write!(f, "'{}' has no member named '{}'", ty, member)
+            }
+            EvalError::BitFieldOffset { member } => {
+                write!(f, "cannot take the offset of bit-field '{}'", member)
+            }
+            EvalError::InvalidWidth { .. } => write!(f, "invalid bit-field width"),
+        }
+    }
+}
+
+impl Error for EvalError {}
+
+/// Value of a constant arithmetic expression
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+}
+
+impl Value {
+    fn as_float(self) -> f64 {
+        match self {
+            Value::Int(value) => value as f64,
+            Value::Float(value) => value,
+        }
+    }
+
+    fn is_true(self) -> bool {
+        match self {
+            Value::Int(value) => value != 0,
+            Value::Float(value) => value != 0.0,
+        }
+    }
+}
+
+/// Values of the enumeration constants a constant expression can refer to
+#[derive(Debug, Default)]
+pub struct Enumerators<'a> {
+    values: HashMap<&'a str, i64>,
+}
+
+impl<'a> Enumerators<'a> {
+    pub fn get(&self, name: &str) -> Option<i64> {
+        self.values.get(name).copied()
+    }
+
+    /// Adds the constants of one `enum`. Each takes the value it's given, or
+    /// one more than the enumerator before, starting from 0. One whose value
+    /// isn't an integer constant is left out and returned with its error, and
+    /// the count carries on as if it had been given none.
+    pub fn define(
+        &mut self,
+        enumerators: &[Enumerator<'a>],
+        target: &TargetConfig,
+        layouts: &Layouts,
+    ) -> Vec<(&'a Token<'a>, EvalError)> {
+        let mut errors = vec![];
+        let mut next: i64 = 0;
+        for enumerator in enumerators {
+            let value = match &enumerator.value {
+                Some(value) => evaluate_int(value, target, self, layouts),
+                None => Ok(next),
+            };
+            match value {
+                Ok(value) => {
+                    self.values.insert(&enumerator.name.literal, value);
+                    next = value;
+                }
+                Err(error) => errors.push((enumerator.name, error)),
+            }
+            next = target.wrap_int(next.wrapping_add(1));
+        }
+        errors
+    }
+}
+
+/// Offset in bytes of `member` from the start of the structure or union type
+/// `ty`, as `offsetof(ty, member)` gives
+pub fn offset_of(ty: &Type, member: &str, layouts: &Layouts) -> Result<i64, EvalError> {
+    let no_such_member = || EvalError::NoSuchMember {
+        ty: ty.clone(),
+        member: member.to_string(),
+    };
+    let (Type::Struct(tag) | Type::Union(tag)) = ty.unqualified() else {
+        return Err(no_such_member());
+    };
+    let layout = layouts
+        .get(tag)
+        .ok_or_else(|| EvalError::IncompleteType { ty: ty.clone() })?;
+    let found = layout.member(member).ok_or_else(no_such_member)?;
+    if found.width.is_some() {
+        return Err(EvalError::BitFieldOffset {
+            member: member.to_string(),
+        });
+    }
+    Ok((found.bit_offset / 8) as i64)
+}
+
+/// Converts `value` to `ty`, as a cast does. A float becomes an integer by
+/// truncating toward zero, and an integer wraps to the width of its type,
+/// taking the sign from its top bit unless the type is unsigned. `None` for
+/// a type with no constant values, such as a pointer.
+fn convert(value: Value, ty: &Type, target: &TargetConfig) -> Option<Value> {
+    if *ty.unqualified() == Type::Float {
+        return Some(Value::Float(value.as_float() as f32 as f64));
+    }
+    let bits = ty.integer_size(target)? * 8;
+    let value = match value {
+        Value::Int(value) => value,
+        // out of range is undefined, so saturating is as good as anything
+        Value::Float(value) => value.trunc() as i64,
+    };
+    // an `unsigned long` beyond `i64::MAX` stays negative, as it's stored
+    if bits >= u64::from(i64::BITS) {
+        return Some(Value::Int(value));
+    }
+    let shift = i64::BITS - bits as u32;
+    let value = if ty.is_unsigned(target) {
+        ((value as u64) << shift >> shift) as i64
+    } else {
+        (value << shift) >> shift
+    };
+    Some(Value::Int(value))
+}
+
+/// Evaluates an integer constant expression, such as a `case` label
+pub fn evaluate_int(
+    expr: &Expr,
+    target: &TargetConfig,
+    enumerators: &Enumerators,
+    layouts: &Layouts,
+) -> Result<i64, EvalError> {
+    match evaluate(expr, target, enumerators, layouts)? {
+        Value::Int(value) => Ok(value),
+        Value::Float(_) => Err(EvalError::NotInteger { line: expr.line() }),
+    }
+}
+
+/// Evaluates a constant arithmetic expression, which may refer to any of
+/// `enumerators` and take the size of any type in `layouts`. Integer results
+/// wrap to the width of `int` on `target`, except that a cast converts to its
+/// own type, and an operation mixing an integer with a float is done in
+/// floating point.
+pub fn evaluate(
+    expr: &Expr,
+    target: &TargetConfig,
+    enumerators: &Enumerators,
+    layouts: &Layouts,
+) -> Result<Value, EvalError> {
+    match &expr.kind {
+        ExprKind::Literal(Literal::Int(value)) => Ok(Value::Int(*value)),
+        ExprKind::Literal(Literal::Float(value)) => Ok(Value::Float(*value)),
+        ExprKind::Literal(Literal::Str(_)) => Err(EvalError::NotInteger { line: None }),
+        // a variable is read at run time, even a `const` one, and a
+        // `volatile` one has to be read each time it's used
+        ExprKind::Identifier(token) => match enumerators.get(&token.literal) {
+            Some(value) => Ok(Value::Int(value)),
+            None => Err(EvalError::NotConstant {
+                line: Some(token.line),
+            }),
+        },
+        ExprKind::Grouping(inner) => evaluate(inner, target, enumerators, layouts),
+        ExprKind::Member { operator, .. } => Err(EvalError::NotConstant {
+            line: Some(operator.line),
+        }),
+        ExprKind::Call { paren, .. } => Err(EvalError::NotConstant {
+            line: Some(paren.line),
+        }),
+        ExprKind::Assign { operator, .. } => Err(EvalError::NotConstant {
+            line: Some(operator.line),
+        }),
+        // choosing an association needs types, which are only known to the type checker
+        ExprKind::Generic { keyword, .. } => Err(EvalError::NotConstant {
+            line: Some(keyword.line),
+        }),
+        // a `size_t` really, but no size is too big for an `i64`
+        ExprKind::SizeOf { ty, .. } => match layouts.size_align(ty, target) {
+            Some((size, _)) => Ok(Value::Int(size as i64)),
+            None => Err(EvalError::IncompleteType { ty: ty.clone() }),
+        },
+        ExprKind::StmtExpr(_) => Err(EvalError::NotConstant { line: expr.line() }),
+        ExprKind::Cast { paren, ty, expr } => {
+            let value = evaluate(expr, target, enumerators, layouts)?;
+            convert(value, ty, target).ok_or(EvalError::NotConstant {
+                line: Some(paren.line),
+            })
+        }
+        ExprKind::Unary { operator, right } => {
+            let right = evaluate(right, target, enumerators, layouts)?;
+            match (operator.token_type, right) {
+                (TokenType::Minus, Value::Int(value)) => {
+                    Ok(Value::Int(target.wrap_int(value.wrapping_neg())))
+                }
+                (TokenType::Minus, Value::Float(value)) => Ok(Value::Float(-value)),
+                (TokenType::Bang, value) => Ok(Value::Int(!value.is_true() as i64)),
+                _ => Err(EvalError::NotConstant {
+                    line: Some(operator.line),
+                }),
+            }
+        }
+        ExprKind::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let left = evaluate(left, target, enumerators, layouts)?;
+            let right = evaluate(right, target, enumerators, layouts)?;
+            if expr.is_comparison() {
+                return Ok(Value::Int(compare(operator.token_type, left, right) as i64));
+            }
+            match (left, right) {
+                (Value::Int(left), Value::Int(right)) => {
+                    let value = binary(operator, left, right)?;
+                    Ok(Value::Int(target.wrap_int(value)))
+                }
+                (left, right) => float_binary(operator, left.as_float(), right.as_float()),
+            }
+        }
+        ExprKind::Ternary {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            // only the selected branch needs to be constant
+            if evaluate(condition, target, enumerators, layouts)?.is_true() {
+                evaluate(then_branch, target, enumerators, layouts)
+            } else {
+                evaluate(else_branch, target, enumerators, layouts)
+            }
+        }
+    }
+}
+
+fn binary(operator: &Token, left: i64, right: i64) -> Result<i64, EvalError> {
+    let value = match operator.token_type {
+        TokenType::Plus => left.wrapping_add(right),
+        TokenType::Minus => left.wrapping_sub(right),
+        TokenType::Star => left.wrapping_mul(right),
+        TokenType::Slash if right == 0 => {
+            return Err(EvalError::DivisionByZero {
+                line: operator.line,
+            });
+        }
+        TokenType::Slash => left.wrapping_div(right),
+        _ => {
+            return Err(EvalError::NotConstant {
+                line: Some(operator.line),
+            });
+        }
+    };
+    Ok(value)
+}
+
+/// Result of the comparison `operator`, in floating point if either operand
+/// is a float, where a NaN is unequal to everything
+fn compare(operator: TokenType, left: Value, right: Value) -> bool {
+    let ordering = match (left, right) {
+        (Value::Int(left), Value::Int(right)) => Some(left.cmp(&right)),
+        (left, right) => left.as_float().partial_cmp(&right.as_float()),
+    };
+    match operator {
+        TokenType::EqualEqual => ordering == Some(Ordering::Equal),
+        TokenType::BangEqual => ordering != Some(Ordering::Equal),
+        TokenType::Less => ordering == Some(Ordering::Less),
+        TokenType::LessEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        TokenType::Greater => ordering == Some(Ordering::Greater),
+        TokenType::GreaterEqual => {
+            matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+        }
+        _ => unreachable!("not a comparison operator"),
+    }
+}
+
+/// Binary operation in floating point, where division by zero is infinite
+/// rather than an error
+fn float_binary(operator: &Token, left: f64, right: f64) -> Result<Value, EvalError> {
+    let value = match operator.token_type {
+        TokenType::Plus => left + right,
+        TokenType::Minus => left - right,
+        TokenType::Star => left * right,
+        TokenType::Slash => left / right,
+        _ => {
+            return Err(EvalError::NotConstant {
+                line: Some(operator.line),
+            });
+        }
+    };
+    Ok(Value::Float(value))
+}
+
+#[cfg(test)]
+mod eval_tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::{Parser, Statement};
+
+    fn eval_value(expr: &str, target: TargetConfig) -> Result<Value, EvalError> {
+        let mut lexer = Lexer::from_string(expr);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+        evaluate(&expr, &target, &Enumerators::default(), &Layouts::default())
+    }
+
+    fn eval_with(expr: &str, target: TargetConfig) -> Result<i64, EvalError> {
+        match eval_value(expr, target)? {
+            Value::Int(value) => Ok(value),
+            Value::Float(value) => panic!("Expected an integer, got {}", value),
+        }
+    }
+
+    fn eval_source(expr: &str) -> Result<i64, EvalError> {
+        eval_with(expr, TargetConfig::default())
+    }
+
+    #[test]
+    fn evaluates_arithmetic_and_comparisons() {
+        assert_eq!(eval_source("1 + 2 * 3"), Ok(7));
+        assert_eq!(eval_source("(1 + 2) * -3"), Ok(-9));
+        assert_eq!(eval_source("7 / 2 == 3"), Ok(1));
+        assert_eq!(eval_source("!(2 > 1) ? 10 : 20"), Ok(20));
+    }
+
+    #[test]
+    fn rejects_non_constant_operands() {
+        assert_eq!(
+            eval_source("x + 1"),
+            Err(EvalError::NotConstant { line: Some(1) })
+        );
+        assert_eq!(
+            eval_source("1 / 0"),
+            Err(EvalError::DivisionByZero { line: 1 })
+        );
+        assert_eq!(
+            eval_source("\"s\" + 1"),
+            Err(EvalError::NotInteger { line: None })
+        );
+    }
+
+    #[test]
+    fn casts_convert_to_their_type() {
+        assert_eq!(eval_source("(char)300"), Ok(44));
+        assert_eq!(eval_source("(signed char)200"), Ok(-56));
+        assert_eq!(eval_source("(int)3.9"), Ok(3));
+        assert_eq!(eval_source("(int)-3.9"), Ok(-3));
+        assert_eq!(eval_source("(unsigned)-1"), Ok(4294967295));
+        assert_eq!(eval_source("(unsigned char)-1 + 1"), Ok(256));
+        assert_eq!(
+            eval_value("(float)0.1", TargetConfig::default()),
+            Ok(Value::Float(0.1f32 as f64))
+        );
+        // plain `char` is unsigned on some targets
+        let unsigned_char = TargetConfig {
+            char_signed: false,
+            ..TargetConfig::default()
+        };
+        assert_eq!(eval_with("(char)200", unsigned_char), Ok(200));
+        assert_eq!(
+            eval_source("(char*)0"),
+            Err(EvalError::NotConstant { line: Some(1) })
+        );
+    }
+
+    /// Evaluates `expr` with the enumerators of the `enum` in `definition`
+    fn eval_with_enum(definition: &str, expr: &str) -> Result<i64, EvalError> {
+        let source = format!("{}\n{};", definition, expr);
+        let mut lexer = Lexer::from_string(&source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let [
+            Statement::Enum { enumerators, .. },
+            Statement::Expression(expr),
+        ] = &program[..]
+        else {
+            panic!("Expected an enum followed by an expression");
+        };
+
+        let target = TargetConfig::default();
+        let mut constants = Enumerators::default();
+        let layouts = Layouts::default();
+        let errors = constants.define(enumerators, &target, &layouts);
+        assert!(errors.is_empty(), "{:?}", errors);
+        evaluate_int(expr, &target, &constants, &layouts)
+    }
+
+    #[test]
+    fn enumerators_fold_to_their_values() {
+        let colours = "enum { RED, GREEN };";
+        assert_eq!(eval_with_enum(colours, "RED + 1"), Ok(1));
+        assert_eq!(eval_with_enum(colours, "GREEN * 10"), Ok(10));
+
+        // counting carries on from a given value, which may use earlier ones
+        let sizes = "enum size { SMALL = 2, MEDIUM, LARGE = MEDIUM * 2, };";
+        assert_eq!(eval_with_enum(sizes, "MEDIUM"), Ok(3));
+        assert_eq!(eval_with_enum(sizes, "LARGE"), Ok(6));
+        assert_eq!(
+            eval_with_enum(sizes, "HUGE"),
+            Err(EvalError::NotConstant { line: Some(2) })
+        );
+    }
+
+    #[test]
+    fn volatile_read_is_not_folded() {
+        let mut lexer = Lexer::from_string("volatile int v = 1;\nv * 0;");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let [_, Statement::Expression(expr)] = &program[..] else {
+            panic!("Expected a declaration followed by an expression");
+        };
+        assert_eq!(
+            evaluate(
+                expr,
+                &TargetConfig::default(),
+                &Enumerators::default(),
+                &Layouts::default()
+            ),
+            Err(EvalError::NotConstant { line: Some(2) })
+        );
+    }
+
+    #[test]
+    fn folds_float_arithmetic() {
+        let target = TargetConfig::default();
+        assert_eq!(eval_value("3.0 * 2.0", target), Ok(Value::Float(6.0)));
+        // the int operand is promoted
+        assert_eq!(eval_value("1 + 2.5", target), Ok(Value::Float(3.5)));
+        assert_eq!(
+            eval_value("1.0 / 0.0", target),
+            Ok(Value::Float(f64::INFINITY))
+        );
+        assert_eq!(eval_value("-0.5 < 1", target), Ok(Value::Int(1)));
+        assert_eq!(eval_value("!0.0", target), Ok(Value::Int(1)));
+    }
+
+    #[test]
+    fn offset_of_members() {
+        let source = "struct pair { char tag; int value; };\n\
+                      struct flags { int a : 1; int b; };";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let target = TargetConfig::default();
+        let mut layouts = Layouts::default();
+        for stmt in &program {
+            let Statement::Struct {
+                name,
+                members,
+                is_union,
+            } = stmt
+            else {
+                panic!("Expected a struct, got {:?}", stmt);
+            };
+            layouts
+                .define(
+                    &name.literal,
+                    members,
+                    *is_union,
+                    &target,
+                    &Enumerators::default(),
+                )
+                .expect("Should lay out");
+        }
+
+        let pair = Type::Struct("pair".to_string());
+        assert_eq!(offset_of(&pair, "tag", &layouts), Ok(0));
+        assert_eq!(offset_of(&pair, "value", &layouts), Ok(4));
+        let flags = Type::Struct("flags".to_string());
+        assert_eq!(offset_of(&flags, "b", &layouts), Ok(4));
+        assert_eq!(
+            offset_of(&flags, "a", &layouts),
+            Err(EvalError::BitFieldOffset {
+                member: "a".to_string()
+            })
+        );
+        assert_eq!(
+            offset_of(&pair, "missing", &layouts).map_err(|e| e.to_string()),
+            Err("'struct pair' has no member named 'missing'".to_string())
+        );
+        assert_eq!(
+            offset_of(&Type::Struct("other".to_string()), "x", &layouts),
+            Err(EvalError::IncompleteType {
+                ty: Type::Struct("other".to_string())
+            })
+        );
+    }
+
+    #[test]
+    fn wraps_at_target_int_width() {
+        let int32 = TargetConfig::default();
+        assert_eq!(eval_with("2147483647 + 1", int32), Ok(-2147483648));
+        assert_eq!(eval_with("-2147483647 - 2", int32), Ok(2147483647));
+
+        let int16 = TargetConfig {
+            int_bits: 16,
+            ..int32
+        };
+        assert_eq!(eval_with("32767 + 1", int16), Ok(-32768));
+        assert_eq!(eval_with("300 * 300", int16), Ok(24464));
+
+        let int64 = TargetConfig {
+            int_bits: 64,
+            ..int32
+        };
+        assert_eq!(eval_with("2147483647 + 1", int64), Ok(2147483648));
+    }
+
+    #[test]
+    fn sizeof_follows_target() {
+        let int16 = TargetConfig {
+            int_bits: 16,
+            ..TargetConfig::default()
+        };
+        assert_eq!(eval_with("sizeof(int)", int16), Ok(2));
+        assert_eq!(eval_with("sizeof(char)", int16), Ok(1));
+        assert_eq!(eval_with("sizeof(int*)", int16), Ok(8));
+        assert_eq!(eval_with("sizeof(unsigned short)", int16), Ok(2));
+        assert_eq!(eval_with("sizeof(long)", int16), Ok(8));
+        assert_eq!(eval_with("sizeof(const int) * 3", int16), Ok(6));
+        assert_eq!(
+            eval_with("sizeof(void)", int16),
+            Err(EvalError::IncompleteType { ty: Type::Void })
+        );
+    }
+
+    #[test]
+    fn sizeof_structure_uses_its_layout() {
+        let source = "struct s { char c; int i; };\nsizeof(struct s) + sizeof(struct t);";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let [
+            Statement::Struct {
+                name,
+                members,
+                is_union,
+            },
+            Statement::Expression(expr),
+        ] = &program[..]
+        else {
+            panic!("Expected a struct followed by an expression");
+        };
+
+        let target = TargetConfig::default();
+        let enumerators = Enumerators::default();
+        let mut layouts = Layouts::default();
+        let ExprKind::Binary { left, right, .. } = &expr.kind else {
+            panic!("Expected a sum");
+        };
+        assert_eq!(
+            evaluate_int(left, &target, &enumerators, &layouts),
+            Err(EvalError::IncompleteType {
+                ty: Type::Struct("s".to_string())
+            })
+        );
+        layouts
+            .define(&name.literal, members, *is_union, &target, &enumerators)
+            .expect("Should lay out");
+        assert_eq!(evaluate_int(left, &target, &enumerators, &layouts), Ok(8));
+        assert_eq!(
+            evaluate_int(right, &target, &enumerators, &layouts),
+            Err(EvalError::IncompleteType {
+                ty: Type::Struct("t".to_string())
+            })
+        );
+    }
+}
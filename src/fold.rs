@@ -0,0 +1,347 @@
+//! Rewriting of the syntax tree, one node at a time. A pass implements
+//! [`Fold`], overriding the methods for the nodes it changes; the rest are
+//! rebuilt as they were, with their children folded.
+
+use crate::parser::{
+    Designator, Enumerator, Expr, ExprKind, InitItem, Initialiser, Member, Statement,
+};
+
+/// Pass taking the tree apart and building a new one. Each method by default
+/// folds the children of its node with the functions of the same name in this
+/// module, so an override can call those to carry on down the tree before or
+/// after making its own change.
+pub trait Fold<'a> {
+    fn fold_statement(&mut self, stmt: Statement<'a>) -> Statement<'a> {
+        fold_statement(self, stmt)
+    }
+
+    fn fold_expr(&mut self, expr: Expr<'a>) -> Expr<'a> {
+        fold_expr(self, expr)
+    }
+}
+
+/// Folds each top-level declaration of `program`
+pub fn fold_program<'a, F: Fold<'a> + ?Sized>(
+    folder: &mut F,
+    program: Vec<Statement<'a>>,
+) -> Vec<Statement<'a>> {
+    program
+        .into_iter()
+        .map(|stmt| folder.fold_statement(stmt))
+        .collect()
+}
+
+/// Folds a boxed statement in place, reusing its allocation
+fn boxed<'a, F: Fold<'a> + ?Sized>(
+    folder: &mut F,
+    mut stmt: Box<Statement<'a>>,
+) -> Box<Statement<'a>> {
+    *stmt = folder.fold_statement(*stmt);
+    stmt
+}
+
+fn boxed_expr<'a, F: Fold<'a> + ?Sized>(folder: &mut F, mut expr: Box<Expr<'a>>) -> Box<Expr<'a>> {
+    *expr = folder.fold_expr(*expr);
+    expr
+}
+
+/// Rebuilds `stmt` from its children, folded with `folder`
+pub fn fold_statement<'a, F: Fold<'a> + ?Sized>(
+    folder: &mut F,
+    stmt: Statement<'a>,
+) -> Statement<'a> {
+    match stmt {
+        Statement::Expression(expr) => Statement::Expression(folder.fold_expr(expr)),
+        Statement::Block(body) => Statement::Block(fold_program(folder, body)),
+        Statement::If {
+            keyword,
+            condition,
+            then_branch,
+            else_branch,
+        } => Statement::If {
+            keyword,
+            condition: folder.fold_expr(condition),
+            then_branch: boxed(folder, then_branch),
+            else_branch: else_branch.map(|stmt| boxed(folder, stmt)),
+        },
+        Statement::While {
+            keyword,
+            condition,
+            body,
+        } => Statement::While {
+            keyword,
+            condition: folder.fold_expr(condition),
+            body: boxed(folder, body),
+        },
+        Statement::For {
+            keyword,
+            initialiser,
+            condition,
+            increment,
+            body,
+        } => Statement::For {
+            keyword,
+            initialiser: fold_program(folder, initialiser),
+            condition: condition.map(|expr| folder.fold_expr(expr)),
+            increment: increment.map(|expr| folder.fold_expr(expr)),
+            body: boxed(folder, body),
+        },
+        Statement::Switch {
+            keyword,
+            condition,
+            body,
+        } => Statement::Switch {
+            keyword,
+            condition: folder.fold_expr(condition),
+            body: boxed(folder, body),
+        },
+        Statement::Case {
+            keyword,
+            value,
+            body,
+        } => Statement::Case {
+            keyword,
+            value: folder.fold_expr(value),
+            body: boxed(folder, body),
+        },
+        Statement::Default { keyword, body } => Statement::Default {
+            keyword,
+            body: boxed(folder, body),
+        },
+        Statement::Labeled { label, body } => Statement::Labeled {
+            label,
+            body: boxed(folder, body),
+        },
+        Statement::Return { keyword, value } => Statement::Return {
+            keyword,
+            value: value.map(|expr| folder.fold_expr(expr)),
+        },
+        Statement::VarDecl {
+            ty,
+            name,
+            storage,
+            initialiser,
+            implicit_int,
+        } => Statement::VarDecl {
+            ty,
+            name,
+            storage,
+            initialiser: initialiser.map(|init| fold_initialiser(folder, init)),
+            implicit_int,
+        },
+        Statement::Struct {
+            name,
+            members,
+            is_union,
+        } => Statement::Struct {
+            name,
+            is_union,
+            members: members
+                .into_iter()
+                .map(|member| Member {
+                    width: member.width.map(|expr| folder.fold_expr(expr)),
+                    ..member
+                })
+                .collect(),
+        },
+        Statement::Enum {
+            keyword,
+            name,
+            enumerators,
+        } => Statement::Enum {
+            keyword,
+            name,
+            enumerators: enumerators
+                .into_iter()
+                .map(|enumerator| Enumerator {
+                    value: enumerator.value.map(|expr| folder.fold_expr(expr)),
+                    ..enumerator
+                })
+                .collect(),
+        },
+        Statement::StaticAssert {
+            keyword,
+            condition,
+            message,
+        } => Statement::StaticAssert {
+            keyword,
+            condition: folder.fold_expr(condition),
+            message,
+        },
+        Statement::Function {
+            return_type,
+            name,
+            params,
+            body,
+            variadic,
+            has_prototype,
+            implicit_int,
+            specifiers,
+        } => Statement::Function {
+            return_type,
+            name,
+            params,
+            body: fold_program(folder, body),
+            variadic,
+            has_prototype,
+            implicit_int,
+            specifiers,
+        },
+        stmt @ (Statement::Break(_)
+        | Statement::Continue(_)
+        | Statement::Goto { .. }
+        | Statement::Prototype { .. }) => stmt,
+    }
+}
+
+/// Rebuilds an initialiser with each of its expressions folded
+pub fn fold_initialiser<'a, F: Fold<'a> + ?Sized>(
+    folder: &mut F,
+    init: Initialiser<'a>,
+) -> Initialiser<'a> {
+    match init {
+        Initialiser::Expr(expr) => Initialiser::Expr(folder.fold_expr(expr)),
+        Initialiser::List { brace, items } => Initialiser::List {
+            brace,
+            items: items
+                .into_iter()
+                .map(|item| InitItem {
+                    designator: item.designator.map(|designator| match designator {
+                        Designator::Index(index) => Designator::Index(folder.fold_expr(index)),
+                        member => member,
+                    }),
+                    value: fold_initialiser(folder, item.value),
+                })
+                .collect(),
+        },
+    }
+}
+
+/// Rebuilds `expr` from its children, folded with `folder`, keeping its span
+pub fn fold_expr<'a, F: Fold<'a> + ?Sized>(folder: &mut F, expr: Expr<'a>) -> Expr<'a> {
+    let Expr { kind, span } = expr;
+    let kind = match kind {
+        ExprKind::Binary {
+            left,
+            operator,
+            right,
+        } => ExprKind::Binary {
+            left: boxed_expr(folder, left),
+            operator,
+            right: boxed_expr(folder, right),
+        },
+        ExprKind::Unary { operator, right } => ExprKind::Unary {
+            operator,
+            right: boxed_expr(folder, right),
+        },
+        ExprKind::Cast { paren, ty, expr } => ExprKind::Cast {
+            paren,
+            ty,
+            expr: boxed_expr(folder, expr),
+        },
+        ExprKind::Ternary {
+            condition,
+            question,
+            then_branch,
+            else_branch,
+        } => ExprKind::Ternary {
+            condition: boxed_expr(folder, condition),
+            question,
+            then_branch: boxed_expr(folder, then_branch),
+            else_branch: boxed_expr(folder, else_branch),
+        },
+        ExprKind::Member {
+            object,
+            operator,
+            member,
+        } => ExprKind::Member {
+            object: boxed_expr(folder, object),
+            operator,
+            member,
+        },
+        ExprKind::Call {
+            callee,
+            paren,
+            arguments,
+        } => ExprKind::Call {
+            callee: boxed_expr(folder, callee),
+            paren,
+            arguments: arguments
+                .into_iter()
+                .map(|expr| folder.fold_expr(expr))
+                .collect(),
+        },
+        ExprKind::Assign {
+            target,
+            operator,
+            value,
+        } => ExprKind::Assign {
+            target: boxed_expr(folder, target),
+            operator,
+            value: boxed_expr(folder, value),
+        },
+        ExprKind::Generic {
+            keyword,
+            controlling,
+            assocs,
+        } => ExprKind::Generic {
+            keyword,
+            controlling: boxed_expr(folder, controlling),
+            assocs: assocs
+                .into_iter()
+                .map(|(ty, expr)| (ty, folder.fold_expr(expr)))
+                .collect(),
+        },
+        ExprKind::Grouping(inner) => ExprKind::Grouping(boxed_expr(folder, inner)),
+        ExprKind::StmtExpr(body) => ExprKind::StmtExpr(fold_program(folder, body)),
+        kind @ (ExprKind::Literal(_) | ExprKind::Identifier(_) | ExprKind::SizeOf { .. }) => kind,
+    };
+    Expr { kind, span }
+}
+
+#[cfg(test)]
+mod fold_tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::{Literal, Parser};
+
+    /// Replaces every `0` with `1`
+    struct ZeroToOne;
+
+    impl<'a> Fold<'a> for ZeroToOne {
+        fn fold_expr(&mut self, expr: Expr<'a>) -> Expr<'a> {
+            match expr.kind {
+                ExprKind::Literal(Literal::Int(0)) => Expr {
+                    kind: ExprKind::Literal(Literal::Int(1)),
+                    ..expr
+                },
+                _ => fold_expr(self, expr),
+            }
+        }
+    }
+
+    fn hash(source: &str, fold: bool) -> u64 {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let mut program = Parser::new(tokens).parse().expect("Should parse");
+        if fold {
+            program = fold_program(&mut ZeroToOne, program);
+        }
+        let [statement] = program.as_slice() else {
+            panic!("Expected a single declaration");
+        };
+        statement.structural_hash()
+    }
+
+    #[test]
+    fn folder_rewrites_throughout_tree() {
+        let before = "int f(int x) {\n  int a = {0, [0] = 0};\n  \
+                      for (x = 0; x < 0; x = f(0)) if (x ? 0 : -0) return 0;\n  \
+                      switch (x) { case 0: return 2; }\n}";
+        let after = "int f(int x) {\n  int a = {1, [1] = 1};\n  \
+                     for (x = 1; x < 1; x = f(1)) if (x ? 1 : -1) return 1;\n  \
+                     switch (x) { case 1: return 2; }\n}";
+        assert_eq!(hash(before, true), hash(after, false));
+        assert_ne!(hash(before, false), hash(after, false));
+    }
+}
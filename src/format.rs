@@ -0,0 +1,302 @@
+//! Checks of the arguments to `printf` and `scanf` style functions against the
+//! conversions of their format string, which the prototype, ending in `...`,
+//! says nothing about
+
+use crate::types::Type;
+
+/// Which conversions a format function understands, and what they take
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Family {
+    /// `printf`, taking the values to print
+    Print,
+    /// `scanf`, taking pointers to where to store what it reads
+    Scan,
+}
+
+/// Family of the format function `name`, and the index of its format string
+/// among its arguments
+pub fn format_function(name: &str) -> Option<(Family, usize)> {
+    match name {
+        "printf" => Some((Family::Print, 0)),
+        "fprintf" | "sprintf" => Some((Family::Print, 1)),
+        "scanf" => Some((Family::Scan, 0)),
+        _ => None,
+    }
+}
+
+/// Kind of argument a conversion takes. Only the kind is checked, not how
+/// wide a length modifier makes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Argument {
+    /// Any integer, each promoted to at least `int`
+    Integer,
+    /// `double`, which a `float` is promoted to
+    Floating,
+    /// Pointer to a string of characters
+    String,
+    /// Any pointer
+    Pointer,
+    /// Pointer to an integer, to store into
+    IntegerPointer,
+    /// Pointer to a `float` or `double`, to store into
+    FloatingPointer,
+}
+
+impl Argument {
+    /// Whether an argument of type `ty` can be passed for this kind
+    pub fn accepts(self, ty: &Type) -> bool {
+        let pointee = match ty.unqualified() {
+            Type::Pointer(pointee) => Some(pointee.unqualified()),
+            _ => None,
+        };
+        match self {
+            Argument::Integer => ty.is_integer(),
+            Argument::Floating => *ty.unqualified() == Type::Float,
+            Argument::String => matches!(
+                pointee,
+                Some(Type::Char | Type::SignedChar | Type::UnsignedChar)
+            ),
+            Argument::Pointer => pointee.is_some(),
+            Argument::IntegerPointer => pointee.is_some_and(Type::is_integer),
+            Argument::FloatingPointer => pointee == Some(&Type::Float),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Argument::Integer => "an integer",
+            Argument::Floating => "a floating-point number",
+            Argument::String => "a string",
+            Argument::Pointer => "a pointer",
+            Argument::IntegerPointer => "a pointer to an integer",
+            Argument::FloatingPointer => "a pointer to a floating-point number",
+        }
+    }
+}
+
+/// One `%` conversion of a format string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    /// As written, from the `%` to the conversion character, e.g. `%-5ld`
+    pub text: String,
+    /// Arguments it takes, in order: an `int` for each `*` width or precision
+    /// of `printf`, then the value, unless it's `%%` or a `scanf` conversion
+    /// whose assignment is suppressed
+    pub arguments: Vec<Argument>,
+}
+
+/// Conversions of `format` in order, or the text of the first one that isn't
+/// valid for `family`
+pub fn conversions(format: &str, family: Family) -> Result<Vec<Conversion>, String> {
+    let mut conversions = vec![];
+    let mut rest = format;
+    while let Some(start) = rest.find('%') {
+        let spec = &rest[start + 1..];
+        let (length, arguments) = match family {
+            Family::Print => print_conversion(spec),
+            Family::Scan => scan_conversion(spec),
+        };
+        let text = format!("%{}", &spec[..length]);
+        conversions.push(Conversion {
+            arguments: arguments.ok_or_else(|| text.clone())?,
+            text,
+        });
+        rest = &spec[length..];
+    }
+    Ok(conversions)
+}
+
+/// Length of the `printf` conversion `spec` starts with, after its `%`, and
+/// the arguments it takes, or `None` if its conversion character is invalid
+fn print_conversion(spec: &str) -> (usize, Option<Vec<Argument>>) {
+    let mut arguments = vec![];
+    let mut chars = spec.char_indices().peekable();
+    while chars.next_if(|(_, c)| "-+ #0".contains(*c)).is_some() {}
+    // the width, then the precision after a `.`, either of which can be an
+    // `int` argument
+    for precision in [false, true] {
+        if precision && chars.next_if(|&(_, c)| c == '.').is_none() {
+            break;
+        }
+        if chars.next_if(|&(_, c)| c == '*').is_some() {
+            arguments.push(Argument::Integer);
+        }
+        while chars.next_if(|(_, c)| c.is_ascii_digit()).is_some() {}
+    }
+    skip_length_modifier(&mut chars);
+    let Some((i, conversion)) = chars.next() else {
+        return (spec.len(), None);
+    };
+    let argument = match conversion {
+        '%' if i == 0 => return (1, Some(vec![])),
+        'd' | 'i' | 'o' | 'u' | 'x' | 'X' | 'c' => Argument::Integer,
+        'f' | 'F' | 'e' | 'E' | 'g' | 'G' | 'a' | 'A' => Argument::Floating,
+        's' => Argument::String,
+        'p' => Argument::Pointer,
+        'n' => Argument::IntegerPointer,
+        _ => return (i + conversion.len_utf8(), None),
+    };
+    arguments.push(argument);
+    (i + 1, Some(arguments))
+}
+
+/// Length of the `scanf` conversion `spec` starts with, after its `%`, and
+/// the arguments it takes, or `None` if its conversion character is invalid
+fn scan_conversion(spec: &str) -> (usize, Option<Vec<Argument>>) {
+    let mut chars = spec.char_indices().peekable();
+    let suppressed = chars.next_if(|&(_, c)| c == '*').is_some();
+    while chars.next_if(|(_, c)| c.is_ascii_digit()).is_some() {}
+    skip_length_modifier(&mut chars);
+    let Some((i, conversion)) = chars.next() else {
+        return (spec.len(), None);
+    };
+    let argument = match conversion {
+        '%' if i == 0 => return (1, Some(vec![])),
+        'd' | 'i' | 'o' | 'u' | 'x' | 'X' | 'n' => Argument::IntegerPointer,
+        'f' | 'F' | 'e' | 'E' | 'g' | 'G' | 'a' | 'A' => Argument::FloatingPointer,
+        's' | 'c' => Argument::String,
+        'p' => Argument::Pointer,
+        // a scanset, in which a `]` straight after the `[` or `[^` is a member
+        '[' => {
+            chars.next_if(|&(_, c)| c == '^');
+            chars.next_if(|&(_, c)| c == ']');
+            let Some((end, _)) = chars.find(|&(_, c)| c == ']') else {
+                return (spec.len(), None);
+            };
+            let arguments = if suppressed {
+                vec![]
+            } else {
+                vec![Argument::String]
+            };
+            return (end + 1, Some(arguments));
+        }
+        _ => return (i + conversion.len_utf8(), None),
+    };
+    let arguments = if suppressed { vec![] } else { vec![argument] };
+    (i + 1, Some(arguments))
+}
+
+fn skip_length_modifier(chars: &mut std::iter::Peekable<std::str::CharIndices>) {
+    match chars.next_if(|(_, c)| "hljztL".contains(*c)) {
+        Some((_, 'h')) => {
+            chars.next_if(|&(_, c)| c == 'h');
+        }
+        Some((_, 'l')) => {
+            chars.next_if(|&(_, c)| c == 'l');
+        }
+        _ => (),
+    }
+}
+
+/// Problems with a call to a format function of `family` whose format string
+/// is `format`, and whose arguments after it have the types `arguments`. The
+/// first of those is argument number `first`, counting from 1.
+pub fn check_call(format: &str, family: Family, first: usize, arguments: &[Type]) -> Vec<String> {
+    let conversions = match conversions(format, family) {
+        Ok(conversions) => conversions,
+        Err(text) => return vec![format!("invalid conversion '{}' in format string", text)],
+    };
+    let mut problems = vec![];
+    let mut next = 0;
+    for conversion in &conversions {
+        for &expected in &conversion.arguments {
+            let Some(found) = arguments.get(next) else {
+                problems.push(format!(
+                    "format '{}' expects {}, but there are no more arguments",
+                    conversion.text,
+                    expected.describe()
+                ));
+                return problems;
+            };
+            if !expected.accepts(found) {
+                problems.push(format!(
+                    "format '{}' expects {}, but argument {} has type '{}'",
+                    conversion.text,
+                    expected.describe(),
+                    first + next,
+                    found
+                ));
+            }
+            next += 1;
+        }
+    }
+    if next < arguments.len() {
+        problems.push("too many arguments for format string".to_string());
+    }
+    problems
+}
+
+#[cfg(test)]
+mod format_tests {
+    use super::*;
+    use crate::types::Qualifiers;
+
+    fn texts(format: &str, family: Family) -> Result<Vec<String>, String> {
+        let conversions = conversions(format, family)?;
+        Ok(conversions.into_iter().map(|c| c.text).collect())
+    }
+
+    #[test]
+    fn finds_conversions() {
+        assert_eq!(
+            texts("%d%% of %-8.*s, %lld and %#x\n", Family::Print),
+            Ok(vec![
+                "%d".to_string(),
+                "%%".to_string(),
+                "%-8.*s".to_string(),
+                "%lld".to_string(),
+                "%#x".to_string()
+            ])
+        );
+        let [_, _, string, ..] = &conversions("%d%% of %-8.*s", Family::Print).unwrap()[..] else {
+            panic!("Expected three conversions");
+        };
+        assert_eq!(string.arguments, [Argument::Integer, Argument::String]);
+
+        assert_eq!(
+            texts("%*d %3[^]a-z] %hhu", Family::Scan),
+            Ok(vec![
+                "%*d".to_string(),
+                "%3[^]a-z]".to_string(),
+                "%hhu".to_string()
+            ])
+        );
+        assert_eq!(texts("%5q", Family::Print), Err("%5q".to_string()));
+        assert_eq!(texts("trailing %", Family::Print), Err("%".to_string()));
+    }
+
+    #[test]
+    fn checks_argument_kinds() {
+        let string = Type::Pointer(Box::new(Type::Char.qualified(Qualifiers::CONST)));
+        let int_pointer = Type::Pointer(Box::new(Type::Int));
+        assert_eq!(
+            check_call(
+                "%d %s %f",
+                Family::Print,
+                2,
+                &[Type::Char, string.clone(), Type::Float]
+            ),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            check_call("%d\n", Family::Print, 2, &[string]),
+            ["format '%d' expects an integer, but argument 2 has type 'const char*'"]
+        );
+        assert_eq!(
+            check_call("%d %d", Family::Print, 2, &[Type::Int]),
+            ["format '%d' expects an integer, but there are no more arguments"]
+        );
+        assert_eq!(
+            check_call("%d", Family::Print, 2, &[Type::Int, Type::Int]),
+            ["too many arguments for format string"]
+        );
+        assert_eq!(
+            check_call("%d %*d", Family::Scan, 2, &[int_pointer]),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            check_call("%d", Family::Scan, 2, &[Type::Int]),
+            ["format '%d' expects a pointer to an integer, but argument 2 has type 'int'"]
+        );
+    }
+}
@@ -0,0 +1,368 @@
+//! Structural hashing of the syntax tree, so a build tool can tell when a
+//! function has actually changed. Only the shape and contents of the tree are
+//! hashed, never spans or line numbers, so reformatting the source or editing
+//! comments leaves the hash alone.
+
+use crate::lexer::Token;
+use crate::parser::{Designator, Expr, ExprKind, Initialiser, Literal, Parameter, Statement};
+use std::hash::{Hash, Hasher};
+use std::mem::discriminant;
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher` its output is fixed, so hashes can
+/// be stored and compared across runs and compiler versions.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+impl<'a> Expr<'a> {
+    pub fn structural_hash(&self) -> u64 {
+        let mut hasher = Fnv::default();
+        hash_expr(self, &mut hasher);
+        hasher.finish()
+    }
+}
+
+impl<'a> Statement<'a> {
+    pub fn structural_hash(&self) -> u64 {
+        let mut hasher = Fnv::default();
+        hash_statement(self, &mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Hashes what a token means, i.e. its type and value but not its position
+fn hash_token(token: &Token, h: &mut Fnv) {
+    token.hash(h);
+}
+
+fn hash_expr(expr: &Expr, h: &mut Fnv) {
+    discriminant(&expr.kind).hash(h);
+    match &expr.kind {
+        ExprKind::Binary {
+            left,
+            operator,
+            right,
+        }
+        | ExprKind::Assign {
+            target: left,
+            operator,
+            value: right,
+        } => {
+            hash_expr(left, h);
+            hash_token(operator, h);
+            hash_expr(right, h);
+        }
+        ExprKind::Unary { operator, right } => {
+            hash_token(operator, h);
+            hash_expr(right, h);
+        }
+        ExprKind::Ternary {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            hash_expr(condition, h);
+            hash_expr(then_branch, h);
+            hash_expr(else_branch, h);
+        }
+        ExprKind::Member {
+            object,
+            operator,
+            member,
+        } => {
+            hash_expr(object, h);
+            hash_token(operator, h);
+            hash_token(member, h);
+        }
+        ExprKind::Literal(literal) => {
+            discriminant(literal).hash(h);
+            match literal {
+                Literal::Int(value) => value.hash(h),
+                Literal::Float(value) => value.to_bits().hash(h),
+                Literal::Str(value) => value.hash(h),
+            }
+        }
+        ExprKind::Call {
+            callee, arguments, ..
+        } => {
+            hash_expr(callee, h);
+            arguments.len().hash(h);
+            for argument in arguments {
+                hash_expr(argument, h);
+            }
+        }
+        ExprKind::Generic {
+            controlling,
+            assocs,
+            ..
+        } => {
+            hash_expr(controlling, h);
+            assocs.len().hash(h);
+            for (ty, expr) in assocs {
+                ty.hash(h);
+                hash_expr(expr, h);
+            }
+        }
+        ExprKind::SizeOf { ty, .. } => ty.hash(h),
+        ExprKind::Cast { ty, expr, .. } => {
+            ty.hash(h);
+            hash_expr(expr, h);
+        }
+        ExprKind::StmtExpr(body) => hash_body(body, h),
+        ExprKind::Identifier(token) => hash_token(token, h),
+        ExprKind::Grouping(inner) => hash_expr(inner, h),
+    }
+}
+
+fn hash_initialiser(init: &Initialiser, h: &mut Fnv) {
+    discriminant(init).hash(h);
+    match init {
+        Initialiser::Expr(expr) => hash_expr(expr, h),
+        Initialiser::List { items, .. } => {
+            items.len().hash(h);
+            for item in items {
+                discriminant(&item.designator).hash(h);
+                match &item.designator {
+                    Some(Designator::Member(member)) => hash_token(member, h),
+                    Some(Designator::Index(index)) => hash_expr(index, h),
+                    None => (),
+                }
+                hash_initialiser(&item.value, h);
+            }
+        }
+    }
+}
+
+fn hash_body(body: &[Statement], h: &mut Fnv) {
+    body.len().hash(h);
+    for statement in body {
+        hash_statement(statement, h);
+    }
+}
+
+fn hash_statement(stmt: &Statement, h: &mut Fnv) {
+    discriminant(stmt).hash(h);
+    match stmt {
+        Statement::Expression(expr) => hash_expr(expr, h),
+        Statement::Block(body) => hash_body(body, h),
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            hash_expr(condition, h);
+            hash_statement(then_branch, h);
+            else_branch.is_some().hash(h);
+            if let Some(else_branch) = else_branch {
+                hash_statement(else_branch, h);
+            }
+        }
+        Statement::While {
+            condition, body, ..
+        }
+        | Statement::Switch {
+            condition, body, ..
+        }
+        | Statement::Case {
+            value: condition,
+            body,
+            ..
+        } => {
+            hash_expr(condition, h);
+            hash_statement(body, h);
+        }
+        Statement::For {
+            initialiser,
+            condition,
+            increment,
+            body,
+            ..
+        } => {
+            hash_body(initialiser, h);
+            for clause in [condition, increment] {
+                clause.is_some().hash(h);
+                if let Some(expr) = clause {
+                    hash_expr(expr, h);
+                }
+            }
+            hash_statement(body, h);
+        }
+        Statement::Default { body, .. } => hash_statement(body, h),
+        Statement::Labeled { label, body } => {
+            hash_token(label, h);
+            hash_statement(body, h);
+        }
+        Statement::Goto { label, .. } => hash_token(label, h),
+        Statement::StaticAssert {
+            condition, message, ..
+        } => {
+            hash_expr(condition, h);
+            message.hash(h);
+        }
+        Statement::Enum {
+            name, enumerators, ..
+        } => {
+            name.is_some().hash(h);
+            if let Some(name) = name {
+                hash_token(name, h);
+            }
+            enumerators.len().hash(h);
+            for enumerator in enumerators {
+                hash_token(enumerator.name, h);
+                enumerator.value.is_some().hash(h);
+                if let Some(value) = &enumerator.value {
+                    hash_expr(value, h);
+                }
+            }
+        }
+        Statement::Break(_) | Statement::Continue(_) => (),
+        Statement::Return { value, .. } => {
+            value.is_some().hash(h);
+            if let Some(value) = value {
+                hash_expr(value, h);
+            }
+        }
+        Statement::VarDecl {
+            ty,
+            name,
+            storage,
+            initialiser,
+            implicit_int,
+        } => {
+            ty.hash(h);
+            implicit_int.hash(h);
+            hash_token(name, h);
+            storage.hash(h);
+            initialiser.is_some().hash(h);
+            if let Some(init) = initialiser {
+                hash_initialiser(init, h);
+            }
+        }
+        Statement::Struct {
+            name,
+            members,
+            is_union,
+        } => {
+            hash_token(name, h);
+            is_union.hash(h);
+            members.len().hash(h);
+            for member in members {
+                member.ty.hash(h);
+                member.name.is_some().hash(h);
+                if let Some(name) = member.name {
+                    hash_token(name, h);
+                }
+                member.width.is_some().hash(h);
+                if let Some(width) = &member.width {
+                    hash_expr(width, h);
+                }
+            }
+        }
+        Statement::Function {
+            return_type,
+            name,
+            params,
+            body,
+            variadic,
+            has_prototype,
+            implicit_int,
+            specifiers,
+        } => {
+            return_type.hash(h);
+            implicit_int.hash(h);
+            specifiers.hash(h);
+            hash_signature(name, params, *variadic, *has_prototype, h);
+            hash_body(body, h);
+        }
+        Statement::Prototype {
+            return_type,
+            name,
+            params,
+            variadic,
+            has_prototype,
+            specifiers,
+        } => {
+            return_type.hash(h);
+            specifiers.hash(h);
+            hash_signature(name, params, *variadic, *has_prototype, h);
+        }
+    }
+}
+
+/// Hashes a function's name and parameters, shared by definitions and
+/// prototypes
+fn hash_signature(
+    name: &Token,
+    params: &[Parameter<&Token>],
+    variadic: bool,
+    has_prototype: bool,
+    h: &mut Fnv,
+) {
+    hash_token(name, h);
+    params.len().hash(h);
+    for param in params {
+        param.ty.hash(h);
+        hash_token(param.name, h);
+    }
+    variadic.hash(h);
+    has_prototype.hash(h);
+}
+
+#[cfg(test)]
+mod hash_tests {
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn hash_source(source: &str) -> u64 {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        assert_eq!(program.len(), 1);
+        program[0].structural_hash()
+    }
+
+    #[test]
+    fn reformatting_keeps_the_hash() {
+        let compact = hash_source("int f(int a, int b) { if (a) return a - b; return 0; }");
+        let spread = hash_source(
+            "int f(int a,\n      int b)\n{\n    // difference\n    if (a)\n        return a - b;\n\n    return 0;\n}\n",
+        );
+        assert_eq!(compact, spread);
+    }
+
+    #[test]
+    fn reordering_tokens_changes_the_hash() {
+        let original = hash_source("int f(int a, int b) { return a - b; }");
+        assert_ne!(
+            original,
+            hash_source("int f(int a, int b) { return b - a; }")
+        );
+        assert_ne!(
+            original,
+            hash_source("int f(int b, int a) { return a - b; }")
+        );
+        assert_ne!(
+            original,
+            hash_source("int f(int a, int b) { return a + b; }")
+        );
+    }
+}
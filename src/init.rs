@@ -0,0 +1,326 @@
+//! Matching of initialiser lists to the scalars of the object they initialise,
+//! following C's rules for elided braces
+
+use crate::lexer::Token;
+use crate::parser::{Designator, Expr, InitItem, Initialiser};
+use std::fmt;
+
+/// How an object nests, so that an initialiser list written with fewer braces
+/// than the object has levels can still be matched to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitShape {
+    Scalar,
+    /// Array of its elements, or structure of its named members, in order
+    Aggregate(Vec<InitShape>),
+}
+
+impl InitShape {
+    /// Shape of an array of `len` elements of shape `element`
+    pub fn array(len: usize, element: InitShape) -> Self {
+        InitShape::Aggregate(vec![element; len])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum InitError {
+    /// More entries than the object, or a braced scalar, has room for
+    ExcessElements { line: Option<usize> },
+    /// Designator naming no member or element of the object it's in
+    InvalidDesignator { line: Option<usize> },
+    /// Aggregate initialised by a single expression rather than a list
+    NotBraced { line: Option<usize> },
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitError::ExcessElements { .. } => write!(f, "excess elements in initialiser"),
+            InitError::InvalidDesignator { .. } => {
+                write!(f, "designator doesn't name a member or element")
+            }
+            InitError::NotBraced { .. } => {
+                write!(f, "initialiser for an aggregate must be a braced list")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InitError {}
+
+/// Scalar an initialiser sets, given by its position at each level of the
+/// object, outermost first, e.g. `[1, 0]` for `m[1][0]`
+pub type InitPath = Vec<usize>;
+
+/// Scalars `init` sets in an object of `shape`, in the order their entries
+/// appear, so a later entry for the same scalar overrides an earlier one. Any
+/// scalar not listed is zero.
+///
+/// An entry that isn't a braced list, for a position holding an aggregate, has
+/// had the aggregate's braces elided: it and the entries after it initialise
+/// the aggregate's scalars in order, until the aggregate is full or an entry
+/// has a designator. So `{1, 2, 3, 4}`, `{{1, 2}, 3, 4}` and `{{1, 2}, {3, 4}}`
+/// all set the same scalars of an `int[2][2]`.
+///
+/// A designator picks the position `resolve` gives for it, in the aggregate
+/// at the path given, or `None` if it names nothing there.
+pub fn init_scalars<'s, 'a>(
+    init: &'s Initialiser<'a>,
+    shape: &InitShape,
+    resolve: impl FnMut(&[usize], &Designator) -> Option<usize>,
+) -> Result<Vec<(InitPath, &'s Expr<'a>)>, InitError> {
+    let mut matcher = Matcher {
+        scalars: vec![],
+        path: vec![],
+        resolve,
+    };
+    match init {
+        Initialiser::Expr(expr) => match shape {
+            InitShape::Scalar => matcher.scalars.push((vec![], expr)),
+            // an aggregate needs braces, except to be copied, which is for
+            // the type checker to allow
+            InitShape::Aggregate(_) => return Err(InitError::NotBraced { line: expr.line() }),
+        },
+        Initialiser::List { brace, items } => matcher.list(brace, items, shape)?,
+    }
+    Ok(matcher.scalars)
+}
+
+struct Matcher<'s, 'a, R> {
+    scalars: Vec<(InitPath, &'s Expr<'a>)>,
+    /// Positions down to the object being initialised
+    path: InitPath,
+    resolve: R,
+}
+
+impl<'s, 'a, R: FnMut(&[usize], &Designator) -> Option<usize>> Matcher<'s, 'a, R> {
+    /// Initialises the object of `shape` at the current path from the braced
+    /// list `items`, opened by `brace`
+    fn list(
+        &mut self,
+        brace: &Token,
+        items: &'s [InitItem<'a>],
+        shape: &InitShape,
+    ) -> Result<(), InitError> {
+        let InitShape::Aggregate(children) = shape else {
+            // a scalar can be braced too, as in `int x = {1};`
+            return match items {
+                [] => Ok(()),
+                [item] if item.designator.is_none() => self.entry(items, &mut 0, shape),
+                [item] => Err(InitError::InvalidDesignator {
+                    line: item_line(item, brace),
+                }),
+                [_, extra, ..] => Err(InitError::ExcessElements {
+                    line: item_line(extra, brace),
+                }),
+            };
+        };
+
+        let mut next = 0;
+        let mut position = 0;
+        while let Some(item) = items.get(next) {
+            if let Some(designator) = &item.designator {
+                position = (self.resolve)(&self.path, designator)
+                    .filter(|&position| position < children.len())
+                    .ok_or(InitError::InvalidDesignator {
+                        line: item_line(item, brace),
+                    })?;
+            }
+            let Some(child) = children.get(position) else {
+                return Err(InitError::ExcessElements {
+                    line: item_line(item, brace),
+                });
+            };
+            self.path.push(position);
+            self.entry(items, &mut next, child)?;
+            self.path.pop();
+            position += 1;
+        }
+        Ok(())
+    }
+
+    /// Initialises the object of `shape` at the current path from the entry
+    /// `next` of `items`, and the entries after it too if the object's braces
+    /// were elided, moving `next` past them all
+    fn entry(
+        &mut self,
+        items: &'s [InitItem<'a>],
+        next: &mut usize,
+        shape: &InitShape,
+    ) -> Result<(), InitError> {
+        match (&items[*next].value, shape) {
+            (
+                Initialiser::List {
+                    brace,
+                    items: inner,
+                },
+                _,
+            ) => {
+                *next += 1;
+                self.list(brace, inner, shape)
+            }
+            (Initialiser::Expr(expr), InitShape::Scalar) => {
+                *next += 1;
+                self.scalars.push((self.path.clone(), expr));
+                Ok(())
+            }
+            (Initialiser::Expr(_), InitShape::Aggregate(children)) => {
+                for (position, child) in children.iter().enumerate() {
+                    // the first entry's designator, if any, chose this object
+                    let ends = items
+                        .get(*next)
+                        .is_none_or(|item| position > 0 && item.designator.is_some());
+                    if ends {
+                        break;
+                    }
+                    self.path.push(position);
+                    self.entry(items, next, child)?;
+                    self.path.pop();
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Line an entry of the list opened by `brace` starts on, as far as its tokens
+/// tell, or else the brace's line, since a literal keeps no token
+fn item_line(item: &InitItem, brace: &Token) -> Option<usize> {
+    let start = match &item.designator {
+        Some(Designator::Member(member)) => return Some(member.line),
+        Some(Designator::Index(index)) => index,
+        None => match &item.value {
+            Initialiser::Expr(expr) => expr,
+            Initialiser::List { brace, .. } => return Some(brace.line),
+        },
+    };
+    start.line().or(Some(brace.line))
+}
+
+#[cfg(test)]
+mod init_tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::{ExprKind, Literal, Parser, Statement};
+
+    /// Shape of `int[2][2]`
+    fn matrix() -> InitShape {
+        InitShape::array(2, InitShape::array(2, InitShape::Scalar))
+    }
+
+    /// Scalars the initialiser of the declaration `source` sets in an object
+    /// of `shape`, with their values, which are integer literals
+    fn scalars(source: &str, shape: &InitShape) -> Result<Vec<(InitPath, i64)>, InitError> {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let [
+            Statement::VarDecl {
+                initialiser: Some(init),
+                ..
+            },
+        ] = program.as_slice()
+        else {
+            panic!("Expected a declaration with an initialiser");
+        };
+        let scalars = init_scalars(init, shape, |_, designator| designator.literal_index())?;
+        Ok(scalars
+            .into_iter()
+            .map(|(path, expr)| match expr.kind {
+                ExprKind::Literal(Literal::Int(value)) => (path, value),
+                _ => panic!("Expected an integer literal"),
+            })
+            .collect())
+    }
+
+    #[test]
+    fn elided_braces_set_the_same_scalars() {
+        let expected = vec![
+            (vec![0, 0], 1),
+            (vec![0, 1], 2),
+            (vec![1, 0], 3),
+            (vec![1, 1], 4),
+        ];
+        for source in [
+            "int m = {{1, 2}, {3, 4}};",
+            "int m = {{1, 2}, 3, 4};",
+            "int m = {1, 2, {3, 4}};",
+            "int m = {1, 2, 3, 4};",
+        ] {
+            assert_eq!(scalars(source, &matrix()), Ok(expected.clone()), "{source}");
+        }
+        // an inner list that stops early leaves the rest of its row zero
+        assert_eq!(
+            scalars("int m = {{1}, 3, 4};", &matrix()),
+            Ok(vec![(vec![0, 0], 1), (vec![1, 0], 3), (vec![1, 1], 4)])
+        );
+    }
+
+    #[test]
+    fn elision_follows_nested_structures() {
+        // struct { int a; struct { int b; int c; } in; int d; }
+        let shape = InitShape::Aggregate(vec![
+            InitShape::Scalar,
+            InitShape::array(2, InitShape::Scalar),
+            InitShape::Scalar,
+        ]);
+        assert_eq!(
+            scalars("int s = {1, 2, 3, 4};", &shape),
+            Ok(vec![
+                (vec![0], 1),
+                (vec![1, 0], 2),
+                (vec![1, 1], 3),
+                (vec![2], 4)
+            ])
+        );
+        assert_eq!(
+            scalars("int s = {1, {2}, 4};", &shape),
+            Ok(vec![(vec![0], 1), (vec![1, 0], 2), (vec![2], 4)])
+        );
+    }
+
+    #[test]
+    fn designator_ends_elided_aggregate() {
+        assert_eq!(
+            scalars("int m = {1, [1] = 3, 4};", &matrix()),
+            Ok(vec![(vec![0, 0], 1), (vec![1, 0], 3), (vec![1, 1], 4)])
+        );
+        assert_eq!(
+            scalars("int m = {[2] = 1};", &matrix()),
+            Err(InitError::InvalidDesignator { line: Some(1) })
+        );
+    }
+
+    #[test]
+    fn rejects_excess_elements() {
+        assert_eq!(
+            scalars("int m = {1, 2, 3, 4, 5};", &matrix()),
+            Err(InitError::ExcessElements { line: Some(1) })
+        );
+        assert_eq!(
+            scalars("int m = {{1, 2, 3}};", &matrix()),
+            Err(InitError::ExcessElements { line: Some(1) })
+        );
+        assert_eq!(
+            scalars("int x = {1, 2};", &InitShape::Scalar),
+            Err(InitError::ExcessElements { line: Some(1) })
+        );
+        // a list is found by its brace, and a literal, which has no line, by
+        // the brace of the list it's in
+        assert_eq!(
+            scalars("int m = {\n  {1, 2},\n  {3, 4},\n  {5}\n};", &matrix()),
+            Err(InitError::ExcessElements { line: Some(4) })
+        );
+        assert_eq!(
+            scalars("int m = {{1, 2},\n  {3, 4,\n   5}};", &matrix()),
+            Err(InitError::ExcessElements { line: Some(2) })
+        );
+        assert_eq!(
+            scalars("int x = {7};", &InitShape::Scalar),
+            Ok(vec![(vec![], 7)])
+        );
+        assert_eq!(
+            scalars("int m = 1;", &matrix()),
+            Err(InitError::NotBraced { line: None })
+        );
+    }
+}
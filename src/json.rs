@@ -0,0 +1,240 @@
+//! The syntax tree as JSON, for editors and other tools. Each node is an
+//! object with the same name and details the tree printer shows, the line it
+//! starts on, and its children:
+//!
+//! ```json
+//! {"node": "Return", "detail": "", "line": 3, "expressions": [...], "statements": []}
+//! ```
+//!
+//! The expressions of an initialiser list are given in order, without the
+//! lists they're nested in.
+
+use crate::ast;
+use crate::parser::{Expr, ExprKind, Statement};
+use std::fmt::Write;
+
+/// A JSON value, only as much of it as the syntax tree needs
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    /// Members in the order they're written
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    /// All on one line, with no space between tokens
+    pub fn compact(&self) -> String {
+        let mut output = String::new();
+        self.write(&mut output, None);
+        output
+    }
+
+    /// Each member and element on a line of its own, indented by two spaces
+    /// per level of nesting
+    pub fn pretty(&self) -> String {
+        let mut output = String::new();
+        self.write(&mut output, Some(0));
+        output
+    }
+
+    /// Writes the value to `output`, at the indentation `depth` if pretty
+    fn write(&self, output: &mut String, depth: Option<usize>) {
+        match self {
+            Json::Null => output.push_str("null"),
+            Json::Number(number) => write!(output, "{}", number).unwrap(),
+            Json::String(text) => output.push_str(&quote(text)),
+            Json::Array(elements) => write_nested(
+                output,
+                depth,
+                ('[', ']'),
+                elements,
+                |element, output, depth| element.write(output, depth),
+            ),
+            Json::Object(members) => write_nested(
+                output,
+                depth,
+                ('{', '}'),
+                members,
+                |(name, value), output, depth| {
+                    output.push_str(&quote(name));
+                    output.push(':');
+                    if depth.is_some() {
+                        output.push(' ');
+                    }
+                    value.write(output, depth);
+                },
+            ),
+        }
+    }
+}
+
+/// Writes `items` between `brackets`, separated by commas, on lines of their
+/// own one level in from `depth` if pretty
+fn write_nested<T>(
+    output: &mut String,
+    depth: Option<usize>,
+    (open, close): (char, char),
+    items: &[T],
+    mut write_item: impl FnMut(&T, &mut String, Option<usize>),
+) {
+    output.push(open);
+    let inner = depth.map(|depth| depth + 1);
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            output.push(',');
+        }
+        if let Some(inner) = inner {
+            write!(output, "\n{}", "  ".repeat(inner)).unwrap();
+        }
+        write_item(item, output, inner);
+    }
+    if let Some(depth) = depth
+        && !items.is_empty()
+    {
+        write!(output, "\n{}", "  ".repeat(depth)).unwrap();
+    }
+    output.push(close);
+}
+
+/// `text` as a JSON string, in quotes and with the characters JSON doesn't
+/// allow escaped
+pub fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The statements of `program` as a JSON array
+pub fn program(program: &[Statement]) -> Json {
+    Json::Array(program.iter().map(statement).collect())
+}
+
+pub fn statement(stmt: &Statement) -> Json {
+    let (statements, exprs) = ast::statement_children(stmt);
+    node(
+        stmt.node_label(),
+        stmt.line(),
+        exprs.into_iter().map(expr).collect(),
+        statements.into_iter().map(statement).collect(),
+    )
+}
+
+pub fn expr(expr: &Expr) -> Json {
+    let label = match &expr.kind {
+        // the printer shows the whole token, where the name is all that's useful
+        ExprKind::Identifier(name) => ("Identifier", name.literal.to_string()),
+        _ => expr.node_label(),
+    };
+    node(
+        label,
+        expr.line(),
+        ast::expr_children(expr)
+            .into_iter()
+            .map(self::expr)
+            .collect(),
+        ast::expr_statements(expr).iter().map(statement).collect(),
+    )
+}
+
+fn node(
+    (name, detail): (&str, String),
+    line: Option<usize>,
+    expressions: Vec<Json>,
+    statements: Vec<Json>,
+) -> Json {
+    Json::Object(vec![
+        ("node", Json::String(name.to_string())),
+        ("detail", Json::String(detail)),
+        (
+            "line",
+            line.map_or(Json::Null, |line| Json::Number(line as u64)),
+        ),
+        ("expressions", Json::Array(expressions)),
+        ("statements", Json::Array(statements)),
+    ])
+}
+
+impl<'a> Expr<'a> {
+    pub fn to_json(&self) -> String {
+        expr(self).compact()
+    }
+
+    pub fn to_json_pretty(&self) -> String {
+        expr(self).pretty()
+    }
+}
+
+impl<'a> Statement<'a> {
+    pub fn to_json(&self) -> String {
+        statement(self).compact()
+    }
+
+    pub fn to_json_pretty(&self) -> String {
+        statement(self).pretty()
+    }
+}
+
+#[cfg(test)]
+mod json_tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn writes_compact_and_pretty() {
+        let value = Json::Object(vec![
+            ("name", Json::String("a \"b\"\n".to_string())),
+            ("list", Json::Array(vec![Json::Number(1), Json::Null])),
+            ("empty", Json::Array(vec![])),
+        ]);
+        assert_eq!(
+            value.compact(),
+            r#"{"name":"a \"b\"\n","list":[1,null],"empty":[]}"#
+        );
+        assert_eq!(
+            value.pretty(),
+            "{\n  \"name\": \"a \\\"b\\\"\\n\",\n  \"list\": [\n    1,\n    null\n  ],\n  \
+             \"empty\": []\n}"
+        );
+    }
+
+    #[test]
+    fn nodes_nest_their_children() {
+        let mut lexer = Lexer::from_string("int f(void) {\n  return -x;\n}");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let leaf = |node: &str, detail: &str, line: &str| {
+            format!(
+                r#"{{"node":"{node}","detail":"{detail}","line":{line},"expressions":[],"statements":[]}}"#
+            )
+        };
+        let negate = format!(
+            r#"{{"node":"Unary","detail":"Minus","line":2,"expressions":[{}],"statements":[]}}"#,
+            leaf("Identifier", "x", "2")
+        );
+        let ret = format!(
+            r#"{{"node":"Return","detail":"","line":2,"expressions":[{negate}],"statements":[]}}"#
+        );
+        assert_eq!(
+            program[0].to_json(),
+            format!(
+                r#"{{"node":"Function","detail":"int f()","line":1,"expressions":[],"statements":[{ret}]}}"#
+            )
+        );
+    }
+}
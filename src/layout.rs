@@ -0,0 +1,278 @@
+//! Sizes, alignments and member offsets of types, laid out as the System V
+//! x86-64 ABI does
+
+use crate::eval::{self, Enumerators, EvalError};
+use crate::parser::Member;
+use crate::target::TargetConfig;
+use crate::types::Type;
+use std::collections::HashMap;
+
+/// Where a structure or union's members go, and how much space it takes up
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructLayout<'a> {
+    /// Size in bytes, including any padding at the end
+    pub size: u64,
+    /// Alignment in bytes, that of its most aligned member
+    pub align: u64,
+    pub members: Vec<MemberLayout<'a>>,
+}
+
+/// Position of one structure member
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberLayout<'a> {
+    /// Left out for an unnamed bit-field
+    pub name: Option<&'a str>,
+    pub ty: Type,
+    /// Offset of the member's first bit from the start of the structure,
+    /// only a whole number of bytes for a member that isn't a bit-field
+    pub bit_offset: u64,
+    /// Width of a bit-field
+    pub width: Option<u64>,
+}
+
+impl<'a> StructLayout<'a> {
+    pub fn member(&self, name: &str) -> Option<&MemberLayout<'a>> {
+        self.members.iter().find(|m| m.name == Some(name))
+    }
+}
+
+/// Layouts of the structures and unions defined so far, by tag
+#[derive(Debug, Default)]
+pub struct Layouts<'a> {
+    structs: HashMap<&'a str, StructLayout<'a>>,
+}
+
+impl<'a> Layouts<'a> {
+    pub fn get(&self, tag: &str) -> Option<&StructLayout<'a>> {
+        self.structs.get(tag)
+    }
+
+    /// Size and alignment in bytes of `ty` on `target`, or `None` for `void`,
+    /// arrays of unknown length and structures not yet defined
+    pub fn size_align(&self, ty: &Type, target: &TargetConfig) -> Option<(u64, u64)> {
+        let size = match ty.unqualified() {
+            Type::Void => return None,
+            Type::Float => 4,
+            Type::Pointer(_) => target.pointer_size(),
+            Type::Array(element, length) => {
+                let (size, align) = self.size_align(element, target)?;
+                return Some((size * (*length)?, align));
+            }
+            Type::Struct(tag) | Type::Union(tag) => {
+                let layout = self.get(tag)?;
+                return Some((layout.size, layout.align));
+            }
+            Type::Qualified(..) => unreachable!("unqualified type is never qualified"),
+            integer => integer.integer_size(target)?,
+        };
+        Some((size, size))
+    }
+
+    /// Lays out the structure `tag` with `members`, in order. Each member goes
+    /// at the next offset aligned for its type, except that a bit-field shares
+    /// the storage of its type with the bit-fields before it, as long as it
+    /// fits. A zero-width bit-field moves on to the next storage unit, and an
+    /// unnamed one doesn't align the structure.
+    ///
+    /// Every member of a union goes at its start instead, so it's as big as
+    /// its biggest member, padded to its alignment.
+    pub fn define(
+        &mut self,
+        tag: &'a str,
+        members: &[Member<'a>],
+        is_union: bool,
+        target: &TargetConfig,
+        enumerators: &Enumerators,
+    ) -> Result<&StructLayout<'a>, EvalError> {
+        let mut bit = 0;
+        let mut end = 0;
+        let mut align = 1;
+        let mut layouts = vec![];
+        for member in members {
+            if is_union {
+                bit = 0;
+            }
+            let (size, member_align) =
+                self.size_align(&member.ty, target)
+                    .ok_or_else(|| EvalError::IncompleteType {
+                        ty: member.ty.clone(),
+                    })?;
+            let unit = size * 8;
+            let width = match &member.width {
+                Some(width) => {
+                    let value = eval::evaluate_int(width, target, enumerators, self)?;
+                    match u64::try_from(value) {
+                        Ok(value) if value <= unit => Some(value),
+                        _ => return Err(EvalError::InvalidWidth { line: width.line() }),
+                    }
+                }
+                None => None,
+            };
+            match width {
+                Some(width) if bit % unit + width > unit || width == 0 => {
+                    bit = bit.next_multiple_of(unit);
+                }
+                Some(_) => (),
+                None => bit = bit.next_multiple_of(member_align * 8),
+            }
+            if member.name.is_some() {
+                align = align.max(member_align);
+            }
+            if width == Some(0) {
+                continue;
+            }
+            layouts.push(MemberLayout {
+                name: member.name.map(|name| &*name.literal),
+                ty: member.ty.clone(),
+                bit_offset: bit,
+                width,
+            });
+            bit += width.unwrap_or(unit);
+            end = end.max(bit);
+        }
+
+        let layout = StructLayout {
+            size: end.div_ceil(8).next_multiple_of(align),
+            align,
+            members: layouts,
+        };
+        self.structs.insert(tag, layout);
+        Ok(&self.structs[tag])
+    }
+}
+
+#[cfg(test)]
+mod layout_tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::{Parser, Statement};
+
+    /// Size, alignment and member offsets in bits of the last structure in
+    /// `source`
+    fn layout(source: &str) -> (u64, u64, Vec<u64>) {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let target = TargetConfig::default();
+        let mut layouts = Layouts::default();
+        let mut last = None;
+        for stmt in &program {
+            if let Statement::Struct {
+                name,
+                members,
+                is_union,
+            } = stmt
+            {
+                layouts
+                    .define(
+                        &name.literal,
+                        members,
+                        *is_union,
+                        &target,
+                        &Enumerators::default(),
+                    )
+                    .expect("Should lay out");
+                last = Some(&*name.literal);
+            }
+        }
+        let layout = &layouts.get(last.expect("Expected a struct")).unwrap();
+        let offsets = layout.members.iter().map(|m| m.bit_offset).collect();
+        (layout.size, layout.align, offsets)
+    }
+
+    #[test]
+    fn members_are_aligned_and_padded() {
+        assert_eq!(layout("struct s { char c; int i; };"), (8, 4, vec![0, 32]));
+        assert_eq!(layout("struct s { int i; char c; };"), (8, 4, vec![0, 32]));
+        assert_eq!(
+            layout("struct s { char c; char* p; float f; };"),
+            (24, 8, vec![0, 64, 128])
+        );
+        // a nested structure is aligned as its most aligned member
+        let nested = "struct in { char c; int i; };\nstruct out { char c; struct in in; };";
+        assert_eq!(layout(nested), (12, 4, vec![0, 32]));
+        assert_eq!(
+            layout("struct s { int a[3]; char c; };"),
+            (16, 4, vec![0, 96])
+        );
+    }
+
+    #[test]
+    fn union_members_share_storage() {
+        assert_eq!(
+            layout("union u { char c; int i; char* p; };"),
+            (8, 8, vec![0, 0, 0])
+        );
+        // the five chars are padded out to the alignment of `int`
+        let padded = "struct t { char a; char b; char c; char d; char e; };\n\
+                      union u { int i; struct t t; };";
+        assert_eq!(layout(padded), (8, 4, vec![0, 0]));
+    }
+
+    #[test]
+    fn bit_fields_share_storage_units() {
+        // `b` doesn't fit in the rest of `a`'s int
+        assert_eq!(
+            layout("struct s { int a : 3; int b : 30; char c; };"),
+            (12, 4, vec![0, 32, 64])
+        );
+        assert_eq!(
+            layout("struct s { char a : 3; char b : 4; int : 0; char c; };"),
+            (5, 1, vec![0, 3, 32])
+        );
+    }
+
+    #[test]
+    fn bit_field_must_fit_its_type() {
+        let mut lexer = Lexer::from_string("struct s { char c : 9; };");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let [
+            Statement::Struct {
+                name,
+                members,
+                is_union,
+            },
+        ] = &program[..]
+        else {
+            panic!("Expected a struct");
+        };
+        let error = Layouts::default()
+            .define(
+                &name.literal,
+                members,
+                *is_union,
+                &TargetConfig::default(),
+                &Enumerators::default(),
+            )
+            .expect_err("Should fail");
+        assert_eq!(error, EvalError::InvalidWidth { line: None });
+    }
+
+    #[test]
+    fn incomplete_member_type_has_no_layout() {
+        let mut lexer = Lexer::from_string("struct s { struct t t; };");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let [
+            Statement::Struct {
+                name,
+                members,
+                is_union,
+            },
+        ] = &program[..]
+        else {
+            panic!("Expected a struct");
+        };
+        let error = Layouts::default()
+            .define(
+                &name.literal,
+                members,
+                *is_union,
+                &TargetConfig::default(),
+                &Enumerators::default(),
+            )
+            .expect_err("Should fail");
+        assert_eq!(error.to_string(), "incomplete type 'struct t'");
+    }
+}
@@ -1,26 +1,75 @@
-use std::{collections::HashMap, fmt};
+use crate::target::{TargetAbi, TargetConfig};
+use crate::types::Type;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Range,
+    str::FromStr,
+};
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Hash)]
 pub enum TokenType {
     Identifier,
     Constant,
+    StringLiteral,
+    CharLiteral,
     LParen,
     RParen,
     LBrace,
     RBrace,
+    LBracket,
+    RBracket,
     Comma,
+    Dot,
+    Arrow,
     Minus,
     Plus,
     Star,
+    MinusEqual,
+    PlusEqual,
+    StarEqual,
+    SlashEqual,
     Semicolon,
+    Colon,
+    /// `...`, ending the parameters of a variadic function
+    Ellipsis,
+    Question,
     Return,
     If,
     Else,
+    While,
+    For,
+    Break,
+    Continue,
+    Goto,
+    Switch,
+    Case,
+    Default,
     Int,
     Float,
     Char,
+    Short,
+    Long,
+    Signed,
+    Unsigned,
     Struct,
+    Union,
+    Enum,
     Void,
+    Const,
+    Volatile,
+    Restrict,
+    Atomic,
+    Generic,
+    /// `_Static_assert`, or its C23 spelling `static_assert`
+    StaticAssert,
+    Inline,
+    Noreturn,
+    Static,
+    Register,
+    Sizeof,
     Bang,
     BangEqual,
     EqualEqual,
@@ -35,18 +84,528 @@ pub enum TokenType {
     EOF,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl TokenType {
+    /// Whether the token is one of the comparison operators, which yield 1 if
+    /// the comparison holds and 0 if not
+    pub fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            TokenType::EqualEqual
+                | TokenType::BangEqual
+                | TokenType::Less
+                | TokenType::LessEqual
+                | TokenType::Greater
+                | TokenType::GreaterEqual
+        )
+    }
+
+    /// How tightly the token binds as a binary operator, higher binding
+    /// tighter, or 0 if it isn't one. Each level takes operands of the levels
+    /// above it, so `a < b + c * d` is `a < (b + (c * d))`.
+    pub fn precedence(self) -> u8 {
+        match self {
+            TokenType::Star | TokenType::Slash => 4,
+            TokenType::Plus | TokenType::Minus => 3,
+            TokenType::Less
+            | TokenType::LessEqual
+            | TokenType::Greater
+            | TokenType::GreaterEqual => 2,
+            TokenType::EqualEqual | TokenType::BangEqual => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Spelling of the token in source, or a description for tokens that don't
+/// have a fixed one
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            TokenType::Identifier => "identifier",
+            TokenType::Constant => "constant",
+            TokenType::StringLiteral => "string literal",
+            TokenType::CharLiteral => "character constant",
+            TokenType::LParen => "(",
+            TokenType::RParen => ")",
+            TokenType::LBrace => "{",
+            TokenType::RBrace => "}",
+            TokenType::LBracket => "[",
+            TokenType::RBracket => "]",
+            TokenType::Comma => ",",
+            TokenType::Dot => ".",
+            TokenType::Arrow => "->",
+            TokenType::Minus => "-",
+            TokenType::Plus => "+",
+            TokenType::Star => "*",
+            TokenType::MinusEqual => "-=",
+            TokenType::PlusEqual => "+=",
+            TokenType::StarEqual => "*=",
+            TokenType::SlashEqual => "/=",
+            TokenType::Semicolon => ";",
+            TokenType::Colon => ":",
+            TokenType::Ellipsis => "...",
+            TokenType::Question => "?",
+            TokenType::Return => "return",
+            TokenType::If => "if",
+            TokenType::Else => "else",
+            TokenType::While => "while",
+            TokenType::For => "for",
+            TokenType::Break => "break",
+            TokenType::Continue => "continue",
+            TokenType::Goto => "goto",
+            TokenType::Switch => "switch",
+            TokenType::Case => "case",
+            TokenType::Default => "default",
+            TokenType::Int => "int",
+            TokenType::Float => "float",
+            TokenType::Char => "char",
+            TokenType::Short => "short",
+            TokenType::Long => "long",
+            TokenType::Signed => "signed",
+            TokenType::Unsigned => "unsigned",
+            TokenType::Struct => "struct",
+            TokenType::Union => "union",
+            TokenType::Enum => "enum",
+            TokenType::Void => "void",
+            TokenType::Const => "const",
+            TokenType::Volatile => "volatile",
+            TokenType::Restrict => "restrict",
+            TokenType::Atomic => "_Atomic",
+            TokenType::Generic => "_Generic",
+            TokenType::StaticAssert => "_Static_assert",
+            TokenType::Inline => "inline",
+            TokenType::Noreturn => "_Noreturn",
+            TokenType::Static => "static",
+            TokenType::Register => "register",
+            TokenType::Sizeof => "sizeof",
+            TokenType::Bang => "!",
+            TokenType::BangEqual => "!=",
+            TokenType::EqualEqual => "==",
+            TokenType::GreaterEqual => ">=",
+            TokenType::Greater => ">",
+            TokenType::LessEqual => "<=",
+            TokenType::True => "true",
+            TokenType::False => "false",
+            TokenType::Less => "<",
+            TokenType::Equal => "=",
+            TokenType::Slash => "/",
+            TokenType::EOF => "end of file",
+        };
+        f.write_str(text)
+    }
+}
+
+/// Broad kind of a token, for highlighting it in an editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenCategory {
+    Keyword,
+    Identifier,
+    /// Operators of expressions, including `?` and `:` of a conditional and
+    /// the member accesses `.` and `->`
+    Operator,
+    /// Brackets and separators, such as `(`, `;` and `...`
+    Punctuation,
+    NumberLiteral,
+    StringLiteral,
+    CharLiteral,
+    /// Not given to any token yet, the lexer skips comments
+    Comment,
+}
+
+/// Encoding prefix of a string literal or character constant, which decides
+/// the type of its elements
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// No prefix, `char`s in the source's encoding
+    Plain,
+    /// `u8`, UTF-8 in `char`s
+    Utf8,
+    /// `L`, `wchar_t`s
+    Wide,
+    /// `u`, UTF-16 in `char16_t`s
+    Utf16,
+    /// `U`, UTF-32 in `char32_t`s
+    Utf32,
+}
+
+impl Encoding {
+    fn from_prefix(prefix: &str) -> Option<Self> {
+        let encoding = match prefix {
+            "" => Encoding::Plain,
+            "u8" => Encoding::Utf8,
+            "L" => Encoding::Wide,
+            "u" => Encoding::Utf16,
+            "U" => Encoding::Utf32,
+            _ => return None,
+        };
+        Some(encoding)
+    }
+
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Encoding::Plain => "",
+            Encoding::Utf8 => "u8",
+            Encoding::Wide => "L",
+            Encoding::Utf16 => "u",
+            Encoding::Utf32 => "U",
+        }
+    }
+
+    /// Type of each element on `target`. `wchar_t` is a 32-bit `int`, except
+    /// on Windows where it's UTF-16 like `char16_t`.
+    pub fn element_type(self, target: &TargetConfig) -> Type {
+        match (self, target.abi) {
+            (Encoding::Plain | Encoding::Utf8, _) => Type::Char,
+            (Encoding::Wide, TargetAbi::SystemV) => Type::Int,
+            (Encoding::Wide, TargetAbi::Windows) | (Encoding::Utf16, _) => Type::UnsignedShort,
+            (Encoding::Utf32, _) => Type::UnsignedInt,
+        }
+    }
+
+    /// Largest value a numeric escape can give an element, whatever the
+    /// target, as long as it's also a valid character
+    fn max_escape(self) -> u32 {
+        match self {
+            Encoding::Plain | Encoding::Utf8 => 0xff,
+            Encoding::Utf16 => 0xffff,
+            Encoding::Wide | Encoding::Utf32 => u32::MAX,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Token<'a> {
     pub(crate) token_type: TokenType,
-    lexeme: &'a str,
-    pub(crate) literal: &'a str,
+    lexeme: Cow<'a, str>,
+    /// Value of the token, borrowed from the source unless it had to be decoded
+    /// (e.g. an identifier spelled with universal character names)
+    pub(crate) literal: Cow<'a, str>,
     pub(crate) line: usize,
+    /// Column of the token's first character, counting from 1
+    pub(crate) col: usize,
+    /// Byte offset of the token's first character in the source
+    pub(crate) offset: usize,
 }
 
 #[derive(Debug)]
 pub enum LexerError {
-    UnterminatedString { line: usize, col: usize },
-    UnexpectedChar { line: usize, col: usize, char: char },
+    UnterminatedString {
+        line: usize,
+        col: usize,
+    },
+    UnterminatedChar {
+        line: usize,
+        col: usize,
+    },
+    /// An empty or multi-character constant such as `''` or `'ab'`
+    InvalidCharConstant {
+        line: usize,
+        col: usize,
+    },
+    /// Unknown or incomplete escape sequence, positioned at the backslash
+    InvalidEscape {
+        line: usize,
+        col: usize,
+    },
+    UnexpectedChar {
+        line: usize,
+        col: usize,
+        char: char,
+    },
+    InvalidUniversalCharName {
+        line: usize,
+        col: usize,
+    },
+    /// Bytes that aren't valid UTF-8 outside of a comment
+    InvalidUtf8 {
+        line: usize,
+        col: usize,
+    },
+    /// `0x` with no digits after it, or a hexadecimal float such as `0x1.8`
+    /// missing its `p` exponent
+    InvalidHexConstant {
+        line: usize,
+        col: usize,
+    },
+}
+
+impl fmt::Display for LexerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexerError::UnterminatedString { line, col } => {
+                write!(f, "On line {}:{}, unterminated string", line, col)
+            }
+            LexerError::UnterminatedChar { line, col } => {
+                write!(
+                    f,
+                    "On line {}:{}, unterminated character constant",
+                    line, col
+                )
+            }
+            LexerError::InvalidCharConstant { line, col } => write!(
+                f,
+                "On line {}:{}, character constant must hold exactly one character",
+                line, col
+            ),
+            LexerError::InvalidEscape { line, col } => {
+                write!(f, "On line {}:{}, invalid escape sequence", line, col)
+            }
+            LexerError::UnexpectedChar { line, col, char } => {
+                write!(
+                    f,
+                    "On line {}:{}, unexpected character '{}'",
+                    line, col, char
+                )
+            }
+            LexerError::InvalidUniversalCharName { line, col } => write!(
+                f,
+                "On line {}:{}, invalid universal character name",
+                line, col
+            ),
+            LexerError::InvalidUtf8 { line, col } => {
+                write!(f, "On line {}:{}, invalid UTF-8 sequence", line, col)
+            }
+            LexerError::InvalidHexConstant { line, col } => write!(
+                f,
+                "On line {}:{}, invalid hexadecimal constant, a hexadecimal float needs a 'p' exponent",
+                line, col
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LexerError {}
+
+impl<'a> Token<'a> {
+    pub fn token_type(&self) -> TokenType {
+        self.token_type
+    }
+
+    /// Source text the token was scanned from
+    pub fn lexeme(&self) -> &str {
+        &self.lexeme
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.col
+    }
+
+    /// Kind of token, for syntax highlighting. `:` counts as an operator
+    /// wherever it appears, as the lexer can't tell a conditional's from a
+    /// label's, and EOF, which has no text, as punctuation.
+    pub fn category(&self) -> TokenCategory {
+        match self.token_type {
+            TokenType::Identifier => TokenCategory::Identifier,
+            TokenType::Constant => TokenCategory::NumberLiteral,
+            TokenType::StringLiteral => TokenCategory::StringLiteral,
+            TokenType::CharLiteral => TokenCategory::CharLiteral,
+            TokenType::Return
+            | TokenType::If
+            | TokenType::Else
+            | TokenType::While
+            | TokenType::For
+            | TokenType::Break
+            | TokenType::Continue
+            | TokenType::Goto
+            | TokenType::Switch
+            | TokenType::Case
+            | TokenType::Default
+            | TokenType::Int
+            | TokenType::Float
+            | TokenType::Char
+            | TokenType::Short
+            | TokenType::Long
+            | TokenType::Signed
+            | TokenType::Unsigned
+            | TokenType::Struct
+            | TokenType::Union
+            | TokenType::Enum
+            | TokenType::Void
+            | TokenType::Const
+            | TokenType::Volatile
+            | TokenType::Restrict
+            | TokenType::Atomic
+            | TokenType::Generic
+            | TokenType::StaticAssert
+            | TokenType::Inline
+            | TokenType::Noreturn
+            | TokenType::Static
+            | TokenType::Register
+            | TokenType::Sizeof
+            | TokenType::True
+            | TokenType::False => TokenCategory::Keyword,
+            TokenType::Dot
+            | TokenType::Arrow
+            | TokenType::Minus
+            | TokenType::Plus
+            | TokenType::Star
+            | TokenType::Slash
+            | TokenType::MinusEqual
+            | TokenType::PlusEqual
+            | TokenType::StarEqual
+            | TokenType::SlashEqual
+            | TokenType::Question
+            | TokenType::Colon
+            | TokenType::Bang
+            | TokenType::BangEqual
+            | TokenType::EqualEqual
+            | TokenType::GreaterEqual
+            | TokenType::Greater
+            | TokenType::LessEqual
+            | TokenType::Less
+            | TokenType::Equal => TokenCategory::Operator,
+            TokenType::LParen
+            | TokenType::RParen
+            | TokenType::LBrace
+            | TokenType::RBrace
+            | TokenType::LBracket
+            | TokenType::RBracket
+            | TokenType::Comma
+            | TokenType::Semicolon
+            | TokenType::Ellipsis
+            | TokenType::EOF => TokenCategory::Punctuation,
+        }
+    }
+
+    /// Whether both tokens mean the same thing, regardless of where they
+    /// appear. Tokens are compared by type and value, so an identifier spelled
+    /// with a universal character name matches one spelled directly.
+    pub fn same_kind(&self, other: &Token) -> bool {
+        self.token_type == other.token_type && self.literal == other.literal
+    }
+
+    /// Token made up by a later pass rather than scanned from the source, so
+    /// it has no position. Like scanned operators, it carries no literal.
+    pub(crate) const fn synthetic(token_type: TokenType, lexeme: &'static str) -> Token<'static> {
+        Token {
+            token_type,
+            lexeme: Cow::Borrowed(lexeme),
+            literal: Cow::Borrowed(""),
+            line: 0,
+            col: 0,
+            offset: 0,
+        }
+    }
+
+    /// Encoding of a string literal or character constant, from its prefix.
+    /// `None` for any other token.
+    pub fn encoding(&self) -> Option<Encoding> {
+        if !matches!(
+            self.token_type,
+            TokenType::StringLiteral | TokenType::CharLiteral
+        ) {
+            return None;
+        }
+        let prefix = &self.lexeme[..self.lexeme.find(['"', '\''])?];
+        Encoding::from_prefix(prefix)
+    }
+
+    /// Bytes a string literal of `char`s stands for, without the trailing
+    /// NUL. Escapes such as `\xff` are a single byte, while characters
+    /// written directly are kept as UTF-8. `None` for any other token,
+    /// including a wide string, see `code_units`.
+    pub fn string_bytes(&self) -> Option<Vec<u8>> {
+        if self.token_type != TokenType::StringLiteral
+            || !matches!(self.encoding(), Some(Encoding::Plain | Encoding::Utf8))
+        {
+            return None;
+        }
+        let quoted = &self.lexeme.as_bytes()[self.encoding()?.prefix().len()..];
+        Some(decode_string_bytes(&quoted[1..quoted.len() - 1]))
+    }
+
+    /// Values of the elements of a string literal on `target`, without the
+    /// trailing NUL, or the single value of a character constant. A
+    /// character outside the Basic Multilingual Plane takes two elements of
+    /// UTF-16. `None` for any other token.
+    pub fn code_units(&self, target: &TargetConfig) -> Option<Vec<u32>> {
+        let encoding = self.encoding()?;
+        let units = match (encoding, self.token_type) {
+            (Encoding::Plain | Encoding::Utf8, TokenType::StringLiteral) => {
+                self.string_bytes()?.into_iter().map(u32::from).collect()
+            }
+            _ if encoding.element_type(target) == Type::UnsignedShort => {
+                self.literal.encode_utf16().map(u32::from).collect()
+            }
+            _ => self.literal.chars().map(u32::from).collect(),
+        };
+        Some(units)
+    }
+
+    /// Size in bytes of the array a string literal initialises, counting its
+    /// trailing NUL
+    pub fn string_size(&self) -> Option<usize> {
+        self.string_bytes().map(|bytes| bytes.len() + 1)
+    }
+
+    /// Copy of the token that owns its text, so it can outlive the source
+    pub fn to_static(&self) -> Token<'static> {
+        Token {
+            token_type: self.token_type,
+            lexeme: Cow::Owned(self.lexeme.to_string()),
+            literal: Cow::Owned(self.literal.to_string()),
+            line: self.line,
+            col: self.col,
+            offset: self.offset,
+        }
+    }
+
+    /// The same token moved to `offset` and `line` of `source`, an edited
+    /// copy of the source it was scanned from. Its text is borrowed from
+    /// `source` wherever it was borrowed before.
+    fn moved<'n>(&self, source: &'n [u8], offset: usize, line: usize) -> Token<'n> {
+        let lexeme = match source
+            .get(offset..offset + self.lexeme.len())
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+        {
+            Some(text) if text == self.lexeme => Cow::Borrowed(text),
+            _ => Cow::Owned(self.lexeme.to_string()),
+        };
+        let literal = match (&self.literal, &lexeme) {
+            (Cow::Borrowed(""), _) => Cow::Borrowed(""),
+            // the same text anywhere in the lexeme will do
+            (Cow::Borrowed(literal), Cow::Borrowed(text)) if let Some(i) = text.find(*literal) => {
+                Cow::Borrowed(&text[i..i + literal.len()])
+            }
+            (literal, _) => Cow::Owned(literal.to_string()),
+        };
+        Token {
+            token_type: self.token_type,
+            lexeme,
+            literal,
+            line,
+            col: self.col,
+            offset,
+        }
+    }
+}
+
+/// Change to a source file, as made by an editor: the bytes in `range` of the
+/// old source replaced with `text`
+#[derive(Debug, Clone)]
+pub struct Edit<'e> {
+    pub range: Range<usize>,
+    pub text: &'e str,
+}
+
+/// Tokens are equal when they're the `same_kind`, ignoring their position
+impl PartialEq for Token<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_kind(other)
+    }
+}
+
+impl Eq for Token<'_> {}
+
+impl Hash for Token<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.token_type.hash(state);
+        self.literal.hash(state);
+    }
 }
 
 impl<'a> fmt::Display for Token<'a> {
@@ -55,37 +614,109 @@ impl<'a> fmt::Display for Token<'a> {
     }
 }
 
+/// Columns between tab stops unless the lexer is told otherwise
+const DEFAULT_TAB_WIDTH: usize = 8;
+
+/// Revision of the C standard, which decides which words are keywords. The
+/// latest is the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum LanguageStandard {
+    C89,
+    C99,
+    C11,
+    #[default]
+    C23,
+}
+
+impl LanguageStandard {
+    /// Whether `word`, a keyword in the latest standard, is one in this one
+    /// too, rather than an ordinary identifier
+    fn has_keyword(self, word: &str) -> bool {
+        let introduced = match word {
+            "inline" | "restrict" => LanguageStandard::C99,
+            "_Atomic" | "_Generic" | "_Noreturn" | "_Static_assert" => LanguageStandard::C11,
+            "static_assert" | "true" | "false" => LanguageStandard::C23,
+            _ => LanguageStandard::C89,
+        };
+        introduced <= self
+    }
+}
+
+impl FromStr for LanguageStandard {
+    type Err = String;
+
+    /// Parses the name `-std=` takes, e.g. `c99`
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "c89" | "c90" => Ok(LanguageStandard::C89),
+            "c99" => Ok(LanguageStandard::C99),
+            "c11" | "c17" => Ok(LanguageStandard::C11),
+            "c23" => Ok(LanguageStandard::C23),
+            _ => Err(format!("Unknown language standard '{}'", name)),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Lexer<'a> {
-    source: &'a str,
-    chars: std::str::Chars<'a>,
+    source: &'a [u8],
     tokens: Vec<Token<'a>>,
     start_byte: usize,
+    start_col: usize,
     curr_byte: usize,
     line: usize,
     col: usize,
+    /// Columns between tab stops, the first of which is at column 1
+    tab_width: usize,
     keywords: HashMap<&'static str, TokenType>,
 }
 
 impl<'a> Default for Lexer<'a> {
     fn default() -> Self {
         Lexer {
-            source: "",
-            chars: "".chars(),
+            source: b"",
             tokens: vec![],
             start_byte: 0,
+            start_col: 1,
             curr_byte: 0,
             line: 1,
             col: 1,
+            tab_width: DEFAULT_TAB_WIDTH,
             keywords: HashMap::from([
                 ("return", TokenType::Return),
                 ("if", TokenType::If),
                 ("else", TokenType::Else),
+                ("while", TokenType::While),
+                ("for", TokenType::For),
+                ("break", TokenType::Break),
+                ("continue", TokenType::Continue),
+                ("goto", TokenType::Goto),
+                ("switch", TokenType::Switch),
+                ("case", TokenType::Case),
+                ("default", TokenType::Default),
                 ("struct", TokenType::Struct),
+                ("union", TokenType::Union),
+                ("enum", TokenType::Enum),
                 ("void", TokenType::Void),
+                ("const", TokenType::Const),
+                ("volatile", TokenType::Volatile),
+                ("restrict", TokenType::Restrict),
+                ("_Atomic", TokenType::Atomic),
+                ("_Generic", TokenType::Generic),
+                ("_Static_assert", TokenType::StaticAssert),
+                ("static_assert", TokenType::StaticAssert),
+                ("inline", TokenType::Inline),
+                ("_Noreturn", TokenType::Noreturn),
+                ("static", TokenType::Static),
+                ("register", TokenType::Register),
+                ("sizeof", TokenType::Sizeof),
                 ("int", TokenType::Int),
                 ("float", TokenType::Float),
                 ("char", TokenType::Char),
+                ("short", TokenType::Short),
+                ("long", TokenType::Long),
+                ("signed", TokenType::Signed),
+                ("unsigned", TokenType::Unsigned),
                 ("true", TokenType::True),
                 ("false", TokenType::False),
             ]),
@@ -95,32 +726,70 @@ impl<'a> Default for Lexer<'a> {
 
 impl<'a> Lexer<'a> {
     pub fn from_string(source: &'a str) -> Self {
+        Self::from_bytes(source.as_bytes())
+    }
+
+    /// Lexes raw bytes which may not be valid UTF-8. Invalid sequences are
+    /// skipped inside comments, anywhere else they are an `InvalidUtf8` error.
+    pub fn from_bytes(source: &'a [u8]) -> Self {
         Lexer {
             source,
-            chars: source.chars(),
             ..Default::default()
         }
     }
 
-    fn add_token(&mut self, token_type: TokenType, literal: &'a str) -> Result<(), LexerError> {
-        let text = &self.source[self.start_byte..self.curr_byte];
+    /// Columns between tab stops, so reported columns line up with an editor
+    /// using the same setting. A width of 0 is taken as 1.
+    pub fn tab_width(mut self, width: usize) -> Self {
+        self.tab_width = width.max(1);
+        self
+    }
+
+    /// Lexes words that are only keywords in later standards than `standard`
+    /// as identifiers, so `inline` is an identifier in C89
+    pub fn standard(mut self, standard: LanguageStandard) -> Self {
+        let mut keywords = Lexer::default().keywords;
+        keywords.retain(|word, _| standard.has_keyword(word));
+        self.keywords = keywords;
+        self
+    }
+
+    /// Source text between two byte offsets, which must be valid UTF-8
+    fn text(&self, start: usize, end: usize) -> Result<&'a str, LexerError> {
+        std::str::from_utf8(&self.source[start..end]).map_err(|_| LexerError::InvalidUtf8 {
+            line: self.line,
+            col: self.col,
+        })
+    }
+
+    fn add_token(
+        &mut self,
+        token_type: TokenType,
+        literal: impl Into<Cow<'a, str>>,
+    ) -> Result<(), LexerError> {
+        let text = self.text(self.start_byte, self.curr_byte)?;
         self.tokens.push(Token {
-            token_type: token_type,
-            lexeme: text,
-            literal: literal,
+            token_type,
+            lexeme: Cow::Borrowed(text),
+            literal: literal.into(),
             line: self.line,
+            col: self.start_col,
+            offset: self.start_byte,
         });
         Ok(())
     }
 
     fn at(&self, pos: usize) -> Option<char> {
-        self.source.get(pos..)?.chars().next()
+        decode_char(self.source.get(pos..)?).map(|(c, _)| c)
     }
 
     fn advance(&mut self) -> Option<char> {
-        let c = self.chars.next()?;
-        self.curr_byte += c.len_utf8();
-        self.col += 1;
+        let (c, len) = decode_char(&self.source[self.curr_byte..])?;
+        self.curr_byte += len;
+        self.col += match c {
+            '\t' => self.tab_width - (self.col - 1) % self.tab_width,
+            _ => 1,
+        };
         Some(c)
     }
 
@@ -139,17 +808,17 @@ impl<'a> Lexer<'a> {
 
     fn peek(&self) -> Option<char> {
         if self.eof() {
-            return Some('\0');
+            Some('\0')
         } else {
-            return self.at(self.curr_byte);
+            self.at(self.curr_byte)
         }
     }
 
     fn peek_after(&self) -> Option<char> {
         if self.eof() {
-            return Some('\0');
+            Some('\0')
         } else {
-            return self.at(self.curr_byte + 1);
+            self.at(self.curr_byte + 1)
         }
     }
 
@@ -160,19 +829,49 @@ impl<'a> Lexer<'a> {
             while self.peek() != Some('\n') && !self.eof() {
                 self.advance();
             }
+        } else if self.matches('=') {
+            self.add_token(TokenType::SlashEqual, "")?;
         } else {
-            self.add_token(TokenType::Slash, "");
+            self.add_token(TokenType::Slash, "")?;
         }
 
         Ok(())
     }
 
-    fn consume_string(&mut self) -> Result<(), LexerError> {
+    /// Scans a string literal, called once the opening `"` and any prefix
+    /// before it have been consumed
+    fn consume_string(&mut self, encoding: Encoding) -> Result<(), LexerError> {
+        let contents = self.curr_byte;
+        // only allocated once an escape means the value differs from the source
+        let mut decoded: Option<String> = None;
+        // keep scanning past a bad escape so the rest of the string isn't
+        // lexed as code
+        let mut error = None;
+
         while self.peek() != Some('"') && !self.eof() {
             if self.peek() == Some('\n') {
                 self.line += 1;
+                // advancing past the newline moves on to the first column
+                self.col = 0;
             }
-            self.advance();
+            let escape_start = self.curr_byte;
+            let c = match self.advance() {
+                Some('\\') => match self.consume_escape(encoding) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        error.get_or_insert(e);
+                        continue;
+                    }
+                },
+                Some(c) if decoded.is_some() => c,
+                _ => continue,
+            };
+            let mut value = match decoded.take() {
+                Some(value) => value,
+                None => self.text(contents, escape_start)?.to_string(),
+            };
+            value.push(c);
+            decoded = Some(value);
         }
 
         if self.eof() {
@@ -184,35 +883,190 @@ impl<'a> Lexer<'a> {
 
         // need to call this to consume the closing '"'
         self.advance();
+        if let Some(e) = error {
+            return Err(e);
+        }
 
-        // FIXME we need to capture the value of the quotation mark instead
-        // of +1/-1 below. Either that or at least double-check the value
-        let constant = &self.source[self.start_byte + 1..self.curr_byte - 1];
-        self.add_token(TokenType::Constant, constant);
-        Ok(())
+        match decoded {
+            Some(value) => self.add_token(TokenType::StringLiteral, value),
+            None => {
+                let constant = self.text(contents, self.curr_byte - 1)?;
+                self.add_token(TokenType::StringLiteral, constant)
+            }
+        }
     }
 
-    fn consume_char(&mut self) {
-        // peek(2) == '\''? if not then report unterminated/invalid char
-        todo!();
+    /// Scans a character constant, called once the opening `'` and any prefix
+    /// before it have been consumed
+    fn consume_char(&mut self, encoding: Encoding) -> Result<(), LexerError> {
+        let col = self.start_col;
+        let mut value = None;
+        let mut error = None;
+
+        // scan up to the closing quote even when the contents are bad
+        while !matches!(self.peek(), Some('\'' | '\n')) && !self.eof() {
+            let c = match self.advance() {
+                Some('\\') => self.consume_escape(encoding),
+                Some(c) => Ok(c),
+                None => break,
+            };
+            match c {
+                Ok(c) if value.is_none() => value = Some(c),
+                Ok(_) => {
+                    error.get_or_insert(LexerError::InvalidCharConstant {
+                        line: self.line,
+                        col,
+                    });
+                }
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+
+        if !self.matches('\'') {
+            return Err(LexerError::UnterminatedChar {
+                line: self.line,
+                col,
+            });
+        }
+        if let Some(e) = error {
+            return Err(e);
+        }
+        let Some(value) = value else {
+            return Err(LexerError::InvalidCharConstant {
+                line: self.line,
+                col,
+            });
+        };
+        self.add_token(TokenType::CharLiteral, value.to_string())
     }
 
-    fn consume_identifier(&mut self) {
-        while matches!(self.peek(), c if self.is_alphanumeric(c)) {
+    /// Decodes an escape sequence in a string or character literal of
+    /// `encoding`, called once the backslash has been consumed
+    fn consume_escape(&mut self, encoding: Encoding) -> Result<char, LexerError> {
+        // report the position of the backslash
+        let invalid = LexerError::InvalidEscape {
+            line: self.line,
+            col: self.col - 1,
+        };
+
+        let c = match self.peek() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('a') => '\x07',
+            Some('b') => '\x08',
+            Some('f') => '\x0c',
+            Some('v') => '\x0b',
+            Some(c @ ('\\' | '\'' | '"' | '?')) => c,
+            Some('x') => {
+                self.advance();
+                return self.numeric_escape(16, usize::MAX, encoding.max_escape(), invalid);
+            }
+            Some('0'..='7') => return self.numeric_escape(8, 3, encoding.max_escape(), invalid),
+            // leave a newline for the caller
+            Some('\n') | None => return Err(invalid),
+            Some(_) => {
+                self.advance();
+                return Err(invalid);
+            }
+        };
+        self.advance();
+        Ok(c)
+    }
+
+    /// Digits of a `\xNN` or `\NNN` escape, which must form a value no more
+    /// than `max`, the largest an element can hold, that's also a character
+    fn numeric_escape(
+        &mut self,
+        radix: u32,
+        max_digits: usize,
+        max: u32,
+        invalid: LexerError,
+    ) -> Result<char, LexerError> {
+        let mut value = 0u32;
+        let mut digits = 0;
+        while digits < max_digits
+            && let Some(digit) = self.peek().and_then(|c| c.to_digit(radix))
+        {
             self.advance();
+            value = value.saturating_mul(radix).saturating_add(digit);
+            digits += 1;
         }
 
-        let text = &self.source[self.start_byte..self.curr_byte];
+        match char::from_u32(value) {
+            Some(c) if digits > 0 && value <= max => Ok(c),
+            _ => Err(invalid),
+        }
+    }
 
-        if let Some(token_type) = self.keywords.get(text).cloned() {
-            self.add_token(token_type, text);
-        } else {
-            self.add_token(TokenType::Identifier, text);
+    fn consume_identifier(&mut self) -> Result<(), LexerError> {
+        loop {
+            if matches!(self.peek(), c if self.is_alphanumeric(c) || is_unicode_identifier(c, false))
+            {
+                self.advance();
+            } else if self.peek() == Some('\\') && matches!(self.peek_after(), Some('u' | 'U')) {
+                self.advance();
+                self.consume_universal_char_name(false)?;
+            } else {
+                break;
+            }
+        }
+
+        let text = self.text(self.start_byte, self.curr_byte)?;
+        if !text.contains('\\') {
+            return match self.keywords.get(text).cloned() {
+                Some(token_type) => self.add_token(token_type, text),
+                None => self.add_token(TokenType::Identifier, text),
+            };
+        }
+
+        let name = decode_universal_char_names(text);
+        self.add_token(TokenType::Identifier, name)
+    }
+
+    /// Consumes and validates a `\uXXXX` or `\UXXXXXXXX` escape inside an
+    /// identifier, called once the backslash has been consumed
+    fn consume_universal_char_name(&mut self, is_start: bool) -> Result<(), LexerError> {
+        // report the position of the backslash
+        let col = self.col - 1;
+        let digits = if self.advance() == Some('u') { 4 } else { 8 };
+
+        let mut value = 0u32;
+        for _ in 0..digits {
+            match self.peek().and_then(|c| c.to_digit(16)) {
+                Some(digit) => {
+                    self.advance();
+                    value = value * 16 + digit;
+                }
+                None => {
+                    return Err(LexerError::InvalidUniversalCharName {
+                        line: self.line,
+                        col,
+                    });
+                }
+            }
         }
+
+        // surrogates and anything outside Annex D are rejected here too
+        if !is_unicode_identifier(char::from_u32(value), is_start) {
+            return Err(LexerError::InvalidUniversalCharName {
+                line: self.line,
+                col,
+            });
+        }
+        Ok(())
     }
 
-    fn consume_number(&mut self) {
-        // similar to string implementation
+    fn consume_number(&mut self) -> Result<(), LexerError> {
+        if matches!(self.peek(), Some('x' | 'X'))
+            && self.text(self.start_byte, self.curr_byte)? == "0"
+        {
+            return self.consume_hex_number();
+        }
+        // similar to string implementation, a leading `.` has already been
+        // consumed for floats like `.5`
         while self.is_digit(self.peek()) && !self.eof() {
             self.advance();
         }
@@ -225,10 +1079,55 @@ impl<'a> Lexer<'a> {
             }
         } // TODO - a syntax error could be caught here
 
-        self.add_token(
-            TokenType::Constant,
-            &self.source[self.start_byte..self.curr_byte],
-        );
+        let text = self.text(self.start_byte, self.curr_byte)?;
+        self.add_token(TokenType::Constant, text)
+    }
+
+    /// Hexadecimal integer such as `0xff`, or float such as `0x1.8p3`, once
+    /// the leading `0` has been consumed. A float's `p` exponent is a power of
+    /// two, written in decimal, and is required.
+    fn consume_hex_number(&mut self) -> Result<(), LexerError> {
+        let invalid = LexerError::InvalidHexConstant {
+            line: self.line,
+            col: self.start_col,
+        };
+        self.advance();
+        let mut digits = self.hex_digits();
+        let fraction = self.matches('.');
+        if fraction {
+            digits += self.hex_digits();
+        }
+        if digits == 0 {
+            return Err(invalid);
+        }
+
+        if matches!(self.peek(), Some('p' | 'P')) {
+            self.advance();
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.advance();
+            }
+            if !self.is_digit(self.peek()) {
+                return Err(invalid);
+            }
+            while self.is_digit(self.peek()) {
+                self.advance();
+            }
+        } else if fraction {
+            return Err(invalid);
+        }
+
+        let text = self.text(self.start_byte, self.curr_byte)?;
+        self.add_token(TokenType::Constant, text)
+    }
+
+    /// Consumes any hexadecimal digits, returning how many there were
+    fn hex_digits(&mut self) -> usize {
+        let mut digits = 0;
+        while self.peek().is_some_and(|c| c.is_ascii_hexdigit()) && !self.eof() {
+            self.advance();
+            digits += 1;
+        }
+        digits
     }
 
     fn is_alphanumeric(&self, c: Option<char>) -> bool {
@@ -259,9 +1158,36 @@ impl<'a> Lexer<'a> {
             Some(')') => self.add_token(TokenType::RParen, ""),
             Some('{') => self.add_token(TokenType::LBrace, ""),
             Some('}') => self.add_token(TokenType::RBrace, ""),
+            Some('[') => self.add_token(TokenType::LBracket, ""),
+            Some(']') => self.add_token(TokenType::RBracket, ""),
+            // a float like `.5` rather than member access
+            Some('.') if self.is_digit(self.peek()) => self.consume_number(),
+            Some('.') if self.peek() == Some('.') && self.peek_after() == Some('.') => {
+                self.advance();
+                self.advance();
+                self.add_token(TokenType::Ellipsis, "")
+            }
+            Some('.') => self.add_token(TokenType::Dot, ""),
             Some(',') => self.add_token(TokenType::Comma, ""),
             Some(';') => self.add_token(TokenType::Semicolon, ""),
-            Some('*') => self.add_token(TokenType::Star, ""),
+            Some(':') => self.add_token(TokenType::Colon, ""),
+            Some('?') => self.add_token(TokenType::Question, ""),
+            Some('*') => {
+                let token_type = self.conditional_token('=', TokenType::StarEqual, TokenType::Star);
+                self.add_token(token_type, "")
+            }
+            Some('+') => {
+                let token_type = self.conditional_token('=', TokenType::PlusEqual, TokenType::Plus);
+                self.add_token(token_type, "")
+            }
+            Some('-') => {
+                let token_type = if self.matches('>') {
+                    TokenType::Arrow
+                } else {
+                    self.conditional_token('=', TokenType::MinusEqual, TokenType::Minus)
+                };
+                self.add_token(token_type, "")
+            }
             // conditional tokens
             Some('!') => {
                 let token_type = self.conditional_token('=', TokenType::BangEqual, TokenType::Bang);
@@ -284,19 +1210,39 @@ impl<'a> Lexer<'a> {
             Some('/') => self.parse_slash(),
             Some('\n') => {
                 self.line += 1;
-                self.col = 0;
+                self.col = 1;
                 Ok(())
             }
-            Some('"') => self.consume_string(),
-            Some('\'') => Ok(self.consume_char()),
+            Some('"') => self.consume_string(Encoding::Plain),
+            Some('\'') => self.consume_char(Encoding::Plain),
+            // an encoding prefix, such as `L"wide"` or `u8"utf-8"`
+            Some('L' | 'u' | 'U')
+                if matches!(self.peek(), Some('"' | '\''))
+                    || (c == Some('u')
+                        && self.peek() == Some('8')
+                        && matches!(self.peek_after(), Some('"' | '\''))) =>
+            {
+                if self.peek() == Some('8') {
+                    self.advance();
+                }
+                let prefix = self.text(self.start_byte, self.curr_byte)?;
+                let encoding = Encoding::from_prefix(prefix).expect("prefix matched above");
+                match self.advance() {
+                    Some('"') => self.consume_string(encoding),
+                    _ => self.consume_char(encoding),
+                }
+            }
             Some(' ') | Some('\r') | Some('\t') => Ok(()),
             _ => {
                 if self.is_digit(c) {
-                    self.consume_number();
-                    Ok(())
-                } else if self.is_alpha(c) {
-                    self.consume_identifier();
-                    Ok(())
+                    self.consume_number()
+                } else if self.is_alpha(c) || is_unicode_identifier(c, true) {
+                    self.consume_identifier()
+                } else if c == Some('\\') && matches!(self.peek(), Some('u' | 'U')) {
+                    self.consume_universal_char_name(true)?;
+                    self.consume_identifier()
+                } else if let Err(e) = self.text(self.start_byte, self.curr_byte) {
+                    Err(e)
                 } else {
                     Err(LexerError::UnexpectedChar {
                         line: self.line,
@@ -309,16 +1255,14 @@ impl<'a> Lexer<'a> {
     }
 
     fn matches(&mut self, expected: char) -> bool {
-        if !self.eof() {
-            if self.at(self.curr_byte) == Some(expected) {
-                self.advance();
-                return true;
-            }
+        if !self.eof() && self.at(self.curr_byte) == Some(expected) {
+            self.advance();
+            return true;
         }
-        return false;
+        false
     }
 
-    pub fn tokens(&self) -> impl Iterator<Item = &Token> {
+    pub fn tokens(&self) -> impl Iterator<Item = &Token<'a>> {
         self.tokens.iter()
     }
 
@@ -328,6 +1272,7 @@ impl<'a> Lexer<'a> {
         // scan file
         while !self.eof() {
             self.start_byte = self.curr_byte;
+            self.start_col = self.col;
 
             if let Err(e) = self.scan_token() {
                 errors.push(e);
@@ -335,12 +1280,7 @@ impl<'a> Lexer<'a> {
         }
 
         // add EOF token before finishing
-        self.tokens.push(Token {
-            token_type: TokenType::EOF,
-            lexeme: "",
-            literal: "",
-            line: self.line,
-        });
+        self.push_eof();
 
         if errors.is_empty() {
             return Ok(&self.tokens);
@@ -349,11 +1289,242 @@ impl<'a> Lexer<'a> {
         // }
     }
 
+    /// Tokenises the source, given the tokens `old` of the source before
+    /// `edit` was made to it. Only the tokens around the edit are scanned
+    /// again: scanning restarts at a token ending before the edit, and stops
+    /// at the first token after it that starts where an old token did, from
+    /// which on the old tokens are reused.
+    pub fn relex(
+        &mut self,
+        old: &[Token],
+        edit: &Edit,
+    ) -> Result<&Vec<Token<'a>>, Vec<LexerError>> {
+        let edit_end = edit.range.start + edit.text.len();
+        // a token touching the edit could be joined onto, and one spanning
+        // lines doesn't record the line it starts on
+        let restart = old.iter().rposition(|t| {
+            t.token_type != TokenType::EOF
+                && t.offset + t.lexeme.len() < edit.range.start
+                && !t.lexeme.contains('\n')
+        });
+        self.tokens = vec![];
+        if let Some(restart) = restart {
+            self.tokens = old[..restart]
+                .iter()
+                .map(|t| t.moved(self.source, t.offset, t.line))
+                .collect();
+            self.curr_byte = old[restart].offset;
+            self.line = old[restart].line;
+            self.col = old[restart].col;
+        }
+
+        let mut errors = Vec::new();
+        while !self.eof() {
+            self.start_byte = self.curr_byte;
+            self.start_col = self.col;
+            let scanned = self.tokens.len();
+
+            if let Err(e) = self.scan_token() {
+                errors.push(e);
+            }
+            if self.tokens.len() > scanned
+                && self.start_byte >= edit_end
+                && let Some(resumed) = self.resume(old, edit)
+            {
+                self.tokens.pop();
+                let line_shift = self.line as isize - old[resumed].line as isize;
+                let shift = edit.text.len() as isize - edit.range.len() as isize;
+                for t in &old[resumed..] {
+                    let offset = (t.offset as isize + shift) as usize;
+                    let line = (t.line as isize + line_shift) as usize;
+                    self.tokens.push(t.moved(self.source, offset, line));
+                }
+                break;
+            }
+        }
+        if self
+            .tokens
+            .last()
+            .is_none_or(|t| t.token_type != TokenType::EOF)
+        {
+            self.push_eof();
+        }
+
+        if errors.is_empty() {
+            return Ok(&self.tokens);
+        }
+        Err(errors)
+    }
+
+    /// Index of the old token the token just scanned lines up with, if any.
+    /// It must start at the same place in the unchanged text after the edit,
+    /// and at the same column so the ones after it don't move either.
+    fn resume(&self, old: &[Token], edit: &Edit) -> Option<usize> {
+        let scanned = self.tokens.last()?;
+        let offset = (scanned.offset + edit.range.len()).checked_sub(edit.text.len())?;
+        let i = old.binary_search_by_key(&offset, |t| t.offset).ok()?;
+        (old[i].col == scanned.col && old[i].token_type == scanned.token_type).then_some(i)
+    }
+
+    fn push_eof(&mut self) {
+        self.tokens.push(Token {
+            token_type: TokenType::EOF,
+            lexeme: Cow::Borrowed(""),
+            literal: Cow::Borrowed(""),
+            line: self.line,
+            col: self.col,
+            offset: self.curr_byte,
+        });
+    }
+
     fn eof(&self) -> bool {
         self.curr_byte >= self.source.len()
     }
 }
 
+/// Ranges of non-ASCII characters C11 allows in identifiers (Annex D.1)
+const IDENTIFIER_RANGES: &[(u32, u32)] = &[
+    (0x00A8, 0x00A8),
+    (0x00AA, 0x00AA),
+    (0x00AD, 0x00AD),
+    (0x00AF, 0x00AF),
+    (0x00B2, 0x00B5),
+    (0x00B7, 0x00BA),
+    (0x00BC, 0x00BE),
+    (0x00C0, 0x00D6),
+    (0x00D8, 0x00F6),
+    (0x00F8, 0x00FF),
+    (0x0100, 0x167F),
+    (0x1681, 0x180D),
+    (0x180F, 0x1FFF),
+    (0x200B, 0x200D),
+    (0x202A, 0x202E),
+    (0x203F, 0x2040),
+    (0x2054, 0x2054),
+    (0x2060, 0x206F),
+    (0x2070, 0x218F),
+    (0x2460, 0x24FF),
+    (0x2776, 0x2793),
+    (0x2C00, 0x2DFF),
+    (0x2E80, 0x2FFF),
+    (0x3004, 0x3007),
+    (0x3021, 0x302F),
+    (0x3031, 0x303F),
+    (0x3040, 0xD7FF),
+    (0xF900, 0xFD3D),
+    (0xFD40, 0xFDCF),
+    (0xFDF0, 0xFE44),
+    (0xFE47, 0xFFFD),
+];
+
+/// Combining characters that may not begin an identifier (Annex D.2)
+const NON_INITIAL_RANGES: &[(u32, u32)] = &[
+    (0x0300, 0x036F),
+    (0x1DC0, 0x1DFF),
+    (0x20D0, 0x20FF),
+    (0xFE20, 0xFE2F),
+];
+
+/// Whether `c` is a non-ASCII character permitted in an identifier, either as
+/// its first character (`is_start`) or afterwards
+fn is_unicode_identifier(c: Option<char>, is_start: bool) -> bool {
+    let Some(c) = c.filter(|c| !c.is_ascii()) else {
+        return false;
+    };
+    let value = c as u32;
+    let in_ranges =
+        |ranges: &[(u32, u32)]| ranges.iter().any(|&(lo, hi)| (lo..=hi).contains(&value));
+
+    // planes 1-14 are allowed apart from the last two code points of each
+    let supplementary = (0x10000..0xF0000).contains(&value) && value & 0xFFFF <= 0xFFFD;
+    if is_start && in_ranges(NON_INITIAL_RANGES) {
+        return false;
+    }
+    in_ranges(IDENTIFIER_RANGES) || in_ranges(NON_INITIAL_RANGES) || supplementary
+}
+
+/// Value of an already-validated hexadecimal float constant such as
+/// `0x1.8p3`, i.e. its hexadecimal digits scaled by two to the power of its
+/// exponent
+pub(crate) fn hex_float_value(text: &str) -> Option<f64> {
+    let (mantissa, exponent) = text.get(2..)?.split_once(['p', 'P'])?;
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let mut value = 0.0;
+    for digit in whole.chars().chain(fraction.chars()) {
+        value = value * 16.0 + f64::from(digit.to_digit(16)?);
+    }
+    let exponent: i32 = exponent.parse().ok()?;
+    // each fractional digit shifts the point four binary places
+    let shift = i32::try_from(fraction.len()).ok()?.checked_mul(4)?;
+    Some(value * 2f64.powi(exponent.saturating_sub(shift)))
+}
+
+/// Replaces every universal character name in an already-validated identifier
+/// with the character it names
+fn decode_universal_char_names(text: &str) -> String {
+    let mut name = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            name.push(c);
+            continue;
+        }
+        let digits = if chars.next() == Some('u') { 4 } else { 8 };
+        let hex: String = chars.by_ref().take(digits).collect();
+        let value = u32::from_str_radix(&hex, 16).expect("UCN validated while lexing");
+        name.push(char::from_u32(value).expect("UCN validated while lexing"));
+    }
+    name
+}
+
+/// Decodes the escapes in the already-validated contents of a string
+/// literal, following the same rules as `Lexer::consume_escape`
+fn decode_string_bytes(text: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.iter().copied().peekable();
+    while let Some(b) = rest.next() {
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        let escaped = rest.next().expect("escape validated while lexing");
+        let (radix, max_digits, value) = match escaped {
+            b'n' => (0, 0, b'\n'),
+            b't' => (0, 0, b'\t'),
+            b'r' => (0, 0, b'\r'),
+            b'a' => (0, 0, 0x07),
+            b'b' => (0, 0, 0x08),
+            b'f' => (0, 0, 0x0c),
+            b'v' => (0, 0, 0x0b),
+            b'x' => (16, usize::MAX, 0),
+            b'0'..=b'7' => (8, 2, escaped - b'0'),
+            other => (0, 0, other),
+        };
+        let mut value = u32::from(value);
+        let mut digits = 0;
+        while digits < max_digits
+            && let Some(digit) = rest.peek().and_then(|&d| char::from(d).to_digit(radix))
+        {
+            rest.next();
+            value = value * radix + digit;
+            digits += 1;
+        }
+        bytes.push(u8::try_from(value).expect("escape validated while lexing"));
+    }
+    bytes
+}
+
+/// Decodes the character at the start of `bytes` along with its length in
+/// bytes. An invalid sequence decodes as U+FFFD covering the bad bytes.
+fn decode_char(bytes: &[u8]) -> Option<(char, usize)> {
+    // a character is at most 4 bytes, don't validate any further than that
+    let chunk = bytes[..bytes.len().min(4)].utf8_chunks().next()?;
+    match chunk.valid().chars().next() {
+        Some(c) => Some((c, c.len_utf8())),
+        None => Some((char::REPLACEMENT_CHARACTER, chunk.invalid().len())),
+    }
+}
+
 #[cfg(test)]
 mod lexer_tests {
     use super::*;
@@ -361,12 +1532,10 @@ mod lexer_tests {
     #[test]
     fn lex_catch_unterminated_string() {
         let source = "int main(void) {\nchar* str = \"string here\nreturn 0=0;\n}";
-        let mut lexer = Lexer::from_string(&source);
+        let mut lexer = Lexer::from_string(source);
         let result = lexer.tokenise();
         match result {
-            Ok(_) => {
-                assert!(false);
-            }
+            Ok(_) => panic!("Expected error on unterminated string"),
             Err(mut errors) => {
                 assert_eq!(errors.len(), 1);
                 let error = errors.pop().unwrap();
@@ -374,19 +1543,134 @@ mod lexer_tests {
             }
         }
     }
+    #[test]
+    fn token_types_display_as_spelled() {
+        assert_eq!(TokenType::EqualEqual.to_string(), "==");
+        assert_eq!(TokenType::Arrow.to_string(), "->");
+        assert_eq!(TokenType::SlashEqual.to_string(), "/=");
+        assert_eq!(TokenType::Return.to_string(), "return");
+        assert_eq!(TokenType::LBrace.to_string(), "{");
+        assert_eq!(TokenType::Identifier.to_string(), "identifier");
+    }
+
+    #[test]
+    fn tokens_have_categories() {
+        let source = "static int f(void) { return x->y ? 1.5 : sizeof(char) + 'c'; } \"s\" ...";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let categories: Vec<_> = tokens.iter().map(|t| (t.lexeme(), t.category())).collect();
+        use TokenCategory::*;
+        assert_eq!(
+            categories,
+            [
+                ("static", Keyword),
+                ("int", Keyword),
+                ("f", Identifier),
+                ("(", Punctuation),
+                ("void", Keyword),
+                (")", Punctuation),
+                ("{", Punctuation),
+                ("return", Keyword),
+                ("x", Identifier),
+                ("->", Operator),
+                ("y", Identifier),
+                ("?", Operator),
+                ("1.5", NumberLiteral),
+                (":", Operator),
+                ("sizeof", Keyword),
+                ("(", Punctuation),
+                ("char", Keyword),
+                (")", Punctuation),
+                ("+", Operator),
+                ("'c'", CharLiteral),
+                (";", Punctuation),
+                ("}", Punctuation),
+                ("\"s\"", StringLiteral),
+                ("...", Punctuation),
+                ("", Punctuation),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens_compare_ignoring_position() {
+        let mut lexer = Lexer::from_string("x + 1\n  x+1 caf\\u00e9 café y");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let [x, plus, one, x2, plus2, one2, ucn, direct, y, ..] = &tokens[..] else {
+            panic!("Expected at least nine tokens");
+        };
+
+        assert_ne!((x.line, x.col), (x2.line, x2.col));
+        assert!(x.same_kind(x2) && plus.same_kind(plus2) && one.same_kind(one2));
+        assert_eq!(x, x2);
+        assert!(ucn.same_kind(direct));
+        assert!(!x.same_kind(y));
+        assert!(!one.same_kind(x));
+
+        // equal tokens collapse in a set
+        let unique: std::collections::HashSet<_> = tokens.iter().collect();
+        assert_eq!(unique.len(), 6);
+    }
+
+    #[test]
+    fn tab_advances_to_next_tab_stop() {
+        let source = "\tint x;\n  \ty;";
+        for (width, columns) in [(4, [5, 9, 5]), (8, [9, 13, 9])] {
+            let mut lexer = Lexer::from_string(source).tab_width(width);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            let int = &tokens[0];
+            let x = &tokens[1];
+            // the tab after two spaces still stops at the same column
+            let y = &tokens[3];
+            assert_eq!([int.col, x.col, y.col], columns, "width {}", width);
+        }
+
+        // the default is 8
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        assert_eq!(tokens[0].col, 9);
+    }
+
     #[test]
     fn lex_tokenizes_simple_return_statement() {
         let source = "return 42;";
         let mut lexer = Lexer::from_string(source);
         let tokens = lexer.tokenise().expect("Should tokenise without errors");
 
-        let token_types: Vec<_> = tokens.iter().map(|t| t.token_type.clone()).collect();
+        let token_types: Vec<_> = tokens.iter().map(|t| t.token_type).collect();
 
         assert!(token_types.contains(&TokenType::Return));
         assert!(token_types.contains(&TokenType::Constant));
         assert!(token_types.contains(&TokenType::Semicolon));
     }
 
+    #[test]
+    fn keywords_depend_on_standard() {
+        let types = |standard| {
+            let mut lexer = Lexer::from_string("inline restrict _Generic true").standard(standard);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            tokens.iter().map(|t| t.token_type).collect::<Vec<_>>()
+        };
+        use TokenType::*;
+        assert_eq!(
+            types(LanguageStandard::C89),
+            [Identifier, Identifier, Identifier, Identifier, EOF]
+        );
+        assert_eq!(
+            types(LanguageStandard::C99),
+            [Inline, Restrict, Identifier, Identifier, EOF]
+        );
+        assert_eq!(
+            types(LanguageStandard::C11),
+            [Inline, Restrict, Generic, Identifier, EOF]
+        );
+        assert_eq!(
+            types(LanguageStandard::default()),
+            [Inline, Restrict, Generic, True, EOF]
+        );
+        assert_eq!("c90".parse(), Ok(LanguageStandard::C89));
+    }
+
     #[test]
     fn lex_identifiers_and_keywords() {
         let source = "int var = 100;";
@@ -421,6 +1705,403 @@ mod lexer_tests {
         }
     }
 
+    #[test]
+    fn lex_identifier_with_multibyte_character() {
+        let source = "int café = 1;";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        assert_eq!(tokens[1].token_type, TokenType::Identifier);
+        assert_eq!(tokens[1].literal, "café");
+        assert_eq!(tokens[2].token_type, TokenType::Equal);
+    }
+
+    #[test]
+    fn lex_identifier_with_universal_character_name() {
+        let source = "int caf\\u00e9 = \\U0001F600x;";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        assert_eq!(tokens[1].token_type, TokenType::Identifier);
+        assert_eq!(tokens[1].lexeme(), "caf\\u00e9");
+        assert_eq!(tokens[1].literal, "café");
+        assert_eq!(tokens[3].token_type, TokenType::Identifier);
+        assert_eq!(tokens[3].literal, "\u{1F600}x");
+    }
+
+    #[test]
+    fn lex_rejects_invalid_universal_character_names() {
+        // surrogate, combining mark in initial position, too few digits
+        for source in ["int a\\uD800;", "int \\u0301a;", "int a\\u12;"] {
+            let mut lexer = Lexer::from_string(source);
+            match lexer.tokenise() {
+                Ok(_) => panic!("Expected error for '{}'", source),
+                Err(errors) => {
+                    assert_eq!(errors.len(), 1);
+                    assert!(matches!(
+                        errors[0],
+                        LexerError::InvalidUniversalCharName { line: 1, .. }
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lex_punctuation() {
+        let source = "a[0].b, c; d: {e->f} g += h -= i *= j /= k";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        let token_types: Vec<_> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::Identifier,
+                TokenType::LBracket,
+                TokenType::Constant,
+                TokenType::RBracket,
+                TokenType::Dot,
+                TokenType::Identifier,
+                TokenType::Comma,
+                TokenType::Identifier,
+                TokenType::Semicolon,
+                TokenType::Identifier,
+                TokenType::Colon,
+                TokenType::LBrace,
+                TokenType::Identifier,
+                TokenType::Arrow,
+                TokenType::Identifier,
+                TokenType::RBrace,
+                TokenType::Identifier,
+                TokenType::PlusEqual,
+                TokenType::Identifier,
+                TokenType::MinusEqual,
+                TokenType::Identifier,
+                TokenType::StarEqual,
+                TokenType::Identifier,
+                TokenType::SlashEqual,
+                TokenType::Identifier,
+                TokenType::EOF,
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_hex_constants() {
+        let source = "0x1p0 0x1.8p1 0X.8P-1 0xff+1";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        let literals: Vec<_> = tokens.iter().map(|t| &*t.literal).collect();
+        assert_eq!(
+            literals,
+            ["0x1p0", "0x1.8p1", "0X.8P-1", "0xff", "", "1", ""]
+        );
+        assert_eq!(hex_float_value(literals[0]), Some(1.0));
+        assert_eq!(hex_float_value(literals[1]), Some(3.0));
+        assert_eq!(hex_float_value(literals[2]), Some(0.25));
+        assert_eq!(hex_float_value("0x1.8p3"), Some(12.0));
+    }
+
+    #[test]
+    fn hex_float_needs_exponent() {
+        for source in ["x = 0x1.8;", "x = 0x1p;", "x = 0x;"] {
+            let mut lexer = Lexer::from_string(source);
+            let errors = lexer.tokenise().expect_err("Should reject hex constant");
+            assert!(
+                matches!(
+                    errors[..],
+                    [LexerError::InvalidHexConstant { line: 1, col: 5 }]
+                ),
+                "{}: {:?}",
+                source,
+                errors
+            );
+        }
+    }
+
+    #[test]
+    fn lex_ellipsis() {
+        let mut lexer = Lexer::from_string("f(a, ...) s.x ..");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let token_types: Vec<_> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(token_types[4], TokenType::Ellipsis);
+        assert_eq!(token_types[7], TokenType::Dot);
+        assert_eq!(&token_types[9..11], [TokenType::Dot, TokenType::Dot]);
+    }
+
+    #[test]
+    fn lex_leading_dot_float_is_not_member_access() {
+        let source = "x = .5 + s.y;";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        assert_eq!(tokens[2].token_type, TokenType::Constant);
+        assert_eq!(tokens[2].literal, ".5");
+        assert_eq!(tokens[5].token_type, TokenType::Dot);
+    }
+
+    #[test]
+    fn lex_bytes_with_invalid_utf8_in_comment() {
+        let source = b"int x; // caf\xe9\nreturn x;";
+        let mut lexer = Lexer::from_bytes(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        let token_types: Vec<_> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::Int,
+                TokenType::Identifier,
+                TokenType::Semicolon,
+                TokenType::Return,
+                TokenType::Identifier,
+                TokenType::Semicolon,
+                TokenType::EOF,
+            ]
+        );
+        assert_eq!(tokens[3].line, 2);
+        assert_eq!(tokens[3].col, 1);
+        assert_eq!(tokens[4].col, 8);
+
+        let mut lexer = Lexer::from_bytes(b"int \xff = \"\xfe\";");
+        let errors = lexer.tokenise().expect_err("Should reject invalid UTF-8");
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[0],
+            LexerError::InvalidUtf8 { line: 1, col: 6 }
+        ));
+        assert!(matches!(errors[1], LexerError::InvalidUtf8 { line: 1, .. }));
+    }
+
+    #[test]
+    fn lex_valid_escapes() {
+        let source = r#""\n\t\r\\\'\"\0\a\b\f\v\?\x41\101" "plain" '\n' 'x' '\x7f'"#;
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        assert_eq!(tokens[0].token_type, TokenType::StringLiteral);
+        assert_eq!(tokens[0].literal, "\n\t\r\\'\"\0\x07\x08\x0c\x0b?AA");
+        assert_eq!(tokens[1].literal, "plain");
+        assert!(matches!(tokens[1].literal, Cow::Borrowed(_)));
+        assert_eq!(tokens[2].token_type, TokenType::CharLiteral);
+        assert_eq!(tokens[2].literal, "\n");
+        assert_eq!(tokens[3].literal, "x");
+        assert_eq!(tokens[4].literal, "\x7f");
+    }
+
+    #[test]
+    fn string_bytes_decode_escapes() {
+        let source = r#""a\0b" "\x41\101\n" "\xe9é" "" 'x'"#;
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        assert_eq!(tokens[0].string_bytes(), Some(b"a\0b".to_vec()));
+        assert_eq!(tokens[0].string_size(), Some(4));
+        assert_eq!(tokens[1].string_bytes(), Some(b"AA\n".to_vec()));
+        // an escape is one byte, a character written directly its UTF-8
+        assert_eq!(tokens[2].string_bytes(), Some(vec![0xe9, 0xc3, 0xa9]));
+        assert_eq!(tokens[3].string_size(), Some(1));
+        assert_eq!(tokens[4].string_bytes(), None);
+    }
+
+    #[test]
+    fn encoding_prefixes_set_element_type() {
+        let source = r#""é" u8"é\xff" L"é😀" u"é😀\xffff" U"é😀\x1f600" L'x' u'\xffff' U'😀' Lx"#;
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let [.., prefixed, ident, _] = &tokens[..] else {
+            panic!("Expected tokens");
+        };
+        assert_eq!(prefixed.lexeme, "U'😀'");
+        assert_eq!(
+            (ident.token_type, ident.lexeme()),
+            (TokenType::Identifier, "Lx")
+        );
+
+        let sysv = TargetConfig::default();
+        let windows = TargetConfig {
+            abi: TargetAbi::Windows,
+            ..sysv
+        };
+        let expected = [
+            (Encoding::Plain, Type::Char, vec![0xc3, 0xa9]),
+            (Encoding::Utf8, Type::Char, vec![0xc3, 0xa9, 0xff]),
+            (Encoding::Wide, Type::Int, vec![0xe9, 0x1f600]),
+            (
+                Encoding::Utf16,
+                Type::UnsignedShort,
+                vec![0xe9, 0xd83d, 0xde00, 0xffff],
+            ),
+            (
+                Encoding::Utf32,
+                Type::UnsignedInt,
+                vec![0xe9, 0x1f600, 0x1f600],
+            ),
+            (Encoding::Wide, Type::Int, vec![u32::from('x')]),
+            (Encoding::Utf16, Type::UnsignedShort, vec![0xffff]),
+            (Encoding::Utf32, Type::UnsignedInt, vec![0x1f600]),
+        ];
+        for (token, (encoding, ty, units)) in tokens.iter().zip(expected) {
+            assert_eq!(token.encoding(), Some(encoding), "{}", token.lexeme);
+            assert_eq!(encoding.element_type(&sysv), ty);
+            assert_eq!(token.code_units(&sysv), Some(units), "{}", token.lexeme);
+        }
+        // only strings of `char`s have bytes
+        assert_eq!(tokens[1].string_bytes(), Some(vec![0xc3, 0xa9, 0xff]));
+        assert_eq!(tokens[2].string_bytes(), None);
+
+        // `wchar_t` is UTF-16 on Windows
+        assert_eq!(Encoding::Wide.element_type(&windows), Type::UnsignedShort);
+        assert_eq!(
+            tokens[2].code_units(&windows),
+            Some(vec![0xe9, 0xd83d, 0xde00])
+        );
+    }
+
+    #[test]
+    fn escapes_must_fit_element() {
+        for source in [
+            r#"u8"\x100""#,
+            r#"u"\x10000""#,
+            r#"U"\xd800""#,
+            r#"L'\x110000'"#,
+        ] {
+            let mut lexer = Lexer::from_string(source);
+            let errors = lexer.tokenise().expect_err("Should reject escape");
+            assert!(
+                matches!(errors[..], [LexerError::InvalidEscape { line: 1, .. }]),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn lex_invalid_escapes_point_at_backslash() {
+        let source = "\"ab\\q\"\n\"\\x\" '\\9'\n\"\\x100\" '\\qz'";
+        let mut lexer = Lexer::from_string(source);
+        let errors = lexer.tokenise().expect_err("Should reject invalid escapes");
+
+        let positions: Vec<_> = errors
+            .iter()
+            .map(|e| match e {
+                LexerError::InvalidEscape { line, col } => (*line, *col),
+                other => panic!("Expected InvalidEscape, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(positions, vec![(1, 4), (2, 2), (2, 7), (3, 2), (3, 10)]);
+    }
+
+    /// Everything about the tokens of `source`, re-lexed after `edit` from
+    /// the tokens of `old`, next to the same for a full lex
+    fn relex_and_lex(old: &str, edit: Edit) -> (Vec<String>, Vec<String>) {
+        let snapshot = |tokens: &[Token]| -> Vec<String> {
+            tokens
+                .iter()
+                .map(|t| {
+                    let (ty, lex, lit) = (t.token_type, &t.lexeme, &t.literal);
+                    format!("{ty:?} {lex:?} {lit:?} {}:{} @{}", t.line, t.col, t.offset)
+                })
+                .collect()
+        };
+        let mut old_lexer = Lexer::from_string(old).tab_width(4);
+        let old_tokens = old_lexer
+            .tokenise()
+            .expect("Should tokenise without errors");
+        let mut source = old.to_string();
+        source.replace_range(edit.range.clone(), edit.text);
+
+        let mut lexer = Lexer::from_string(&source).tab_width(4);
+        let relexed = match lexer.relex(old_tokens, &edit) {
+            Ok(tokens) => snapshot(tokens),
+            Err(_) => snapshot(&lexer.tokens),
+        };
+        let mut lexer = Lexer::from_string(&source).tab_width(4);
+        let lexed = match lexer.tokenise() {
+            Ok(tokens) => snapshot(tokens),
+            Err(_) => snapshot(&lexer.tokens),
+        };
+        (relexed, lexed)
+    }
+
+    #[test]
+    fn relex_matches_full_lex() {
+        let old = "int main(void) {\n\tint x = 2; /* two */\n\treturn x + y;\n}\n";
+        let at = |needle: &str| old.find(needle).expect("Should be in the source");
+        let edits = [
+            // a constant and a comment changed
+            (at("2;")..at("2;") + 1, "42"),
+            (at("two")..at("two") + 3, "three"),
+            // identifiers joined, and a line split in two
+            (at(" + ")..at("y;"), ""),
+            (at("x = ")..at("x = ") + 1, "x\n\t"),
+            // deleted across tokens and lines
+            (at("2;")..at("return"), ""),
+            // the rest of the file made a comment
+            (at("\treturn")..at("\treturn"), "/*"),
+            // at either end of the source
+            (0..0, "static "),
+            (0..3, "char"),
+            (old.len()..old.len(), "int z;"),
+            (old.len() - 2..old.len(), ""),
+        ];
+        for (range, text) in edits {
+            let (relexed, lexed) = relex_and_lex(old, Edit { range, text });
+            assert_eq!(relexed, lexed, "after replacing with {text:?}");
+        }
+    }
+
+    #[test]
+    fn relex_only_scans_near_the_edit() {
+        let old = "int a = 1;\nint b = 2;\nint c = 3;\n";
+        let mut old_lexer = Lexer::from_string(old);
+        let old_tokens = old_lexer
+            .tokenise()
+            .expect("Should tokenise without errors");
+        let source = old.replace("b = 2", "bb = 22");
+        let edit = Edit {
+            range: 15..20,
+            text: "bb = 22",
+        };
+
+        let mut lexer = Lexer::from_string(&source);
+        lexer.relex(old_tokens, &edit).expect("Should re-lex");
+        // scanning started at the `int` before the edit, and the `;` after it
+        // moved along its line, so it stopped at the `int` on the next
+        assert_eq!(lexer.curr_byte, 27);
+        let tokens = &lexer.tokens;
+        assert_eq!(tokens[8].lexeme, "22");
+        assert_eq!(
+            (tokens[11].lexeme(), tokens[11].line, tokens[11].offset),
+            ("c", 3, 28)
+        );
+        assert_eq!(tokens.last().map(|t| t.offset), Some(source.len()));
+    }
+
+    #[test]
+    fn positions_stay_exact_on_huge_line() {
+        // a minified file on one line of over 1 MB, with characters of more
+        // than one byte counting as one column each
+        let source = format!("{}z", "é+".repeat(350_000));
+        let mut lexer = Lexer::from_string(&source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        assert_eq!(tokens.len(), 700_002);
+        let last = &tokens[tokens.len() - 2];
+        assert_eq!(last.lexeme(), "z");
+        assert_eq!((last.line, last.col, last.offset), (1, 700_001, 1_050_000));
+        let eof = &tokens[tokens.len() - 1];
+        assert_eq!((eof.line, eof.col, eof.offset), (1, 700_002, source.len()));
+    }
+
+    #[test]
+    fn column_restarts_after_newline_in_string() {
+        let mut lexer = Lexer::from_string("\"a\nbc\" x");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        assert_eq!((tokens[1].line, tokens[1].col), (2, 5));
+    }
+
     #[test]
     fn handles_empty_input() {
         let source = "";
@@ -0,0 +1,26 @@
+pub mod analysis;
+pub mod ast;
+pub mod debug_info;
+pub mod diagnostic;
+pub mod driver;
+pub mod eval;
+pub mod fold;
+pub mod format;
+pub mod hash;
+pub mod init;
+pub mod json;
+pub mod layout;
+pub mod lexer;
+pub mod link;
+pub mod normalize;
+pub mod owned;
+pub mod parser;
+pub mod preprocessor;
+pub mod simplify;
+pub mod symbols;
+pub mod target;
+pub mod typeck;
+pub mod types;
+
+#[cfg(test)]
+mod temp_dir;
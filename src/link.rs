@@ -0,0 +1,244 @@
+//! Resolution of the symbols translation units share, reporting what a linker
+//! would: functions called but defined nowhere, and symbols defined by more
+//! than one unit
+
+use crate::analysis;
+use crate::driver::TranslationUnit;
+use crate::owned;
+use crate::parser::{Statement, StorageClass};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Symbol that doesn't resolve to exactly one definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// Function called, at its first call, but defined in no unit, or for a
+    /// `static` one, not in its own
+    Undefined {
+        name: String,
+        path: PathBuf,
+        line: usize,
+    },
+    /// Symbol defined again by another unit, at the later definition
+    MultipleDefinition {
+        name: String,
+        path: PathBuf,
+        line: usize,
+        first_path: PathBuf,
+        first_line: usize,
+    },
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::Undefined { name, path, line } => write!(
+                f,
+                "{}:{}: undefined reference to '{}'",
+                path.display(),
+                line,
+                name
+            ),
+            LinkError::MultipleDefinition {
+                name,
+                path,
+                line,
+                first_path,
+                first_line,
+            } => write!(
+                f,
+                "{}:{}: multiple definition of '{}', first defined at {}:{}",
+                path.display(),
+                line,
+                name,
+                first_path.display(),
+                first_line
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+/// Matches the functions each of `units` calls to their definitions, and
+/// checks no two units define the same symbol.
+///
+/// A unit exports every function it defines, unless a declaration of it is
+/// `static`, and every variable at file scope that isn't `static`, as there's
+/// no `extern` to declare one without defining it. A call resolves to a definition in its
+/// own unit first, then to one exported by any unit. Defining a function
+/// twice within one unit is left to [`analysis::check`].
+pub fn resolve(units: &[TranslationUnit]) -> Vec<LinkError> {
+    let programs: Vec<_> = units
+        .iter()
+        .map(|unit| owned::borrow(&unit.program))
+        .collect();
+    let mut errors = vec![];
+    let mut exported: HashMap<&str, (&Path, usize)> = HashMap::new();
+    for (unit, program) in units.iter().zip(&programs) {
+        let mut defined: HashMap<&str, usize> = HashMap::new();
+        for stmt in program {
+            let name = match stmt {
+                Statement::Function { name, .. }
+                    if !analysis::is_static(program, &name.literal) =>
+                {
+                    name
+                }
+                Statement::VarDecl { name, storage, .. } if *storage != StorageClass::Static => {
+                    name
+                }
+                _ => continue,
+            };
+            // a variable may be declared again, tentatively defining it once
+            defined.entry(&name.literal).or_insert(name.line);
+        }
+        for (name, line) in defined {
+            match exported.entry(name) {
+                Entry::Vacant(entry) => {
+                    entry.insert((&unit.path, line));
+                }
+                Entry::Occupied(entry) => {
+                    let (first_path, first_line) = *entry.get();
+                    errors.push(LinkError::MultipleDefinition {
+                        name: name.to_string(),
+                        path: unit.path.clone(),
+                        line,
+                        first_path: first_path.to_path_buf(),
+                        first_line,
+                    });
+                }
+            }
+        }
+    }
+
+    for (unit, program) in units.iter().zip(&programs) {
+        let mut undefined: HashMap<&str, usize> = HashMap::new();
+        for stmt in program {
+            if !matches!(stmt, Statement::Function { .. }) {
+                continue;
+            }
+            for callee in analysis::function_calls(stmt).into_iter().flatten() {
+                let name = &*callee.literal;
+                let local = program.iter().any(
+                    |stmt| matches!(stmt, Statement::Function { name: n, .. } if n.literal == name),
+                );
+                let resolved =
+                    local || (!analysis::is_static(program, name) && exported.contains_key(name));
+                if !resolved {
+                    let line = undefined.entry(name).or_insert(callee.line);
+                    *line = (*line).min(callee.line);
+                }
+            }
+        }
+        for (name, line) in undefined {
+            errors.push(LinkError::Undefined {
+                name: name.to_string(),
+                path: unit.path.clone(),
+                line,
+            });
+        }
+    }
+
+    // units in the order given, and each in line order
+    errors.sort_by_key(|error| match error {
+        LinkError::Undefined { name, path, line }
+        | LinkError::MultipleDefinition {
+            name, path, line, ..
+        } => {
+            let unit = units.iter().position(|unit| unit.path == *path);
+            (unit, *line, name.clone())
+        }
+    });
+    errors
+}
+
+#[cfg(test)]
+mod link_tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    /// Parses `source` into a unit at `path`, as the driver does
+    fn unit(path: &str, source: &str) -> TranslationUnit {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        TranslationUnit {
+            path: PathBuf::from(path),
+            program: owned::to_owned(&program),
+        }
+    }
+
+    #[test]
+    fn call_resolves_to_another_unit() {
+        let units = [
+            unit(
+                "main.c",
+                "int twice(int x);\nint main(void) { return twice(2); }",
+            ),
+            unit("twice.c", "int twice(int x) { return x + x; }"),
+        ];
+        assert_eq!(resolve(&units), vec![]);
+    }
+
+    #[test]
+    fn reports_unresolved_external() {
+        let units = [
+            unit(
+                "main.c",
+                "int missing(void);\nint unused(void);\n\
+                 int main(void) {\n  return missing() + missing() + helper();\n}",
+            ),
+            // static, so not exported to main.c
+            unit(
+                "helper.c",
+                "static int helper(void) { return 1; }\nint api(void) { return helper(); }",
+            ),
+        ];
+        let errors: Vec<_> = resolve(&units).iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            [
+                "main.c:4: undefined reference to 'helper'",
+                "main.c:4: undefined reference to 'missing'",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_duplicate_definition() {
+        let units = [
+            unit(
+                "a.c",
+                "int count;\nint f(void) { return 1; }\nstatic int n;",
+            ),
+            unit("b.c", "int count;\nint count;\n\nint f(void) { return 2; }"),
+            // unit-local, so no clash
+            unit(
+                "c.c",
+                "static int f(void) { return 3; }\nint g(void) { return f(); }\nstatic int n;",
+            ),
+        ];
+        assert_eq!(
+            resolve(&units),
+            vec![
+                LinkError::MultipleDefinition {
+                    name: "count".to_string(),
+                    path: PathBuf::from("b.c"),
+                    line: 1,
+                    first_path: PathBuf::from("a.c"),
+                    first_line: 1,
+                },
+                LinkError::MultipleDefinition {
+                    name: "f".to_string(),
+                    path: PathBuf::from("b.c"),
+                    line: 4,
+                    first_path: PathBuf::from("a.c"),
+                    first_line: 2,
+                },
+            ]
+        );
+    }
+}
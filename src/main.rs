@@ -1,20 +1,244 @@
+use c_comp_rs::analysis;
+use c_comp_rs::diagnostic::{Colour, Severity};
+use c_comp_rs::json;
+use c_comp_rs::lexer::{LanguageStandard, Lexer, Token};
+use c_comp_rs::parser::{Parser, Statement};
+use c_comp_rs::symbols;
+use c_comp_rs::target::TargetConfig;
 use std::fs;
-mod lexer;
-mod parser;
-use crate::lexer::Lexer;
-use crate::parser::Parser;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = std::env::args().collect();
+const USAGE: &str = "Usage: c-comp-rs [--check|--fsyntax-only] [-Werror] [-Wformat] [--no-color] [-std=c89|c99|c11|c23] [--time] [--emit=ast|tokens-json] [--ast=json|json-pretty] [--dump-symbols] <source.c>";
 
-    // let source = fs::read_to_string(&args[1]).expect("Couldn't read file '{}'");
-    let source = "int main(void){\n\treturn 0;\n}";
-    let mut lexer = Lexer::from_string(&source);
+/// What to print once the source has been processed
+#[derive(Default, PartialEq)]
+enum Emit {
+    #[default]
+    Ast,
+    /// The token stream as a JSON array, for editors and other tools
+    TokensJson,
+    /// The syntax tree as JSON, all on one line
+    AstJson,
+    /// The syntax tree as JSON, indented for reading
+    AstJsonPretty,
+    /// The names declared in each scope, nested scopes indented
+    Symbols,
+}
+
+/// Options parsed from the command line
+#[derive(Default)]
+struct Options {
+    /// Run the semantic analyses and print their diagnostics instead of the AST
+    check: bool,
+    /// Fail a check that finds warnings but no errors
+    warnings_as_errors: bool,
+    /// Also check `printf` and `scanf` calls against their format strings
+    format_warnings: bool,
+    /// Never colour diagnostics, even when stderr, which they're printed to,
+    /// is a terminal
+    no_colour: bool,
+    /// Standard whose keywords are recognised
+    standard: LanguageStandard,
+    /// Report how long each phase took
+    time: bool,
+    emit: Emit,
+    path: String,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut path = None;
+
+    for arg in args {
+        match arg.as_str() {
+            // the name editors and build tools expect from other compilers
+            "--check" | "--fsyntax-only" => options.check = true,
+            "-Werror" => options.warnings_as_errors = true,
+            "-Wformat" => options.format_warnings = true,
+            "--no-color" | "--no-colour" => options.no_colour = true,
+            "--time" => options.time = true,
+            "--emit=ast" => options.emit = Emit::Ast,
+            "--emit=tokens-json" => options.emit = Emit::TokensJson,
+            "--ast=json" => options.emit = Emit::AstJson,
+            "--ast=json-pretty" => options.emit = Emit::AstJsonPretty,
+            "--dump-symbols" => options.emit = Emit::Symbols,
+            _ if let Some(name) = arg.strip_prefix("-std=") => options.standard = name.parse()?,
+            _ if arg.starts_with('-') => return Err(format!("Unknown option '{}'", arg)),
+            _ if path.is_some() => return Err(format!("Unexpected argument '{}'", arg)),
+            _ => path = Some(arg),
+        }
+    }
+
+    options.path = path.ok_or("Missing source file argument")?;
+    Ok(options)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut timings = Timings::default();
+    let status = run(&options, &mut timings);
+    if options.time {
+        eprint!("{}", timings.table());
+    }
+    status
+}
+
+/// Wall-clock time spent in each phase, in the order they ran
+#[derive(Default)]
+struct Timings {
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    /// Runs `f`, recording its duration under `phase`
+    fn time<T>(&mut self, phase: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.phases.push((phase, start.elapsed()));
+        result
+    }
+
+    /// One line per phase with its time in milliseconds, under a header
+    fn table(&self) -> String {
+        let mut table = format!("{:<24} {:>10}\n", "phase", "ms");
+        for (phase, duration) in &self.phases {
+            table.push_str(&format!(
+                "{:<24} {:>10.3}\n",
+                phase,
+                duration.as_secs_f64() * 1000.0
+            ));
+        }
+        table
+    }
+}
+
+impl Options {
+    /// Colour for diagnostics, which go to stderr
+    fn colour(&self) -> Colour {
+        if self.no_colour {
+            Colour::Plain
+        } else {
+            Colour::detect(&std::io::stderr())
+        }
+    }
+}
+
+fn run(options: &Options, timings: &mut Timings) -> ExitCode {
+    let source = match fs::read(&options.path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Couldn't read file '{}': {}", options.path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut lexer = Lexer::from_bytes(&source).standard(options.standard);
+    let tokens = match timings.time("lex", || lexer.tokenise()) {
+        Ok(tokens) => tokens,
+        Err(errors) => {
+            for e in errors {
+                let error = Severity::Error.render(options.colour());
+                eprintln!("{}: {}: {}", options.path, error, e);
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if options.emit == Emit::TokensJson {
+        let json = timings.time("emit", || tokens_json(tokens));
+        println!("{}", json);
+        return ExitCode::SUCCESS;
+    }
+
+    let program = match timings.time("parse", || Parser::new(tokens).parse()) {
+        Ok(program) => program,
+        Err(e) => {
+            let error = Severity::Error.render(options.colour());
+            eprintln!("{}: {}: {}", options.path, error, e);
+            return ExitCode::FAILURE;
+        }
+    };
 
-    let result = lexer.tokenise().unwrap();
+    if options.check {
+        return check(options, &program, timings);
+    }
+
+    if options.emit == Emit::Symbols {
+        let table = timings.time("emit", || symbols::collect(&program).to_string());
+        print!("{}", table);
+        return ExitCode::SUCCESS;
+    }
+
+    if matches!(options.emit, Emit::AstJson | Emit::AstJsonPretty) {
+        let json = timings.time("emit", || {
+            let json = json::program(&program);
+            match options.emit {
+                Emit::AstJsonPretty => json.pretty(),
+                _ => json.compact(),
+            }
+        });
+        println!("{}", json);
+        return ExitCode::SUCCESS;
+    }
+
+    let trees: Vec<_> = timings.time("emit", || {
+        program.iter().map(Statement::print_tree).collect()
+    });
+    for tree in trees {
+        println!("{}", tree);
+    }
+    ExitCode::SUCCESS
+}
+
+/// Prints every diagnostic to stderr as `file:line: severity: message`, or
+/// `file:line:col: ...` where the column is known, in source order, the
+/// severity coloured if stderr is a terminal. Fails if any of them are errors,
+/// or any at all with `-Werror`. Each analysis is timed as a phase of its own.
+fn check(options: &Options, program: &[Statement], timings: &mut Timings) -> ExitCode {
+    let target = TargetConfig::default();
+    let mut diagnostics =
+        analysis::check_timed(program, &target, options.standard, |analysis, duration| {
+            timings.phases.push((analysis, duration))
+        });
+    if options.format_warnings {
+        let warnings = timings.time("format", || analysis::format_warnings(program));
+        diagnostics.extend(warnings);
+        diagnostics.sort();
+    }
+    let colour = options.colour();
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic.render(&options.path, colour));
+    }
+
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    let warnings = diagnostics.len() - errors;
+    if errors > 0 || (options.warnings_as_errors && warnings > 0) {
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
 
-    let mut parser = Parser::new(result);
-    let ast = parser.parse()?;
-    println!("{}", ast.print_tree());
-    Ok(())
+/// Formats tokens as a JSON array with one object per token
+fn tokens_json(tokens: &[Token]) -> String {
+    let objects: Vec<_> = tokens
+        .iter()
+        .map(|token| {
+            format!(
+                "  {{\"type\": {}, \"lexeme\": {}, \"line\": {}, \"column\": {}}}",
+                json::quote(&format!("{:?}", token.token_type())),
+                json::quote(token.lexeme()),
+                token.line(),
+                token.column()
+            )
+        })
+        .collect();
+    format!("[\n{}\n]", objects.join(",\n"))
 }
@@ -0,0 +1,327 @@
+//! Desugaring of the syntax tree into a smaller core language, so later passes
+//! have fewer constructs to handle. Nodes made up here have an empty span and
+//! synthetic tokens with no line.
+
+use crate::fold::{self, Fold};
+use crate::lexer::{Token, TokenType};
+use crate::parser::{Expr, ExprKind, Literal, Span, Statement};
+
+static ASSIGN: Token<'static> = Token::synthetic(TokenType::Equal, "=");
+static PLUS: Token<'static> = Token::synthetic(TokenType::Plus, "+");
+static MINUS: Token<'static> = Token::synthetic(TokenType::Minus, "-");
+static STAR: Token<'static> = Token::synthetic(TokenType::Star, "*");
+static SLASH: Token<'static> = Token::synthetic(TokenType::Slash, "/");
+
+/// Rewrites `program` so that
+/// * compound assignments such as `a += b` become `a = a + b`
+/// * `for` loops become a `while` loop inside a block holding the initialiser
+/// * groupings are dropped, the tree already records precedence
+pub fn normalize<'a>(program: Vec<Statement<'a>>) -> Vec<Statement<'a>> {
+    fold::fold_program(&mut Normalize, program)
+}
+
+/// Collapses each grouping directly inside another, so `((x))` is a single
+/// grouping of `x` spanning both pairs of parentheses. Unlike [`normalize`]
+/// every grouping written is still represented, once.
+pub fn flatten_groupings<'a>(program: Vec<Statement<'a>>) -> Vec<Statement<'a>> {
+    fold::fold_program(&mut FlattenGroupings, program)
+}
+
+/// Each node is rewritten after its children, so the parts it's rebuilt from
+/// are already normalized
+struct Normalize;
+
+impl<'a> Fold<'a> for Normalize {
+    fn fold_statement(&mut self, stmt: Statement<'a>) -> Statement<'a> {
+        match fold::fold_statement(self, stmt) {
+            Statement::For {
+                keyword,
+                initialiser,
+                condition,
+                increment,
+                body,
+            } => for_loop(keyword, initialiser, condition, increment, body),
+            stmt => stmt,
+        }
+    }
+
+    fn fold_expr(&mut self, expr: Expr<'a>) -> Expr<'a> {
+        let Expr { kind, span } = fold::fold_expr(self, expr);
+        let kind = match kind {
+            // keep the span of the parentheses, so errors still point at them
+            ExprKind::Grouping(inner) => return Expr { span, ..*inner },
+            ExprKind::Assign {
+                target,
+                operator,
+                value,
+            } => assignment(target, operator, value, span),
+            kind => kind,
+        };
+        Expr { kind, span }
+    }
+}
+
+/// Inner groupings are flattened first, so a grouping inside another has no
+/// grouping left inside it
+struct FlattenGroupings;
+
+impl<'a> Fold<'a> for FlattenGroupings {
+    fn fold_expr(&mut self, expr: Expr<'a>) -> Expr<'a> {
+        match fold::fold_expr(self, expr) {
+            Expr {
+                kind: ExprKind::Grouping(inner),
+                span,
+            } if matches!(inner.kind, ExprKind::Grouping(_)) => Expr {
+                kind: inner.kind,
+                span,
+            },
+            expr => expr,
+        }
+    }
+}
+
+/// `for (init; cond; incr) body` as `{ init; while (cond) { body; incr; } }`,
+/// with a missing condition taken as true. The parts are already normalized.
+fn for_loop<'a>(
+    keyword: &'a Token<'a>,
+    initialiser: Vec<Statement<'a>>,
+    condition: Option<Expr<'a>>,
+    increment: Option<Expr<'a>>,
+    body: Box<Statement<'a>>,
+) -> Statement<'a> {
+    // a `continue` would skip an increment moved into the body
+    if increment.is_some() && continues(&body) {
+        return Statement::For {
+            keyword,
+            initialiser,
+            condition,
+            increment,
+            body,
+        };
+    }
+
+    let condition = condition.unwrap_or(Expr {
+        kind: ExprKind::Literal(Literal::Int(1)),
+        span: Span::default(),
+    });
+    let body = match increment {
+        Some(increment) => Box::new(Statement::Block(vec![
+            *body,
+            Statement::Expression(increment),
+        ])),
+        None => body,
+    };
+    let while_loop = Statement::While {
+        keyword,
+        condition,
+        body,
+    };
+    // the block keeps a variable declared by the initialiser local to the loop
+    Statement::Block(initialiser.into_iter().chain([while_loop]).collect())
+}
+
+/// Whether `stmt` contains a `continue` belonging to the loop around it
+fn continues(stmt: &Statement) -> bool {
+    match stmt {
+        Statement::Continue(_) => true,
+        Statement::Block(body) => body.iter().any(continues),
+        Statement::If {
+            then_branch,
+            else_branch,
+            ..
+        } => continues(then_branch) || else_branch.as_deref().is_some_and(continues),
+        Statement::Switch { body, .. }
+        | Statement::Case { body, .. }
+        | Statement::Default { body, .. }
+        | Statement::Labeled { body, .. } => continues(body),
+        // a `continue` in a nested loop belongs to that loop
+        Statement::While { .. } | Statement::For { .. } => false,
+        Statement::Expression(_)
+        | Statement::Return { .. }
+        | Statement::VarDecl { .. }
+        | Statement::Struct { .. }
+        | Statement::Enum { .. }
+        | Statement::Function { .. }
+        | Statement::Prototype { .. }
+        | Statement::StaticAssert { .. }
+        | Statement::Goto { .. }
+        | Statement::Break(_) => false,
+    }
+}
+
+/// `a op= b` as `a = a op b`. The target is evaluated twice, so this is only
+/// done when that has no side effects.
+fn assignment<'a>(
+    target: Box<Expr<'a>>,
+    operator: &'a Token<'a>,
+    value: Box<Expr<'a>>,
+    span: Span,
+) -> ExprKind<'a> {
+    let binary_operator = match operator.token_type {
+        TokenType::PlusEqual => Some(&PLUS),
+        TokenType::MinusEqual => Some(&MINUS),
+        TokenType::StarEqual => Some(&STAR),
+        TokenType::SlashEqual => Some(&SLASH),
+        _ => None,
+    };
+    match binary_operator {
+        Some(binary_operator) if is_pure(&target) => ExprKind::Assign {
+            target: target.clone(),
+            operator: &ASSIGN,
+            value: Box::new(Expr {
+                kind: ExprKind::Binary {
+                    left: target,
+                    operator: binary_operator,
+                    right: value,
+                },
+                span,
+            }),
+        },
+        _ => ExprKind::Assign {
+            target,
+            operator,
+            value,
+        },
+    }
+}
+
+/// Whether evaluating `expr` twice would behave the same as evaluating it once
+fn is_pure(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Identifier(_) | ExprKind::Literal(_) => true,
+        ExprKind::Member { object, .. } | ExprKind::Grouping(object) => is_pure(object),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod normalize_tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn parse_with<T>(source: &str, f: impl FnOnce(Vec<Statement>) -> T) -> T {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        f(Parser::new(tokens).parse().expect("Should parse"))
+    }
+
+    /// Hash of the normalized program, to compare against the expected shape
+    fn normalized_hash(source: &str) -> u64 {
+        parse_with(source, |program| hash(&normalize(program)))
+    }
+
+    fn parsed_hash(source: &str) -> u64 {
+        parse_with(source, |program| hash(&program))
+    }
+
+    fn hash(program: &[Statement]) -> u64 {
+        let [statement] = program else {
+            panic!("Expected a single declaration");
+        };
+        statement.structural_hash()
+    }
+
+    #[test]
+    fn for_loop_becomes_while_loop_in_block() {
+        let for_loop =
+            normalized_hash("void f(int x) { for (int i = 0; i < 10; i += 1) x = x + i; }");
+        let while_loop = parsed_hash(
+            "void f(int x) { { int i = 0; while (i < 10) { x = x + i; i = i + 1; } } }",
+        );
+        assert_eq!(for_loop, while_loop);
+    }
+
+    #[test]
+    fn empty_for_clauses() {
+        let for_loop = normalized_hash("void f(void) { for (;;) { } }");
+        let while_loop = parsed_hash("void f(void) { { while (1) { } } }");
+        assert_eq!(for_loop, while_loop);
+    }
+
+    #[test]
+    fn for_loop_with_continue_is_kept() {
+        let source = "void f(int x) { for (; x; x -= 1) { if (x) continue; } }";
+        parse_with(source, |program| {
+            let [Statement::Function { body, .. }] = &normalize(program)[..] else {
+                panic!("Expected a function");
+            };
+            let [Statement::For { increment, .. }] = &body[..] else {
+                panic!("Expected the loop to stay a for loop");
+            };
+            // the clauses are still normalized
+            let Some(ExprKind::Assign { operator, .. }) = increment.as_ref().map(|e| &e.kind)
+            else {
+                panic!("Expected an assignment");
+            };
+            assert_eq!(operator.token_type, TokenType::Equal);
+        });
+    }
+
+    #[test]
+    fn groupings_are_removed() {
+        let mut lexer = Lexer::from_string("((1 + 2)) * 3");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+
+        let ExprKind::Binary { left, .. } = Normalize.fold_expr(expr).kind else {
+            panic!("Expected a binary expression");
+        };
+        assert!(matches!(left.kind, ExprKind::Binary { .. }));
+        // the sum still covers its parentheses
+        assert_eq!(left.span, Span { start: 0, end: 7 });
+    }
+
+    /// Runs `f` on the expression `source` with its nested groupings flattened
+    fn flattened<T>(source: &str, f: impl FnOnce(Expr) -> T) -> T {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+        f(FlattenGroupings.fold_expr(expr))
+    }
+
+    #[test]
+    fn nested_groupings_are_flattened() {
+        flattened("((x))", |expr| {
+            let ExprKind::Grouping(inner) = expr.kind else {
+                panic!("Expected a grouping");
+            };
+            assert!(matches!(inner.kind, ExprKind::Identifier(_)));
+            assert_eq!(expr.span, Span { start: 0, end: 5 });
+        });
+
+        // a grouping that's needed for precedence stays
+        flattened("(a + b) * c", |expr| {
+            let ExprKind::Binary { left, .. } = expr.kind else {
+                panic!("Expected a binary expression");
+            };
+            let ExprKind::Grouping(sum) = left.kind else {
+                panic!("Expected a grouping");
+            };
+            assert!(matches!(sum.kind, ExprKind::Binary { .. }));
+        });
+    }
+
+    #[test]
+    fn volatile_accesses_are_kept() {
+        // each read and store happens as written, even the repeated ones
+        let source = "void f(void) { volatile int v = 0; v; v; v = 1; v = 1; }";
+        assert_eq!(normalized_hash(source), parsed_hash(source));
+        assert_eq!(
+            normalized_hash("void f(volatile int v) { v += 1; }"),
+            parsed_hash("void f(volatile int v) { v = v + 1; }")
+        );
+    }
+
+    #[test]
+    fn compound_assignment_becomes_plain_assignment() {
+        assert_eq!(
+            normalized_hash("void f(int x) { x *= (x - 1); }"),
+            normalized_hash("void f(int x) { x = x * (x - 1); }")
+        );
+    }
+}
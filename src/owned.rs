@@ -0,0 +1,420 @@
+//! Copies of the syntax tree that own their tokens, for keeping a tree after
+//! the source text and tokens it was parsed from are gone
+
+use crate::lexer::Token;
+use crate::parser::{
+    Designator, Enumerator, Expr, ExprKind, InitItem, Initialiser, Literal, Member, Parameter,
+    Statement,
+};
+use std::borrow::Cow;
+
+/// Expression that owns its tokens rather than borrowing them
+pub type OwnedExpr = Expr<'static, Token<'static>>;
+
+/// Statement that owns its tokens rather than borrowing them
+pub type OwnedStatement = Statement<'static, Token<'static>>;
+
+/// Copies `program`, along with every token and string it refers to
+pub fn to_owned(program: &[Statement]) -> Vec<OwnedStatement> {
+    let copier = Copier {
+        token: own_token,
+        text: own_text,
+    };
+    copier.body(program)
+}
+
+/// The tree `program` as an ordinary one, borrowing its tokens and strings
+/// rather than copying them, for the passes that take one
+pub fn borrow(program: &[OwnedStatement]) -> Vec<Statement<'_>> {
+    let copier = Copier {
+        token: borrow_token,
+        text: Cow::Borrowed,
+    };
+    copier.body(program)
+}
+
+fn own_token(token: &&Token) -> Token<'static> {
+    token.to_static()
+}
+
+fn own_text(text: &str) -> Cow<'static, str> {
+    Cow::Owned(text.to_string())
+}
+
+fn borrow_token<'s>(token: &'s Token<'static>) -> &'s Token<'s> {
+    token
+}
+
+/// Rebuilds a tree whose tokens are `S`, borrowed for `'s`, as one whose
+/// tokens are `D` and whose strings live for `'o`
+struct Copier<'s, 'o, S, D> {
+    token: fn(&'s S) -> D,
+    text: fn(&'s str) -> Cow<'o, str>,
+}
+
+impl<'s, 'o, S, D> Copier<'s, 'o, S, D> {
+    fn token(&self, token: &'s S) -> D {
+        (self.token)(token)
+    }
+
+    fn body(&self, stmts: &'s [Statement<'_, S>]) -> Vec<Statement<'o, D>> {
+        stmts.iter().map(|stmt| self.statement(stmt)).collect()
+    }
+
+    fn boxed(&self, stmt: &'s Statement<'_, S>) -> Box<Statement<'o, D>> {
+        Box::new(self.statement(stmt))
+    }
+
+    fn statement(&self, stmt: &'s Statement<'_, S>) -> Statement<'o, D> {
+        match stmt {
+            Statement::Expression(expr) => Statement::Expression(self.expression(expr)),
+            Statement::Block(stmts) => Statement::Block(self.body(stmts)),
+            Statement::If {
+                keyword,
+                condition,
+                then_branch,
+                else_branch,
+            } => Statement::If {
+                keyword: self.token(keyword),
+                condition: self.expression(condition),
+                then_branch: self.boxed(then_branch),
+                else_branch: else_branch.as_deref().map(|stmt| self.boxed(stmt)),
+            },
+            Statement::While {
+                keyword,
+                condition,
+                body,
+            } => Statement::While {
+                keyword: self.token(keyword),
+                condition: self.expression(condition),
+                body: self.boxed(body),
+            },
+            Statement::For {
+                keyword,
+                initialiser,
+                condition,
+                increment,
+                body,
+            } => Statement::For {
+                keyword: self.token(keyword),
+                initialiser: self.body(initialiser),
+                condition: condition.as_ref().map(|expr| self.expression(expr)),
+                increment: increment.as_ref().map(|expr| self.expression(expr)),
+                body: self.boxed(body),
+            },
+            Statement::Switch {
+                keyword,
+                condition,
+                body,
+            } => Statement::Switch {
+                keyword: self.token(keyword),
+                condition: self.expression(condition),
+                body: self.boxed(body),
+            },
+            Statement::Case {
+                keyword,
+                value,
+                body,
+            } => Statement::Case {
+                keyword: self.token(keyword),
+                value: self.expression(value),
+                body: self.boxed(body),
+            },
+            Statement::Default { keyword, body } => Statement::Default {
+                keyword: self.token(keyword),
+                body: self.boxed(body),
+            },
+            Statement::Break(keyword) => Statement::Break(self.token(keyword)),
+            Statement::Continue(keyword) => Statement::Continue(self.token(keyword)),
+            Statement::Goto { keyword, label } => Statement::Goto {
+                keyword: self.token(keyword),
+                label: self.token(label),
+            },
+            Statement::Labeled { label, body } => Statement::Labeled {
+                label: self.token(label),
+                body: self.boxed(body),
+            },
+            Statement::Return { keyword, value } => Statement::Return {
+                keyword: self.token(keyword),
+                value: value.as_ref().map(|expr| self.expression(expr)),
+            },
+            Statement::VarDecl {
+                ty,
+                name,
+                storage,
+                initialiser,
+                implicit_int,
+            } => Statement::VarDecl {
+                ty: ty.clone(),
+                name: self.token(name),
+                storage: *storage,
+                initialiser: initialiser.as_ref().map(|init| self.initialiser(init)),
+                implicit_int: *implicit_int,
+            },
+            Statement::StaticAssert {
+                keyword,
+                condition,
+                message,
+            } => Statement::StaticAssert {
+                keyword: self.token(keyword),
+                condition: self.expression(condition),
+                message: (self.text)(message),
+            },
+            Statement::Struct {
+                name,
+                members,
+                is_union,
+            } => Statement::Struct {
+                name: self.token(name),
+                is_union: *is_union,
+                members: members
+                    .iter()
+                    .map(|member| Member {
+                        ty: member.ty.clone(),
+                        name: member.name.as_ref().map(|name| self.token(name)),
+                        width: member.width.as_ref().map(|width| self.expression(width)),
+                    })
+                    .collect(),
+            },
+            Statement::Enum {
+                keyword,
+                name,
+                enumerators,
+            } => Statement::Enum {
+                keyword: self.token(keyword),
+                name: name.as_ref().map(|name| self.token(name)),
+                enumerators: enumerators
+                    .iter()
+                    .map(|enumerator| Enumerator {
+                        name: self.token(&enumerator.name),
+                        value: enumerator
+                            .value
+                            .as_ref()
+                            .map(|value| self.expression(value)),
+                    })
+                    .collect(),
+            },
+            Statement::Function {
+                return_type,
+                name,
+                params,
+                body,
+                variadic,
+                has_prototype,
+                implicit_int,
+                specifiers,
+            } => Statement::Function {
+                return_type: return_type.clone(),
+                name: self.token(name),
+                params: self.parameters(params),
+                body: self.body(body),
+                variadic: *variadic,
+                has_prototype: *has_prototype,
+                implicit_int: *implicit_int,
+                specifiers: *specifiers,
+            },
+            Statement::Prototype {
+                return_type,
+                name,
+                params,
+                variadic,
+                has_prototype,
+                specifiers,
+            } => Statement::Prototype {
+                return_type: return_type.clone(),
+                name: self.token(name),
+                params: self.parameters(params),
+                variadic: *variadic,
+                has_prototype: *has_prototype,
+                specifiers: *specifiers,
+            },
+        }
+    }
+
+    fn parameters(&self, params: &'s [Parameter<S>]) -> Vec<Parameter<D>> {
+        params
+            .iter()
+            .map(|param| Parameter {
+                ty: param.ty.clone(),
+                name: self.token(&param.name),
+            })
+            .collect()
+    }
+
+    fn initialiser(&self, init: &'s Initialiser<'_, S>) -> Initialiser<'o, D> {
+        match init {
+            Initialiser::Expr(expr) => Initialiser::Expr(self.expression(expr)),
+            Initialiser::List { brace, items } => Initialiser::List {
+                brace: self.token(brace),
+                items: items
+                    .iter()
+                    .map(|item| InitItem {
+                        designator: item.designator.as_ref().map(|designator| match designator {
+                            Designator::Member(member) => Designator::Member(self.token(member)),
+                            Designator::Index(index) => Designator::Index(self.expression(index)),
+                        }),
+                        value: self.initialiser(&item.value),
+                    })
+                    .collect(),
+            },
+        }
+    }
+
+    fn boxed_expr(&self, expr: &'s Expr<'_, S>) -> Box<Expr<'o, D>> {
+        Box::new(self.expression(expr))
+    }
+
+    fn expression(&self, expr: &'s Expr<'_, S>) -> Expr<'o, D> {
+        let kind = match &expr.kind {
+            ExprKind::Binary {
+                left,
+                operator,
+                right,
+            } => ExprKind::Binary {
+                left: self.boxed_expr(left),
+                operator: self.token(operator),
+                right: self.boxed_expr(right),
+            },
+            ExprKind::Unary { operator, right } => ExprKind::Unary {
+                operator: self.token(operator),
+                right: self.boxed_expr(right),
+            },
+            ExprKind::Cast { paren, ty, expr } => ExprKind::Cast {
+                paren: self.token(paren),
+                ty: ty.clone(),
+                expr: self.boxed_expr(expr),
+            },
+            ExprKind::Ternary {
+                condition,
+                question,
+                then_branch,
+                else_branch,
+            } => ExprKind::Ternary {
+                condition: self.boxed_expr(condition),
+                question: self.token(question),
+                then_branch: self.boxed_expr(then_branch),
+                else_branch: self.boxed_expr(else_branch),
+            },
+            ExprKind::Member {
+                object,
+                operator,
+                member,
+            } => ExprKind::Member {
+                object: self.boxed_expr(object),
+                operator: self.token(operator),
+                member: self.token(member),
+            },
+            ExprKind::Call {
+                callee,
+                paren,
+                arguments,
+            } => ExprKind::Call {
+                callee: self.boxed_expr(callee),
+                paren: self.token(paren),
+                arguments: arguments.iter().map(|arg| self.expression(arg)).collect(),
+            },
+            ExprKind::Assign {
+                target,
+                operator,
+                value,
+            } => ExprKind::Assign {
+                target: self.boxed_expr(target),
+                operator: self.token(operator),
+                value: self.boxed_expr(value),
+            },
+            ExprKind::Generic {
+                keyword,
+                controlling,
+                assocs,
+            } => ExprKind::Generic {
+                keyword: self.token(keyword),
+                controlling: self.boxed_expr(controlling),
+                assocs: assocs
+                    .iter()
+                    .map(|(ty, expr)| (ty.clone(), self.expression(expr)))
+                    .collect(),
+            },
+            ExprKind::SizeOf { keyword, ty } => ExprKind::SizeOf {
+                keyword: self.token(keyword),
+                ty: ty.clone(),
+            },
+            ExprKind::StmtExpr(stmts) => ExprKind::StmtExpr(self.body(stmts)),
+            ExprKind::Literal(literal) => ExprKind::Literal(match literal {
+                Literal::Int(value) => Literal::Int(*value),
+                Literal::Float(value) => Literal::Float(*value),
+                Literal::Str(value) => Literal::Str((self.text)(value)),
+            }),
+            ExprKind::Identifier(name) => ExprKind::Identifier(self.token(name)),
+            ExprKind::Grouping(inner) => ExprKind::Grouping(self.boxed_expr(inner)),
+        };
+        Expr {
+            kind,
+            span: expr.span,
+        }
+    }
+}
+
+#[cfg(test)]
+mod owned_tests {
+    use super::*;
+    use crate::lexer::{Lexer, TokenType};
+    use crate::parser::Parser;
+
+    #[test]
+    fn owned_tree_outlives_source() {
+        let program = {
+            let source =
+                String::from("int main(void) { char* s = \"hi\" \" there\"; return 1 + x; }");
+            let mut lexer = Lexer::from_string(&source);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            let program = Parser::new(tokens).parse().expect("Should parse");
+            to_owned(&program)
+        };
+
+        let [Statement::Function { name, body, .. }] = program.as_slice() else {
+            panic!("Expected a single function");
+        };
+        assert_eq!(name.lexeme(), "main");
+        let [
+            Statement::VarDecl { initialiser, .. },
+            Statement::Return { value, .. },
+        ] = body.as_slice()
+        else {
+            panic!("Expected a declaration and a return");
+        };
+        let Some(Initialiser::Expr(Expr {
+            kind: ExprKind::Literal(Literal::Str(text)),
+            ..
+        })) = initialiser
+        else {
+            panic!("Expected a string initialiser");
+        };
+        assert_eq!(text, "hi there");
+        let Some(Expr {
+            kind: ExprKind::Binary {
+                operator, right, ..
+            },
+            ..
+        }) = value
+        else {
+            panic!("Expected a binary return value");
+        };
+        assert_eq!(operator.token_type(), TokenType::Plus);
+        let ExprKind::Identifier(x) = &right.kind else {
+            panic!("Expected an identifier");
+        };
+        assert_eq!((x.lexeme(), x.line()), ("x", 1));
+    }
+
+    #[test]
+    fn borrowed_tree_matches_original() {
+        let (hash, program) = {
+            let source = String::from("int f(int a) { return a ? f(a - 1) : sizeof(int); }");
+            let mut lexer = Lexer::from_string(&source);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            let program = Parser::new(tokens).parse().expect("Should parse");
+            (program[0].structural_hash(), to_owned(&program))
+        };
+        assert_eq!(borrow(&program)[0].structural_hash(), hash);
+    }
+}
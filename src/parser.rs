@@ -1,12 +1,17 @@
-use crate::lexer::{Token, TokenType};
+use crate::ast;
+use crate::diagnostic::Diagnostic;
+use crate::eval::{self, Enumerators, EvalError};
+use crate::layout::Layouts;
+use crate::lexer::{self, Token, TokenType};
+use crate::target::TargetConfig;
+use crate::types::{Qualifiers, Type};
 use core::fmt;
+use std::borrow::Cow;
 use std::error::Error;
 use std::fmt::Write;
-use std::iter::Peekable;
-use std::slice::Iter;
 
 #[derive(Debug)]
-pub(crate) enum ParserError {
+pub enum ParserError {
     UnclosedParen,
     UnknownPrimaryToken {
         line: usize,
@@ -19,7 +24,81 @@ pub(crate) enum ParserError {
         found: Option<TokenType>,
         message: String,
     },
+    /// Any of several tokens would have been valid, e.g. `,` or `;` after a
+    /// declarator
+    ExpectedOneOf {
+        line: usize,
+        expected: Vec<TokenType>,
+        found: Option<TokenType>,
+    },
     UnexpectedEOF,
+    InvalidConstant {
+        line: usize,
+        text: String,
+    },
+    /// Expressions nested deeper than the parser's limit, which would
+    /// otherwise risk overflowing the stack
+    NestingTooDeep {
+        line: usize,
+    },
+    /// The left side of an assignment isn't something that can be assigned to
+    InvalidAssignmentTarget {
+        line: usize,
+    },
+    /// Assignment where only a constant expression is allowed, such as a
+    /// `case` label
+    AssignmentInConstant {
+        line: usize,
+    },
+    /// `restrict` applied to something other than a pointer
+    InvalidRestrict {
+        line: usize,
+    },
+    /// `...` as the only parameter, with no named one before it
+    LoneEllipsis {
+        line: usize,
+    },
+    /// Declaration with no type specifier, in strict mode
+    ImplicitInt {
+        line: usize,
+    },
+    /// Type specifiers that don't name a type together, e.g. `short long` or
+    /// `unsigned float`
+    InvalidTypeSpecifiers {
+        line: usize,
+    },
+    /// Bit-field member of a type other than an integer type
+    InvalidBitField {
+        line: usize,
+        ty: Type,
+    },
+    /// Array length that isn't a positive integer constant
+    InvalidArrayLength {
+        line: usize,
+    },
+    /// Array length only known at run time, as in `int a[n];`, which C99
+    /// allows but nothing here can lay out
+    VariableLengthArray {
+        line: usize,
+    },
+    /// `__func__` used outside of a function body
+    FuncOutsideFunction {
+        line: usize,
+    },
+    /// `inline` or `_Noreturn` on something other than a function
+    MisplacedFunctionSpecifier {
+        line: usize,
+        specifier: TokenType,
+    },
+    /// `register` on a function or at file scope, or along with `static`
+    InvalidStorageClass {
+        line: usize,
+        specifier: TokenType,
+    },
+    /// Recovery gave up after `limit` errors, leaving the rest unparsed
+    TooManyErrors {
+        limit: usize,
+    },
 }
 
 impl fmt::Display for ParserError {
@@ -30,13 +109,107 @@ impl fmt::Display for ParserError {
             ParserError::UnknownPrimaryToken { line, token_type } => {
                 write!(
                     f,
-                    "On line {}, unknown primary token '{:?}'",
+                    "On line {}, unknown primary token '{}'",
                     line, token_type
                 )
             }
             ParserError::ExpectedToken {
                 expected, found, ..
-            } => write!(f, "Expected token '{:?}', found '{:?}'", expected, found),
+            } => match found {
+                Some(found) => write!(f, "Expected token '{}', found '{}'", expected, found),
+                None => write!(f, "Expected token '{}', found end of input", expected),
+            },
+            ParserError::ExpectedOneOf {
+                line,
+                expected,
+                found,
+            } => {
+                let expected: Vec<_> = expected.iter().map(|t| format!("`{}`", t)).collect();
+                let found = match found {
+                    Some(found) if *found != TokenType::EOF => format!("`{}`", found),
+                    _ => "end of input".to_string(),
+                };
+                write!(
+                    f,
+                    "On line {}, expected one of {} but found {}",
+                    line,
+                    expected.join(", "),
+                    found
+                )
+            }
+            ParserError::InvalidConstant { line, text } => {
+                write!(f, "On line {}, invalid constant '{}'", line, text)
+            }
+            ParserError::NestingTooDeep { line } => {
+                write!(f, "On line {}, expression is nested too deeply", line)
+            }
+            ParserError::InvalidAssignmentTarget { line } => {
+                write!(f, "On line {}, invalid assignment target", line)
+            }
+            ParserError::AssignmentInConstant { line } => {
+                write!(f, "On line {}, assignment in a constant expression", line)
+            }
+            ParserError::InvalidRestrict { line } => {
+                write!(f, "On line {}, restrict requires a pointer type", line)
+            }
+            ParserError::LoneEllipsis { line } => {
+                write!(f, "On line {}, '...' must follow a named parameter", line)
+            }
+            ParserError::ImplicitInt { line } => {
+                write!(
+                    f,
+                    "On line {}, type specifier missing, defaults to 'int'",
+                    line
+                )
+            }
+            ParserError::InvalidTypeSpecifiers { line } => {
+                write!(
+                    f,
+                    "On line {}, invalid combination of type specifiers",
+                    line
+                )
+            }
+            ParserError::InvalidBitField { line, ty } => {
+                write!(
+                    f,
+                    "On line {}, bit-field has non-integer type '{}'",
+                    line, ty
+                )
+            }
+            ParserError::InvalidArrayLength { line } => {
+                write!(
+                    f,
+                    "On line {}, array length must be a positive integer constant",
+                    line
+                )
+            }
+            ParserError::VariableLengthArray { line } => {
+                write!(
+                    f,
+                    "On line {}, variable-length arrays aren't supported",
+                    line
+                )
+            }
+            ParserError::FuncOutsideFunction { line } => {
+                write!(
+                    f,
+                    "On line {}, '__func__' is not defined outside of function scope",
+                    line
+                )
+            }
+            ParserError::MisplacedFunctionSpecifier { line, specifier } => {
+                write!(
+                    f,
+                    "On line {}, '{}' can only appear on functions",
+                    line, specifier
+                )
+            }
+            ParserError::InvalidStorageClass { line, specifier } => {
+                write!(f, "On line {}, '{}' is not allowed here", line, specifier)
+            }
+            ParserError::TooManyErrors { limit } => {
+                write!(f, "too many errors ({}), stopping now", limit)
+            }
             ParserError::NoPreviousToken => write!(f, "No previous token"),
             ParserError::UnknownError => write!(f, "You're on your own pal"),
         }
@@ -45,38 +218,558 @@ impl fmt::Display for ParserError {
 
 impl Error for ParserError {}
 
+/// Value of a literal expression, decoded from its token
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal<'a> {
+    Int(i64),
+    Float(f64),
+    /// Borrowed from the token unless adjacent literals had to be joined
+    Str(Cow<'a, str>),
+}
+
+/// Range of tokens a node was parsed from, as indices into the slice given to
+/// the `Parser`. `end` is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Smallest span covering both `self` and `other`
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// The tokens covered by the span
+    pub fn tokens<'t, 'a>(self, tokens: &'t [Token<'a>]) -> &'t [Token<'a>] {
+        &tokens[self.start..self.end]
+    }
+}
+
+/// Expression node of the syntax tree along with the tokens it spans
+#[derive(Clone, Debug)]
+pub struct Expr<'a, T = &'a Token<'a>> {
+    pub kind: ExprKind<'a, T>,
+    pub span: Span,
+}
+
 /// Representation of expression objects for creation of syntax tree. Contains
-/// five types of expression objects:
+/// thirteen types of expression objects:
 /// * **Binary**: standard binary expression of <left> <operator> <right> (e.g.
-/// 1 + 2)
+///   1 + 2)
 /// * **Unary**: unary expression of form <operator> <right> (e.g. -1).
+/// * **Ternary**: conditional expression of form <condition> ? <then> : <else>
+/// * **Member**: member access of form <object>.<member> or <object>-><member>
+/// * **Call**: function call of form <callee>(<arguments>)
+/// * **Assign**: assignment of form <target> = <value>, or a compound
+///   assignment such as <target> += <value>
+/// * **Cast**: conversion of form (<type>) <expr>
+/// * **Generic**: type-based selection of form _Generic(<controlling>,
+///   <type>: <expr>, ..., default: <expr>)
+/// * **SizeOf**: size in bytes of a type, of form sizeof(<type>)
+/// * **StmtExpr**: GNU statement expression of form ({ <stmt>; ... <expr>; }),
+///   whose value is that of the final expression statement
 ///
 /// The remaining three are holding patterns for **Literal** (e.g. string or
 /// numbers), **Identifier** (i.e. `int foo`) and **Grouping** (expressions
 /// within parentheses)
-pub(crate) enum Expr<'a> {
+#[derive(Clone, Debug)]
+pub enum ExprKind<'a, T = &'a Token<'a>> {
     Binary {
-        left: Box<Expr<'a>>,
-        operator: &'a Token<'a>,
-        right: Box<Expr<'a>>,
+        left: Box<Expr<'a, T>>,
+        operator: T,
+        right: Box<Expr<'a, T>>,
     },
     Unary {
-        operator: &'a Token<'a>,
-        right: Box<Expr<'a>>,
+        operator: T,
+        right: Box<Expr<'a, T>>,
+    },
+    Ternary {
+        condition: Box<Expr<'a, T>>,
+        question: T,
+        then_branch: Box<Expr<'a, T>>,
+        else_branch: Box<Expr<'a, T>>,
+    },
+    Member {
+        object: Box<Expr<'a, T>>,
+        /// Either `.` or `->`
+        operator: T,
+        member: T,
+    },
+    Call {
+        callee: Box<Expr<'a, T>>,
+        /// The closing `)`, used to report errors
+        paren: T,
+        arguments: Vec<Expr<'a, T>>,
+    },
+    Assign {
+        target: Box<Expr<'a, T>>,
+        /// `=` or one of the compound operators such as `+=`
+        operator: T,
+        value: Box<Expr<'a, T>>,
+    },
+    Cast {
+        /// The opening `(`
+        paren: T,
+        ty: Type,
+        expr: Box<Expr<'a, T>>,
+    },
+    Generic {
+        keyword: T,
+        controlling: Box<Expr<'a, T>>,
+        /// Each association's type, `None` for `default`, and its expression
+        assocs: Vec<(Option<Type>, Expr<'a, T>)>,
+    },
+    /// `sizeof` applied to a parenthesised type name
+    SizeOf {
+        keyword: T,
+        ty: Type,
+    },
+    StmtExpr(Vec<Statement<'a, T>>),
+    Literal(Literal<'a>),
+    Identifier(T),
+    Grouping(Box<Expr<'a, T>>),
+}
+
+#[derive(Clone, Debug)]
+pub enum Statement<'a, T = &'a Token<'a>> {
+    Expression(Expr<'a, T>),
+    Block(Vec<Statement<'a, T>>),
+    If {
+        keyword: T,
+        condition: Expr<'a, T>,
+        then_branch: Box<Statement<'a, T>>,
+        else_branch: Option<Box<Statement<'a, T>>>,
+    },
+    While {
+        keyword: T,
+        condition: Expr<'a, T>,
+        body: Box<Statement<'a, T>>,
     },
-    Literal(&'a str),
-    Identifier(&'a Token<'a>),
-    Grouping(Box<Expr<'a>>),
+    /// `for (initialiser; condition; increment) body`, where each clause may
+    /// be left out
+    For {
+        keyword: T,
+        /// Statements run once before the loop: nothing, an expression
+        /// statement, or one declaration per declarator of `int i = 0, j = 1`
+        initialiser: Vec<Statement<'a, T>>,
+        condition: Option<Expr<'a, T>>,
+        increment: Option<Expr<'a, T>>,
+        body: Box<Statement<'a, T>>,
+    },
+    Switch {
+        keyword: T,
+        condition: Expr<'a, T>,
+        body: Box<Statement<'a, T>>,
+    },
+    Case {
+        keyword: T,
+        value: Expr<'a, T>,
+        body: Box<Statement<'a, T>>,
+    },
+    Default {
+        keyword: T,
+        body: Box<Statement<'a, T>>,
+    },
+    Break(T),
+    Continue(T),
+    Goto {
+        keyword: T,
+        label: T,
+    },
+    /// Statement with a label that a `goto` can jump to, e.g. `end: return 0;`
+    Labeled {
+        label: T,
+        body: Box<Statement<'a, T>>,
+    },
+    Return {
+        keyword: T,
+        value: Option<Expr<'a, T>>,
+    },
+    VarDecl {
+        ty: Type,
+        name: T,
+        storage: StorageClass,
+        initialiser: Option<Initialiser<'a, T>>,
+        /// Whether the type was left out after a storage class, as pre-C99
+        /// code may do with `static x;`, so defaulted to `int`
+        implicit_int: bool,
+    },
+    /// Definition of a structure type, e.g. `struct point { int x; int y; };`,
+    /// or of a union type, whose members all share the same storage
+    Struct {
+        name: T,
+        members: Vec<Member<'a, T>>,
+        is_union: bool,
+    },
+    /// Definition of an enumeration, e.g. `enum colour { RED, GREEN = 2 };`,
+    /// whose tag may be left out
+    Enum {
+        keyword: T,
+        name: Option<T>,
+        enumerators: Vec<Enumerator<'a, T>>,
+    },
+    Function {
+        return_type: Type,
+        name: T,
+        params: Vec<Parameter<T>>,
+        body: Vec<Statement<'a, T>>,
+        /// Whether the parameters end in `...`, accepting any further arguments
+        variadic: bool,
+        /// Whether the parameters were declared, if only as `(void)`, rather
+        /// than the parentheses left empty as in `int f()`, which before C23
+        /// says nothing about them
+        has_prototype: bool,
+        /// Whether the return type was left out, as pre-C99 code may do with
+        /// `main() { ... }`, so defaulted to `int`
+        implicit_int: bool,
+        specifiers: FunctionSpecifiers,
+    },
+    /// Declaration of a function without its body, e.g. `int f(int x, ...);`
+    Prototype {
+        return_type: Type,
+        name: T,
+        params: Vec<Parameter<T>>,
+        variadic: bool,
+        has_prototype: bool,
+        specifiers: FunctionSpecifiers,
+    },
+    /// `_Static_assert(sizeof(int) == 4, "message");`, checked once the
+    /// condition is folded to a constant
+    StaticAssert {
+        keyword: T,
+        condition: Expr<'a, T>,
+        message: Cow<'a, str>,
+    },
+}
+
+/// Value a variable is initialised with, either a single expression or a
+/// brace-enclosed list as in `int a[2] = {1, 2};`
+#[derive(Clone, Debug)]
+pub enum Initialiser<'a, T = &'a Token<'a>> {
+    Expr(Expr<'a, T>),
+    List {
+        /// The opening `{`
+        brace: T,
+        items: Vec<InitItem<'a, T>>,
+    },
+}
+
+/// Entry of an initialiser list, e.g. `2` or `.y = 2` in `{1, .y = 2}`
+#[derive(Clone, Debug)]
+pub struct InitItem<'a, T = &'a Token<'a>> {
+    pub designator: Option<Designator<'a, T>>,
+    pub value: Initialiser<'a, T>,
+}
+
+/// Names the member or element an initialiser list entry sets
+#[derive(Clone, Debug)]
+pub enum Designator<'a, T = &'a Token<'a>> {
+    /// `.member = value`
+    Member(T),
+    /// `[index] = value`
+    Index(Expr<'a, T>),
+}
+
+#[cfg(test)]
+impl Designator<'_> {
+    /// Element an array designator names, for tests whose indices are all
+    /// integer literals
+    pub(crate) fn literal_index(&self) -> Option<usize> {
+        match self {
+            Designator::Index(Expr {
+                kind: ExprKind::Literal(Literal::Int(index)),
+                ..
+            }) => usize::try_from(*index).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Position each entry of an initialiser list sets, following C's rules: a
+/// designated entry sets the position `resolve` gives for its designator, and
+/// any other entry the position after the one before it.
+pub fn init_positions<E>(
+    items: &[InitItem],
+    mut resolve: impl FnMut(&Designator) -> Result<usize, E>,
+) -> Result<Vec<usize>, E> {
+    let mut next = 0;
+    items
+        .iter()
+        .map(|item| {
+            let position = match &item.designator {
+                Some(designator) => resolve(designator)?,
+                None => next,
+            };
+            next = position + 1;
+            Ok(position)
+        })
+        .collect()
+}
+
+/// Function specifiers written before the return type, e.g. `inline` in
+/// `inline int f(void)`, along with the `static` storage class
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FunctionSpecifiers {
+    /// `static`, the function can't be called from other translation units
+    pub is_static: bool,
+    pub is_inline: bool,
+    /// `_Noreturn`, the function never returns to its caller
+    pub is_noreturn: bool,
 }
 
-pub(crate) enum Statement<'a> {
-    Expression(Expr<'a>),
-    Return { keyword: &'a Token<'a> , value: Option<Expr<'a>> },
-    VarDecl { name: &'a Token<'a>, initialiser: Option<Expr<'a>> },
-    Function {name: &'a Token<'a>, params: Vec<&'a Token<'a>>, body: Vec<Statement<'a>> },
+impl FunctionSpecifiers {
+    pub fn is_empty(self) -> bool {
+        self == FunctionSpecifiers::default()
+    }
+}
+
+impl fmt::Display for FunctionSpecifiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            (self.is_static, "static"),
+            (self.is_inline, "inline"),
+            (self.is_noreturn, "_Noreturn"),
+        ];
+        let names: Vec<_> = names
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, name)| *name)
+            .collect();
+        write!(f, "{}", names.join(" "))
+    }
+}
+
+/// Storage class a variable is declared with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum StorageClass {
+    /// None written: automatic in a function, static at file scope
+    #[default]
+    Unspecified,
+    /// `static`: a single instance that lasts the whole program, so in a
+    /// function it keeps its value between calls, and at file scope it can't
+    /// be used from other translation units
+    Static,
+    /// `register`: automatic, as a hint to keep it out of memory
+    Register,
+}
+
+/// How long the storage of a variable lasts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageDuration {
+    /// Created each time its block is entered, and gone when it's left
+    Automatic,
+    /// Created once, before the program starts
+    Static,
+}
+
+impl StorageClass {
+    /// Storage duration of a variable declared with this class, at file scope
+    /// or in a function
+    pub fn duration(self, file_scope: bool) -> StorageDuration {
+        match self {
+            StorageClass::Static => StorageDuration::Static,
+            StorageClass::Unspecified if file_scope => StorageDuration::Static,
+            StorageClass::Unspecified | StorageClass::Register => StorageDuration::Automatic,
+        }
+    }
+}
+
+impl fmt::Display for StorageClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            StorageClass::Unspecified => "",
+            StorageClass::Static => "static",
+            StorageClass::Register => "register",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Parameter<T> {
+    pub ty: Type,
+    pub name: T,
+}
+
+#[derive(Clone, Debug)]
+pub struct Member<'a, T = &'a Token<'a>> {
+    pub ty: Type,
+    /// Left out only for an unnamed bit-field such as `int : 0;`, which pads
+    /// the layout
+    pub name: Option<T>,
+    /// Width in bits of a bit-field, e.g. `3` in `int x : 3;`
+    pub width: Option<Expr<'a, T>>,
+}
+
+/// Constant declared by an `enum`, e.g. `GREEN = 2`
+#[derive(Clone, Debug)]
+pub struct Enumerator<'a, T = &'a Token<'a>> {
+    pub name: T,
+    /// Given value, without which it's one more than the enumerator before
+    pub value: Option<Expr<'a, T>>,
 }
 
+/// Token types of the type qualifiers
+const QUALIFIERS: &[TokenType] = &[
+    TokenType::Const,
+    TokenType::Volatile,
+    TokenType::Restrict,
+    TokenType::Atomic,
+];
+
+/// Token types of the type specifiers, which name the type being qualified
+const SPECIFIERS: &[TokenType] = &[
+    TokenType::Int,
+    TokenType::Float,
+    TokenType::Char,
+    TokenType::Short,
+    TokenType::Long,
+    TokenType::Signed,
+    TokenType::Unsigned,
+    TokenType::Void,
+    TokenType::Struct,
+    TokenType::Union,
+];
+
+/// Token types of the specifiers that combine to name an integer type
+const INTEGER_SPECIFIERS: &[TokenType] = &[
+    TokenType::Char,
+    TokenType::Short,
+    TokenType::Int,
+    TokenType::Long,
+    TokenType::Signed,
+    TokenType::Unsigned,
+];
+
+/// Token types that can begin a declaration
+const TYPE_SPECIFIERS: &[TokenType] = &[
+    TokenType::Const,
+    TokenType::Volatile,
+    TokenType::Restrict,
+    TokenType::Atomic,
+    TokenType::Int,
+    TokenType::Float,
+    TokenType::Char,
+    TokenType::Short,
+    TokenType::Long,
+    TokenType::Signed,
+    TokenType::Unsigned,
+    TokenType::Void,
+    TokenType::Struct,
+    TokenType::Union,
+];
+
+/// Token types that begin a statement, other than a declaration, which
+/// error recovery resumes at
+const STATEMENT_STARTS: &[TokenType] = &[
+    TokenType::If,
+    TokenType::While,
+    TokenType::For,
+    TokenType::Switch,
+    TokenType::Return,
+    TokenType::LBrace,
+];
+
+/// Token types of `=` and the compound assignment operators
+const ASSIGNMENT_OPERATORS: &[TokenType] = &[
+    TokenType::Equal,
+    TokenType::PlusEqual,
+    TokenType::MinusEqual,
+    TokenType::StarEqual,
+    TokenType::SlashEqual,
+];
+
 impl<'a> Expr<'a> {
+    /// Line of the first token in the expression that carries one. Literals
+    /// don't keep their token so may have no line.
+    pub fn line(&self) -> Option<usize> {
+        match &self.kind {
+            ExprKind::Binary { left, operator, .. } => left.line().or(Some(operator.line)),
+            ExprKind::Ternary {
+                condition,
+                question,
+                ..
+            } => condition.line().or(Some(question.line)),
+            ExprKind::Member {
+                object, operator, ..
+            } => object.line().or(Some(operator.line)),
+            ExprKind::Call { callee, paren, .. } => callee.line().or(Some(paren.line)),
+            ExprKind::Assign {
+                target, operator, ..
+            } => target.line().or(Some(operator.line)),
+            ExprKind::Unary { operator, .. } => Some(operator.line),
+            ExprKind::Cast { paren, .. } => Some(paren.line),
+            ExprKind::Generic { keyword, .. } | ExprKind::SizeOf { keyword, .. } => {
+                Some(keyword.line)
+            }
+            ExprKind::StmtExpr(body) => body.iter().find_map(|s| s.line()),
+            ExprKind::Identifier(token) => Some(token.line),
+            ExprKind::Grouping(inner) => inner.line(),
+            ExprKind::Literal(_) => None,
+        }
+    }
+
+    /// Bytes of a string literal, joining any adjacent literals it was parsed
+    /// from, given the tokens the parser was given. See `Token::string_bytes`.
+    pub fn string_bytes(&self, tokens: &[Token]) -> Option<Vec<u8>> {
+        let ExprKind::Literal(Literal::Str(value)) = &self.kind else {
+            return None;
+        };
+        let mut bytes = vec![];
+        for token in self.span.tokens(tokens) {
+            match token.string_bytes() {
+                Some(token_bytes) => bytes.extend(token_bytes),
+                // a wide string, which isn't made of bytes
+                None if token.token_type() == TokenType::StringLiteral => return None,
+                // `__func__`, a function name with no escapes to decode
+                None => return Some(value.as_bytes().to_vec()),
+            }
+        }
+        Some(bytes)
+    }
+
+    /// Whether the expression designates an object that can be assigned to
+    pub fn is_lvalue(&self) -> bool {
+        match &self.kind {
+            ExprKind::Identifier(_) | ExprKind::Member { .. } => true,
+            ExprKind::Grouping(inner) => inner.is_lvalue(),
+            _ => false,
+        }
+    }
+
+    /// Type of the operator of a binary, unary or assignment expression
+    pub fn operator_type(&self) -> Option<TokenType> {
+        match &self.kind {
+            ExprKind::Binary { operator, .. }
+            | ExprKind::Unary { operator, .. }
+            | ExprKind::Assign { operator, .. } => Some(operator.token_type),
+            _ => None,
+        }
+    }
+
+    /// Whether the expression compares its operands, as in `a < b`
+    pub fn is_comparison(&self) -> bool {
+        matches!(self.kind, ExprKind::Binary { .. })
+            && self.operator_type().is_some_and(TokenType::is_comparison)
+    }
+
+    /// Whether evaluating the expression can change anything, by assigning,
+    /// calling a function or running statements. Reading a `volatile` object
+    /// does too, but that takes its declaration to know.
+    pub fn has_side_effects(&self) -> bool {
+        match &self.kind {
+            ExprKind::Assign { .. } | ExprKind::Call { .. } | ExprKind::StmtExpr(_) => true,
+            _ => ast::expr_children(self)
+                .into_iter()
+                .any(Expr::has_side_effects),
+        }
+    }
+
     pub fn print_tree(&self) -> String {
         let mut tree = String::new();
         Self::print_tree_unicode(self, &mut tree, 0, true);
@@ -88,15 +781,7 @@ impl<'a> Expr<'a> {
         let indent = "  ".repeat(depth);
         let connector = if is_last { "└─ " } else { "├─ " };
 
-        let type_name = match expr {
-            Expr::Binary { .. } => "Binary",
-            Expr::Unary { .. } => "Unary",
-            Expr::Literal { .. } => "Literal",
-            Expr::Grouping { .. } => "Grouping",
-            _ => "Unknown",
-        };
-
-        let details = Self::format_node(expr);
+        let (type_name, details) = expr.node_label();
 
         writeln!(
             output,
@@ -105,96 +790,779 @@ impl<'a> Expr<'a> {
         )
         .unwrap();
 
-        match expr {
-            Expr::Binary {
+        match &expr.kind {
+            ExprKind::Binary {
                 left,
                 // operator,
                 right,
                 ..
+            }
+            | ExprKind::Assign {
+                target: left,
+                value: right,
+                ..
             } => {
                 Self::print_tree_unicode(left, output, depth + 1, false);
                 // Self::print_tree_unicode(operator, output, depth + 1, false);
                 Self::print_tree_unicode(right, output, depth + 1, true);
             }
-            Expr::Unary { right, .. } => {
+            ExprKind::Unary { right, .. } | ExprKind::Cast { expr: right, .. } => {
                 // Self::print_tree_unicode(&**operator, output, depth + 1, false);
                 Self::print_tree_unicode(right, output, depth + 1, true);
             }
-            Expr::Grouping(expr) => {
+            ExprKind::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                Self::print_tree_unicode(condition, output, depth + 1, false);
+                Self::print_tree_unicode(then_branch, output, depth + 1, false);
+                Self::print_tree_unicode(else_branch, output, depth + 1, true);
+            }
+            ExprKind::Grouping(expr) | ExprKind::Member { object: expr, .. } => {
                 Self::print_tree_unicode(expr, output, depth + 1, true);
             }
-            Expr::Literal { .. } | Expr::Identifier { .. } => (),
+            ExprKind::Call {
+                callee, arguments, ..
+            } => {
+                Self::print_tree_unicode(callee, output, depth + 1, arguments.is_empty());
+                for (i, argument) in arguments.iter().enumerate() {
+                    let is_last = i == arguments.len() - 1;
+                    Self::print_tree_unicode(argument, output, depth + 1, is_last);
+                }
+            }
+            ExprKind::Generic {
+                controlling,
+                assocs,
+                ..
+            } => {
+                Self::print_tree_unicode(controlling, output, depth + 1, assocs.is_empty());
+                for (i, (_, expr)) in assocs.iter().enumerate() {
+                    Self::print_tree_unicode(expr, output, depth + 1, i == assocs.len() - 1);
+                }
+            }
+            ExprKind::StmtExpr(body) => {
+                let indent = "  ".repeat(depth + 1);
+                for statement in body {
+                    for line in statement.print_tree().lines() {
+                        writeln!(output, "{}{}", indent, line).unwrap();
+                    }
+                }
+            }
+            ExprKind::Literal { .. } | ExprKind::Identifier { .. } | ExprKind::SizeOf { .. } => {}
         }
     }
 
-    fn format_node(expr: &Self) -> String {
-        match expr {
-            Expr::Binary { operator, .. } => format!("{:?}", operator.token_type),
-            Expr::Unary { operator, .. } => format!("{:?}", operator.token_type),
-            Expr::Literal(token) => format!("{:?}", token),
-            Expr::Grouping(_) => "(...)".to_string(),
-            Expr::Identifier(token) => format!("{:?}", token),
-        }
+    /// Name of the kind of node, and the details of this one, as the tree
+    /// printers show them
+    pub(crate) fn node_label(&self) -> (&'static str, String) {
+        let type_name = match self.kind {
+            ExprKind::Binary { .. } => "Binary",
+            ExprKind::Unary { .. } => "Unary",
+            ExprKind::Ternary { .. } => "Ternary",
+            ExprKind::Member { .. } => "Member",
+            ExprKind::Call { .. } => "Call",
+            ExprKind::Assign { .. } => "Assign",
+            ExprKind::Cast { .. } => "Cast",
+            ExprKind::Generic { .. } => "Generic",
+            ExprKind::SizeOf { .. } => "SizeOf",
+            ExprKind::StmtExpr(_) => "StmtExpr",
+            ExprKind::Literal { .. } => "Literal",
+            ExprKind::Grouping { .. } => "Grouping",
+            ExprKind::Identifier { .. } => "Identifier",
+        };
+        let details = match &self.kind {
+            ExprKind::Binary { operator, .. } => format!("{:?}", operator.token_type),
+            ExprKind::Unary { operator, .. } | ExprKind::Assign { operator, .. } => {
+                format!("{:?}", operator.token_type)
+            }
+            ExprKind::Ternary { .. } => "?:".to_string(),
+            ExprKind::Member {
+                operator, member, ..
+            } => format!("{:?} {}", operator.token_type, member.literal),
+            ExprKind::Call { arguments, .. } => format!("{} args", arguments.len()),
+            ExprKind::Generic { assocs, .. } => {
+                let types: Vec<_> = assocs
+                    .iter()
+                    .map(|(ty, _)| ty.as_ref().map_or("default".to_string(), Type::to_string))
+                    .collect();
+                types.join(", ")
+            }
+            ExprKind::SizeOf { ty, .. } | ExprKind::Cast { ty, .. } => ty.to_string(),
+            ExprKind::Literal(literal) => format!("{:?}", literal),
+            ExprKind::Grouping(_) => "(...)".to_string(),
+            ExprKind::StmtExpr(body) => format!("{} statements", body.len()),
+            ExprKind::Identifier(token) => format!("{:?}", token),
+        };
+        (type_name, details)
     }
 }
 
-/// Simple recursive descent parser for the C language. Takes a iterable list
-/// of `Token` enums and attempts to produce a AST from them.
-///
-/// * `tokens`: iterable list of `Token` enum objects (see Lexer.rs)
-pub struct Parser<'a> {
-    tokens: Peekable<Iter<'a, Token<'a>>>,
-    previous: Option<&'a Token<'a>>,
-}
+impl<'a> Initialiser<'a> {
+    pub fn print_tree(&self) -> String {
+        let mut tree = String::new();
+        Self::print_tree_unicode(self, &mut tree, 0, true);
+        tree
+    }
 
-impl<'a> Default for Parser<'a> {
-    fn default() -> Self {
-        let empty_slice: &[Token<'a>] = &[];
-        Self {
-            tokens: empty_slice.iter().peekable(),
-            previous: None,
+    fn print_tree_unicode(init: &Self, output: &mut String, depth: usize, is_last: bool) {
+        match init {
+            Initialiser::Expr(expr) => {
+                // expression trees start at the left margin, so shift them over
+                let indent = "  ".repeat(depth);
+                for line in expr.print_tree().lines() {
+                    writeln!(output, "{}{}", indent, line).unwrap();
+                }
+            }
+            Initialiser::List { items, .. } => {
+                let indent = "  ".repeat(depth);
+                let connector = if is_last { "└─ " } else { "├─ " };
+                writeln!(
+                    output,
+                    "{}{}┌─ InitList ({})",
+                    indent,
+                    connector,
+                    items.len()
+                )
+                .unwrap();
+                for item in items {
+                    let indent = "  ".repeat(depth + 1);
+                    match &item.designator {
+                        None => Self::print_tree_unicode(&item.value, output, depth + 1, true),
+                        Some(designator) => {
+                            let name = match designator {
+                                Designator::Member(member) => format!(".{}", member.literal),
+                                Designator::Index(_) => "[...]".to_string(),
+                            };
+                            writeln!(output, "{}└─ ┌─ Designated ({})", indent, name).unwrap();
+                            if let Designator::Index(index) = designator {
+                                for line in index.print_tree().lines() {
+                                    writeln!(output, "{}  {}", indent, line).unwrap();
+                                }
+                            }
+                            Self::print_tree_unicode(&item.value, output, depth + 2, true);
+                        }
+                    }
+                }
+            }
         }
     }
 }
 
-impl<'a> Parser<'a> {
-    pub fn new(tokens: &'a [Token<'a>]) -> Self {
-        Self {
-            tokens: tokens.iter().peekable(),
-            previous: None,
+impl<'a> Statement<'a> {
+    /// Line of the first token in the statement, where one can be found
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Statement::Expression(expr) => expr.line(),
+            Statement::Block(body) => body.iter().find_map(|s| s.line()),
+            Statement::If { keyword, .. }
+            | Statement::While { keyword, .. }
+            | Statement::For { keyword, .. }
+            | Statement::Switch { keyword, .. }
+            | Statement::Case { keyword, .. }
+            | Statement::Default { keyword, .. }
+            | Statement::Return { keyword, .. }
+            | Statement::Goto { keyword, .. }
+            | Statement::Enum { keyword, .. }
+            | Statement::StaticAssert { keyword, .. }
+            | Statement::Break(keyword)
+            | Statement::Continue(keyword) => Some(keyword.line),
+            Statement::Labeled { label, .. } => Some(label.line),
+            Statement::VarDecl { name, .. }
+            | Statement::Struct { name, .. }
+            | Statement::Function { name, .. }
+            | Statement::Prototype { name, .. } => Some(name.line),
         }
     }
 
-    fn peek(&mut self) -> Option<&Token<'a>> {
-        self.tokens.peek().cloned()
-    }
-
-    fn previous(&mut self) -> Result<&'a Token<'a>, ParserError> {
-        self.previous.ok_or(ParserError::NoPreviousToken)
+    /// Name of the kind of node, and the details of this one, as the tree
+    /// printers show them
+    pub(crate) fn node_label(&self) -> (&'static str, String) {
+        match self {
+            Statement::Expression(_) => ("Expression", String::new()),
+            Statement::Block(_) => ("Block", String::new()),
+            Statement::If { .. } => ("If", String::new()),
+            Statement::While { .. } => ("While", String::new()),
+            Statement::For { .. } => ("For", String::new()),
+            Statement::Switch { .. } => ("Switch", String::new()),
+            Statement::Case { .. } => ("Case", String::new()),
+            Statement::Default { .. } => ("Default", String::new()),
+            Statement::Break(_) => ("Break", String::new()),
+            Statement::Continue(_) => ("Continue", String::new()),
+            Statement::Goto { label, .. } => ("Goto", label.literal.to_string()),
+            Statement::Labeled { label, .. } => ("Labeled", label.literal.to_string()),
+            Statement::Return { .. } => ("Return", String::new()),
+            Statement::StaticAssert { message, .. } => ("StaticAssert", format!("{:?}", message)),
+            Statement::VarDecl {
+                ty,
+                name,
+                storage: StorageClass::Unspecified,
+                ..
+            } => ("VarDecl", format!("{} {}", ty, name.literal)),
+            Statement::VarDecl {
+                ty, name, storage, ..
+            } => ("VarDecl", format!("{} {} {}", storage, ty, name.literal)),
+            Statement::Struct {
+                name,
+                members,
+                is_union,
+            } => {
+                let members: Vec<_> = members
+                    .iter()
+                    .map(|m| {
+                        let mut member = m.ty.to_string();
+                        if let Some(name) = m.name {
+                            member = format!("{} {}", member, name.literal);
+                        }
+                        // the width is printed below, with the other expressions
+                        if m.width.is_some() {
+                            member.push_str(" :");
+                        }
+                        member
+                    })
+                    .collect();
+                let (type_name, keyword) = match is_union {
+                    true => ("Union", "union"),
+                    false => ("Struct", "struct"),
+                };
+                (
+                    type_name,
+                    format!("{} {} {{ {} }}", keyword, name.literal, members.join("; ")),
+                )
+            }
+            Statement::Enum {
+                name, enumerators, ..
+            } => {
+                let names: Vec<_> = enumerators.iter().map(|e| &*e.name.literal).collect();
+                let tag = name.map_or(String::new(), |name| format!("{} ", name.literal));
+                ("Enum", format!("enum {}{{ {} }}", tag, names.join(", ")))
+            }
+            Statement::Function {
+                return_type,
+                name,
+                params,
+                variadic,
+                specifiers,
+                ..
+            }
+            | Statement::Prototype {
+                return_type,
+                name,
+                params,
+                variadic,
+                specifiers,
+                ..
+            } => {
+                let mut params: Vec<_> = params
+                    .iter()
+                    .map(|p| format!("{} {}", p.ty, p.name.literal))
+                    .collect();
+                if *variadic {
+                    params.push("...".to_string());
+                }
+                let type_name = match self {
+                    Statement::Prototype { .. } => "Prototype",
+                    _ => "Function",
+                };
+                let mut signature =
+                    format!("{} {}({})", return_type, name.literal, params.join(", "));
+                if !specifiers.is_empty() {
+                    signature = format!("{} {}", specifiers, signature);
+                }
+                (type_name, signature)
+            }
+        }
     }
 
-    fn expression(&mut self) -> Result<Expr<'a>, ParserError> {
-        self.equality()
+    pub fn print_tree(&self) -> String {
+        let mut tree = String::new();
+        Self::print_tree_unicode(self, &mut tree, 0, true);
+        tree
     }
 
-    fn equality(&mut self) -> Result<Expr<'a>, ParserError> {
-        let mut expr: Expr = self.comparison()?;
+    fn print_tree_unicode(stmt: &Self, output: &mut String, depth: usize, is_last: bool) {
+        let indent = "  ".repeat(depth);
+        let connector = if is_last { "└─ " } else { "├─ " };
 
-        while self.matches(&[TokenType::BangEqual, TokenType::EqualEqual]) {
-            let operator = self.previous()?;
-            let right = self.comparison()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator: operator,
-                right: Box::new(right),
-            }
-        }
+        let (type_name, details) = stmt.node_label();
 
-        Ok(expr)
-    }
+        writeln!(
+            output,
+            "{}{}┌─ {} ({})",
+            indent, connector, type_name, details
+        )
+        .unwrap();
+
+        // expressions are printed with their own tree, indented below the statement
+        let print_expr = |expr: &Expr, output: &mut String| {
+            for line in expr.print_tree().lines() {
+                writeln!(output, "{}  {}", indent, line).unwrap();
+            }
+        };
+
+        match stmt {
+            Statement::Expression(expr)
+            | Statement::StaticAssert {
+                condition: expr, ..
+            } => print_expr(expr, output),
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    print_expr(expr, output);
+                }
+            }
+            Statement::VarDecl {
+                initialiser: Some(init),
+                ..
+            } => {
+                for line in init.print_tree().lines() {
+                    writeln!(output, "{}  {}", indent, line).unwrap();
+                }
+            }
+            Statement::VarDecl { .. } => (),
+            Statement::Struct { members, .. } => {
+                for width in members.iter().filter_map(|m| m.width.as_ref()) {
+                    print_expr(width, output);
+                }
+            }
+            Statement::Enum { enumerators, .. } => {
+                for value in enumerators.iter().filter_map(|e| e.value.as_ref()) {
+                    print_expr(value, output);
+                }
+            }
+            Statement::Function { body, .. } | Statement::Block(body) => {
+                for (i, child) in body.iter().enumerate() {
+                    Self::print_tree_unicode(child, output, depth + 1, i == body.len() - 1);
+                }
+            }
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                print_expr(condition, output);
+                Self::print_tree_unicode(then_branch, output, depth + 1, else_branch.is_none());
+                if let Some(else_branch) = else_branch {
+                    Self::print_tree_unicode(else_branch, output, depth + 1, true);
+                }
+            }
+            Statement::While {
+                condition, body, ..
+            }
+            | Statement::Switch {
+                condition, body, ..
+            }
+            | Statement::Case {
+                value: condition,
+                body,
+                ..
+            } => {
+                print_expr(condition, output);
+                Self::print_tree_unicode(body, output, depth + 1, true);
+            }
+            Statement::For {
+                initialiser,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                for statement in initialiser {
+                    Self::print_tree_unicode(statement, output, depth + 1, false);
+                }
+                for expr in condition.iter().chain(increment) {
+                    print_expr(expr, output);
+                }
+                Self::print_tree_unicode(body, output, depth + 1, true);
+            }
+            Statement::Default { body, .. } | Statement::Labeled { body, .. } => {
+                Self::print_tree_unicode(body, output, depth + 1, true);
+            }
+            Statement::Break(_)
+            | Statement::Continue(_)
+            | Statement::Goto { .. }
+            | Statement::Prototype { .. } => (),
+        }
+    }
+}
+
+/// Simple recursive descent parser for the C language. Takes a iterable list
+/// of `Token` enums and attempts to produce a AST from them.
+///
+/// * `tokens`: iterable list of `Token` enum objects (see Lexer.rs)
+/// * `current`: index of the next token to be consumed
+/// * `recover`: whether `statements` carries on past a syntax error
+/// * `max_errors`: how many errors `statements` recovers from before giving up
+/// * `allow_trailing_comma`: accept `f(a, b,)`, which strict C rejects
+/// * `allow_gnu_extensions`: accept GNU statement expressions `({ ... })` and
+///   skip `__attribute__((...))` annotations on declarations
+/// * `strict`: reject pre-C99 implicit `int` rather than accepting it
+/// * `allow_missing_semicolons`: insert a `;` missing at the end of a line,
+///   with a warning in `warnings`
+/// * `function`: name of the function whose body is being parsed, which
+///   `__func__` holds
+/// * `depth`/`max_depth`: current and maximum nesting of expressions
+/// * `pratt`: parse binary operators by precedence climbing on
+///   `TokenType::precedence` instead of a rule per level, giving the same tree
+/// * `target`/`enumerators`/`layouts`: what array lengths are evaluated with,
+///   the enumerators and structures defined so far being kept as they're
+///   parsed
+pub struct Parser<'a> {
+    tokens: &'a [Token<'a>],
+    current: usize,
+    recover: bool,
+    max_errors: usize,
+    allow_trailing_comma: bool,
+    allow_gnu_extensions: bool,
+    strict: bool,
+    allow_missing_semicolons: bool,
+    warnings: Vec<Diagnostic>,
+    function: Option<&'a Token<'a>>,
+    depth: usize,
+    max_depth: usize,
+    pratt: bool,
+    target: TargetConfig,
+    enumerators: Enumerators<'a>,
+    layouts: Layouts<'a>,
+}
+
+/// Default limit on expression nesting. Each level costs several stack frames,
+/// around 11KiB in debug builds, so this fits well within the 8MiB main thread
+/// but not necessarily a smaller spawned one.
+const MAX_DEPTH: usize = 128;
+
+/// Default limit on the errors recovered from, past which they are more likely
+/// to cascade from the earlier ones than to be new
+const MAX_ERRORS: usize = 20;
+
+impl<'a> Default for Parser<'a> {
+    fn default() -> Self {
+        Self {
+            tokens: &[],
+            current: 0,
+            recover: false,
+            max_errors: MAX_ERRORS,
+            allow_trailing_comma: false,
+            allow_gnu_extensions: false,
+            strict: false,
+            allow_missing_semicolons: false,
+            warnings: vec![],
+            function: None,
+            depth: 0,
+            max_depth: MAX_DEPTH,
+            pratt: false,
+            target: TargetConfig::default(),
+            enumerators: Enumerators::default(),
+            layouts: Layouts::default(),
+        }
+    }
+}
+
+/// Iterator over the top-level declarations of a program, see
+/// `Parser::statements`
+pub struct Statements<'p, 'a> {
+    parser: &'p mut Parser<'a>,
+    done: bool,
+    errors: usize,
+    /// Whether `max_errors` was reached with input left, which ends the
+    /// iterator with a `TooManyErrors`
+    gave_up: bool,
+}
+
+impl<'p, 'a> Iterator for Statements<'p, 'a> {
+    type Item = Result<Statement<'a>, ParserError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.gave_up {
+            self.gave_up = false;
+            return Some(Err(ParserError::TooManyErrors {
+                limit: self.parser.max_errors,
+            }));
+        }
+        if self.done || self.parser.at_end() {
+            return None;
+        }
+
+        let result = self.parser.declaration();
+        if result.is_err() {
+            self.errors += 1;
+            if self.parser.recover {
+                self.parser.synchronise();
+                if self.errors == self.parser.max_errors {
+                    self.done = true;
+                    self.gave_up = !self.parser.at_end();
+                }
+            } else {
+                self.done = true;
+            }
+        }
+        Some(result)
+    }
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(tokens: &'a [Token<'a>]) -> Self {
+        Self {
+            tokens,
+            ..Default::default()
+        }
+    }
+
+    /// Skip past syntax errors in `statements` rather than stopping at the
+    /// first one
+    pub fn recover_errors(mut self, recover: bool) -> Self {
+        self.recover = recover;
+        self
+    }
+
+    /// How many errors `statements` recovers from before stopping, with a
+    /// final `TooManyErrors` if any input was left. 0 allows any number.
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    /// Accept a trailing comma at the end of a call's argument list
+    pub fn allow_trailing_comma(mut self, allow: bool) -> Self {
+        self.allow_trailing_comma = allow;
+        self
+    }
+
+    /// Accept GNU C extensions, currently statement expressions and attributes
+    pub fn allow_gnu_extensions(mut self, allow: bool) -> Self {
+        self.allow_gnu_extensions = allow;
+        self
+    }
+
+    /// Recover from a `;` missing at the end of a statement by inserting one,
+    /// as long as the statement ends its line or block so the `;` is the only
+    /// thing that can be missing
+    pub fn allow_missing_semicolons(mut self, allow: bool) -> Self {
+        self.allow_missing_semicolons = allow;
+        self
+    }
+
+    /// Problems the parser recovered from, such as an inserted `;`
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    /// Reject pre-C99 constructs that are otherwise accepted, currently a
+    /// function defined without a return type
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// How deeply expressions may nest, counting each parenthesised or
+    /// otherwise nested subexpression and each unary operator
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Machine whose type sizes a `sizeof` in an array length takes
+    pub fn target(mut self, target: TargetConfig) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Parse binary operators with a single precedence-climbing (Pratt) rule
+    /// rather than the recursive descent chain from `equality` down to
+    /// `factor`. Both build the same tree, so this is for comparison.
+    pub fn pratt(mut self, pratt: bool) -> Self {
+        self.pratt = pratt;
+        self
+    }
+
+    /// Runs the recursive rule `rule` one level deeper, failing rather than
+    /// recursing past `max_depth`
+    fn nested<T>(
+        &mut self,
+        rule: impl FnOnce(&mut Self) -> Result<T, ParserError>,
+    ) -> Result<T, ParserError> {
+        if self.depth >= self.max_depth {
+            let line = self.peek().map_or(0, |t| t.line);
+            return Err(ParserError::NestingTooDeep { line });
+        }
+        self.depth += 1;
+        let result = rule(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn peek(&mut self) -> Option<&'a Token<'a>> {
+        self.tokens.get(self.current)
+    }
+
+    fn previous(&mut self) -> Result<&'a Token<'a>, ParserError> {
+        self.current
+            .checked_sub(1)
+            .and_then(|i| self.tokens.get(i))
+            .ok_or(ParserError::NoPreviousToken)
+    }
+
+    /// Runs the parse rule `rule`, tagging the expression it builds with the
+    /// tokens consumed along the way
+    fn with_span(
+        &mut self,
+        rule: impl FnOnce(&mut Self) -> Result<ExprKind<'a>, ParserError>,
+    ) -> Result<Expr<'a>, ParserError> {
+        let start = self.current;
+        let kind = rule(self)?;
+        Ok(Expr {
+            kind,
+            span: Span {
+                start,
+                end: self.current,
+            },
+        })
+    }
+
+    /// Binary node spanning both of its operands
+    fn binary(left: Expr<'a>, operator: &'a Token<'a>, right: Expr<'a>) -> Expr<'a> {
+        Expr {
+            span: left.span.to(right.span),
+            kind: ExprKind::Binary {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            },
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr<'a>, ParserError> {
+        self.nested(Self::assignment)
+    }
+
+    fn assignment(&mut self) -> Result<Expr<'a>, ParserError> {
+        let target = self.conditional()?;
+
+        if self.matches(ASSIGNMENT_OPERATORS) {
+            let operator = self.previous()?;
+            // right associative, `a = b = c` assigns `c` to both
+            let value = self.nested(Self::assignment)?;
+            if !target.is_lvalue() {
+                return Err(ParserError::InvalidAssignmentTarget {
+                    line: operator.line,
+                });
+            }
+            return Ok(Expr {
+                span: target.span.to(value.span),
+                kind: ExprKind::Assign {
+                    target: Box::new(target),
+                    operator,
+                    value: Box::new(value),
+                },
+            });
+        }
+
+        Ok(target)
+    }
+
+    fn conditional(&mut self) -> Result<Expr<'a>, ParserError> {
+        let condition = if self.pratt {
+            self.precedence_climbing(1)?
+        } else {
+            self.equality()?
+        };
+
+        if self.matches(&[TokenType::Question]) {
+            let question = self.previous()?;
+            let then_branch = self.expression()?;
+            self.consume(TokenType::Colon, "Expect ':' in conditional expression")?;
+            // right associative, `a ? b : c ? d : e` nests in the else branch.
+            // Unlike C++ the else branch can't be an assignment, so
+            // `a ? b : c = d` assigns to the whole conditional and is rejected.
+            let else_branch = self.nested(Self::conditional)?;
+            return Ok(Expr {
+                span: condition.span.to(else_branch.span),
+                kind: ExprKind::Ternary {
+                    condition: Box::new(condition),
+                    question,
+                    then_branch: Box::new(then_branch),
+                    else_branch: Box::new(else_branch),
+                },
+            });
+        }
+
+        Ok(condition)
+    }
+
+    /// Parses a constant expression, as taken by a `case` label, a bit-field
+    /// width, an enumerator's value or a static assertion. That's a
+    /// conditional expression, so neither an assignment nor a comma operator
+    /// at the top level.
+    fn constant_expression(&mut self) -> Result<Expr<'a>, ParserError> {
+        let expr = self.nested(Self::conditional)?;
+        if let Some(operator) = self
+            .peek()
+            .filter(|t| ASSIGNMENT_OPERATORS.contains(&t.token_type))
+        {
+            return Err(ParserError::AssignmentInConstant {
+                line: operator.line,
+            });
+        }
+        Ok(expr)
+    }
+
+    /// Parses a chain of operands from `next` joined by any of the operators in
+    /// `ops`, grouping to the left so `a - b - c` is `(a - b) - c`
+    fn parse_left_assoc(
+        &mut self,
+        next: fn(&mut Self) -> Result<Expr<'a>, ParserError>,
+        ops: &[TokenType],
+    ) -> Result<Expr<'a>, ParserError> {
+        let mut expr = next(self)?;
+
+        while self.matches(ops) {
+            let operator = self.previous()?;
+            let right = next(self)?;
+            expr = Self::binary(expr, operator, right);
+        }
+
+        Ok(expr)
+    }
+
+    /// Expressions separated by the comma operator, e.g. `i += 1, j -= 1`,
+    /// as a left-associative `Binary` chain. Only used where C takes a full
+    /// expression and a `,` can't mean anything else, such as `for` clauses.
+    fn comma_expression(&mut self) -> Result<Expr<'a>, ParserError> {
+        self.parse_left_assoc(Self::expression, &[TokenType::Comma])
+    }
+
+    /// Binary operators binding at least as tightly as `min_precedence`, and
+    /// their operands. The right operand of each only takes operators binding
+    /// tighter, so `a - b * c` nests the `*` and `a - b - c` groups to the left.
+    fn precedence_climbing(&mut self, min_precedence: u8) -> Result<Expr<'a>, ParserError> {
+        let mut expr = self.unary()?;
+
+        while let Some(operator) = self.peek().filter(|t| {
+            let precedence = t.token_type.precedence();
+            precedence > 0 && precedence >= min_precedence
+        }) {
+            self.advance()?;
+            let right = self.precedence_climbing(operator.token_type.precedence() + 1)?;
+            expr = Self::binary(expr, operator, right);
+        }
+
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> Result<Expr<'a>, ParserError> {
+        self.parse_left_assoc(
+            Self::comparison,
+            &[TokenType::BangEqual, TokenType::EqualEqual],
+        )
+    }
 
-    fn consume(&mut self, expected: TokenType, message: &str) -> Result<&Token, ParserError> {
-        if self.check(expected) {
+    fn consume(
+        &mut self,
+        expected: TokenType,
+        message: &str,
+    ) -> Result<&'a Token<'a>, ParserError> {
+        if self.peek_is(expected) {
             return self.advance();
         }
 
@@ -206,18 +1574,107 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Consumes the `;` ending a statement, or inserts a missing one when
+    /// allowed and the next token is on a later line, a `}` or the end of input
+    fn end_statement(&mut self, message: &str) -> Result<(), ParserError> {
+        if self.matches(&[TokenType::Semicolon]) {
+            return Ok(());
+        }
+        if self.allow_missing_semicolons
+            && let Ok(previous) = self.previous()
+            && self.peek().is_some_and(|next| {
+                next.line > previous.line
+                    || matches!(next.token_type, TokenType::RBrace | TokenType::EOF)
+            })
+        {
+            self.warnings.push(Diagnostic::warning(
+                previous.line,
+                "missing ';' at end of statement, one was inserted",
+            ));
+            return Ok(());
+        }
+        self.consume(TokenType::Semicolon, message).map(|_| ())
+    }
+
+    /// Consumes the next token if it's any of `expected`
+    fn consume_one_of(&mut self, expected: &[TokenType]) -> Result<&'a Token<'a>, ParserError> {
+        if self.matches(expected) {
+            return self.previous();
+        }
+
+        let next = self.peek();
+        Err(ParserError::ExpectedOneOf {
+            line: next.map_or(0, |t| t.line),
+            expected: expected.to_vec(),
+            found: next.map(|t| t.token_type),
+        })
+    }
+
     fn primary(&mut self) -> Result<Expr<'a>, ParserError> {
+        self.with_span(Self::primary_kind)
+    }
+
+    /// Value of the string literal `token`, joined with any that follow it
+    fn string(&mut self, token: &'a Token<'a>) -> Result<Cow<'a, str>, ParserError> {
+        let mut value = Cow::Borrowed(&*token.literal);
+        while self.matches(&[TokenType::StringLiteral]) {
+            value.to_mut().push_str(&self.previous()?.literal);
+        }
+        Ok(value)
+    }
+
+    fn primary_kind(&mut self) -> Result<ExprKind<'a>, ParserError> {
         if let Some(token) = self.peek() {
             match token.token_type {
-                TokenType::False | TokenType::True | TokenType::Constant => {
+                TokenType::False | TokenType::True => {
+                    let token = self.advance()?;
+                    let value = (token.token_type == TokenType::True) as i64;
+                    return Ok(ExprKind::Literal(Literal::Int(value)));
+                }
+                TokenType::Constant => {
+                    let token = self.advance()?;
+                    return Self::number(token).map(ExprKind::Literal);
+                }
+                // character constants have type `int` in C
+                TokenType::CharLiteral => {
+                    let token = self.advance()?;
+                    let value = token.literal.chars().next().map_or(0, |c| c as i64);
+                    return Ok(ExprKind::Literal(Literal::Int(value)));
+                }
+                // adjacent string literals are concatenated, `"a" "b"` is `"ab"`
+                TokenType::StringLiteral => {
+                    let token = self.advance()?;
+                    return Ok(ExprKind::Literal(Literal::Str(self.string(token)?)));
+                }
+                // `__func__` is declared in every function as its name
+                TokenType::Identifier if token.literal == "__func__" => {
+                    let token = self.advance()?;
+                    let Some(function) = self.function else {
+                        return Err(ParserError::FuncOutsideFunction { line: token.line });
+                    };
+                    return Ok(ExprKind::Literal(Literal::Str(Cow::Borrowed(
+                        &function.literal,
+                    ))));
+                }
+                TokenType::Identifier => {
                     let token = self.advance()?;
-                    return Ok(Expr::Literal(token.literal));
+                    return Ok(ExprKind::Identifier(token));
+                }
+                TokenType::Generic => {
+                    let keyword = self.advance()?;
+                    return self.generic_selection(keyword);
                 }
-                TokenType::LParen | TokenType::LBrace => {
-                    let _ = self.advance();
+                // `{` starts a block or initialiser list, handled by the statement rules
+                TokenType::LParen => {
+                    self.advance()?;
+                    if self.allow_gnu_extensions && self.matches(&[TokenType::LBrace]) {
+                        let body = self.block()?;
+                        self.consume(TokenType::RParen, "Expect ')' after statement expression")?;
+                        return Ok(ExprKind::StmtExpr(body));
+                    }
                     let expr = self.expression()?;
-                    self.consume(TokenType::RParen, "Expect ')' after expression");
-                    return Ok(Expr::Grouping(Box::new(expr)));
+                    self.consume(TokenType::RParen, "Expect ')' after expression")?;
+                    return Ok(ExprKind::Grouping(Box::new(expr)));
                 }
                 _ => {
                     return Err(ParserError::UnknownPrimaryToken {
@@ -231,111 +1688,2617 @@ impl<'a> Parser<'a> {
         Err(ParserError::UnknownError)
     }
 
+    /// Controlling expression and associations of a `_Generic`, with the
+    /// keyword already consumed
+    fn generic_selection(&mut self, keyword: &'a Token<'a>) -> Result<ExprKind<'a>, ParserError> {
+        self.consume(TokenType::LParen, "Expect '(' after '_Generic'")?;
+        let controlling = self.expression()?;
+        self.consume(TokenType::Comma, "Expect ',' after controlling expression")?;
+
+        let mut assocs = vec![];
+        loop {
+            let ty = if self.matches(&[TokenType::Default]) {
+                None
+            } else {
+                Some(self.type_name()?)
+            };
+            self.consume(
+                TokenType::Colon,
+                "Expect ':' after generic association type",
+            )?;
+            assocs.push((ty, self.expression()?));
+            if !self.matches(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(TokenType::RParen, "Expect ')' after generic associations")?;
+
+        Ok(ExprKind::Generic {
+            keyword,
+            controlling: Box::new(controlling),
+            assocs,
+        })
+    }
+
+    fn number(token: &'a Token<'a>) -> Result<Literal<'a>, ParserError> {
+        let text: &str = &token.literal;
+        let invalid = || ParserError::InvalidConstant {
+            line: token.line,
+            text: text.to_string(),
+        };
+        if let Some(digits) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+            if digits.contains(['p', 'P']) {
+                lexer::hex_float_value(text)
+                    .map(Literal::Float)
+                    .ok_or_else(invalid)
+            } else {
+                i64::from_str_radix(digits, 16)
+                    .map(Literal::Int)
+                    .map_err(|_| invalid())
+            }
+        } else if text.contains('.') {
+            text.parse().map(Literal::Float).map_err(|_| invalid())
+        } else {
+            text.parse().map(Literal::Int).map_err(|_| invalid())
+        }
+    }
+
     fn unary(&mut self) -> Result<Expr<'a>, ParserError> {
-        if self.matches(&[TokenType::Bang, TokenType::Minus]) {
-            let op = self.previous()?;
-            let right = self.unary()?;
-            return Ok(Expr::Unary {
-                operator: op,
-                right: Box::new(right),
+        if self.peek_is_any(&[TokenType::Bang, TokenType::Minus]) {
+            return self.with_span(|parser| {
+                let operator = parser.advance()?;
+                let right = parser.nested(Self::unary)?;
+                Ok(ExprKind::Unary {
+                    operator,
+                    right: Box::new(right),
+                })
             });
         }
 
-        self.primary()
+        // a `(` starting a type name, rather than a parenthesised expression
+        if self.peek_is(TokenType::LParen)
+            && self
+                .tokens
+                .get(self.current + 1)
+                .is_some_and(|t| TYPE_SPECIFIERS.contains(&t.token_type))
+        {
+            return self.with_span(|parser| {
+                let paren = parser.advance()?;
+                let ty = parser.type_name()?;
+                parser.consume(TokenType::RParen, "Expect ')' after type name")?;
+                let expr = parser.nested(Self::unary)?;
+                Ok(ExprKind::Cast {
+                    paren,
+                    ty,
+                    expr: Box::new(expr),
+                })
+            });
+        }
+
+        if self.peek_is(TokenType::Sizeof) {
+            return self.with_span(|parser| {
+                let keyword = parser.advance()?;
+                parser.consume(TokenType::LParen, "Expect '(' after 'sizeof'")?;
+                let ty = parser.type_name()?;
+                parser.consume(TokenType::RParen, "Expect ')' after type name")?;
+                Ok(ExprKind::SizeOf { keyword, ty })
+            });
+        }
+
+        self.postfix()
     }
 
-    fn factor(&mut self) -> Result<Expr<'a>, ParserError> {
-        let mut expr = self.unary()?;
+    /// Calls and member accesses applied to a primary expression, e.g.
+    /// `a.b->c(d)`
+    fn postfix(&mut self) -> Result<Expr<'a>, ParserError> {
+        let mut expr = self.primary()?;
 
-        while self.matches(&[TokenType::Slash, TokenType::Star]) {
-            let op = self.previous()?;
-            let right = self.unary()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator: op,
-                right: Box::new(right),
+        while self.matches(&[TokenType::Dot, TokenType::Arrow, TokenType::LParen]) {
+            let operator = self.previous()?;
+            if operator.token_type == TokenType::LParen {
+                expr = self.call(expr)?;
+                continue;
             }
+            let member = self.consume(TokenType::Identifier, "Expect member name")?;
+            expr = Expr {
+                span: Span {
+                    start: expr.span.start,
+                    end: self.current,
+                },
+                kind: ExprKind::Member {
+                    object: Box::new(expr),
+                    operator,
+                    member,
+                },
+            };
         }
 
         Ok(expr)
     }
 
-    fn term(&mut self) -> Result<Expr<'a>, ParserError> {
-        let mut expr = self.factor()?;
-
-        while self.matches(&[TokenType::Minus, TokenType::Plus]) {
-            let op = self.previous()?;
-            let right = self.factor()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator: op,
-                right: Box::new(right),
-            };
-        }
+    fn factor(&mut self) -> Result<Expr<'a>, ParserError> {
+        self.parse_left_assoc(Self::unary, &[TokenType::Slash, TokenType::Star])
+    }
 
-        Ok(expr)
+    fn term(&mut self) -> Result<Expr<'a>, ParserError> {
+        self.parse_left_assoc(Self::factor, &[TokenType::Minus, TokenType::Plus])
     }
 
     fn comparison(&mut self) -> Result<Expr<'a>, ParserError> {
-        let mut expr: Expr = self.term()?;
+        self.parse_left_assoc(
+            Self::term,
+            &[
+                TokenType::Greater,
+                TokenType::GreaterEqual,
+                TokenType::Less,
+                TokenType::LessEqual,
+            ],
+        )
+    }
 
-        while self.matches(&[
-            TokenType::Greater,
-            TokenType::GreaterEqual,
-            TokenType::Less,
-            TokenType::LessEqual,
-        ]) {
-            let operator = self.previous()?;
-            let right = self.term()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator: operator,
-                right: Box::new(right),
+    /// Argument list of a call, with the opening `(` already consumed
+    fn call(&mut self, callee: Expr<'a>) -> Result<Expr<'a>, ParserError> {
+        let mut arguments = vec![];
+        if !self.peek_is(TokenType::RParen) {
+            loop {
+                arguments.push(self.expression()?);
+                if !self.matches(&[TokenType::Comma]) {
+                    break;
+                }
+                if self.allow_trailing_comma && self.peek_is(TokenType::RParen) {
+                    break;
+                }
             }
         }
+        let paren = self.consume(TokenType::RParen, "Expect ')' after arguments")?;
 
-        Ok(expr)
+        Ok(Expr {
+            span: Span {
+                start: callee.span.start,
+                end: self.current,
+            },
+            kind: ExprKind::Call {
+                callee: Box::new(callee),
+                paren,
+                arguments,
+            },
+        })
     }
 
     fn matches(&mut self, types: &[TokenType]) -> bool {
-        if let Some(token) = self.peek() {
-            if types.contains(&token.token_type) {
-                self.advance();
-                return true;
-            }
+        if self.peek_is_any(types) {
+            let _ = self.advance();
+            return true;
         }
         false
     }
 
-    fn check(&mut self, token_type: TokenType) -> bool {
-        // check if value matches input type, return false in all other situations
-        self.peek().map_or(false, |t| t.token_type == token_type)
+    /// Whether the next token is a `token_type`, without consuming it
+    fn peek_is(&self, token_type: TokenType) -> bool {
+        self.peek_is_any(&[token_type])
     }
 
-    fn eof(&mut self) -> bool {
-        if self.check(TokenType::EOF) {
-            return true;
-        }
-        false
+    /// Whether the next token is any of `types`, without consuming it
+    fn peek_is_any(&self, types: &[TokenType]) -> bool {
+        self.tokens
+            .get(self.current)
+            .is_some_and(|t| types.contains(&t.token_type))
+    }
+
+    /// Like `peek_is`, but for the token `offset` places after the next one
+    fn check_ahead(&self, offset: usize, token_type: TokenType) -> bool {
+        self.tokens
+            .get(self.current + offset)
+            .is_some_and(|t| t.token_type == token_type)
+    }
+
+    /// Whether everything but the end of input has been consumed
+    fn at_end(&self) -> bool {
+        self.peek_is(TokenType::EOF)
     }
 
-    fn advance(&mut self) -> Result<&Token<'a>, ParserError> {
-        let token = self.tokens.next().ok_or(ParserError::UnexpectedEOF)?;
-        self.previous = Some(token);
+    fn advance(&mut self) -> Result<&'a Token<'a>, ParserError> {
+        let token = self
+            .tokens
+            .get(self.current)
+            .ok_or(ParserError::UnexpectedEOF)?;
+        self.current += 1;
         Ok(token)
     }
 
     pub fn parse(&mut self) -> Result<Vec<Statement<'a>>, ParserError> {
         let mut statements = vec![];
-        while !self.eof() {
+        while !self.at_end() {
             statements.push(self.declaration()?);
         }
         Ok(statements)
     }
 
-    fn declaration(&mut self) -> Result<Statement<'a>, ParserError> {
-     if self.matches(&)
+    /// Parses the program one top-level declaration at a time, ending at EOF
+    /// or after the first error unless recovery is enabled
+    pub fn statements(&mut self) -> Statements<'_, 'a> {
+        Statements {
+            parser: self,
+            done: false,
+            errors: 0,
+            gave_up: false,
+        }
+    }
+
+    /// Whether the current token is the likely start of a statement, where
+    /// parsing can resume after an error. That's any token just after a `;`
+    /// or a `}`, which end statements, and any `{`, `if`, `while`, `for`,
+    /// `switch`, `return` or type keyword, which begin them.
+    pub fn recover_at(&self) -> bool {
+        let ends_statement = self.current.checked_sub(1).is_some_and(|i| {
+            matches!(
+                self.tokens[i].token_type,
+                TokenType::Semicolon | TokenType::RBrace
+            )
+        });
+        ends_statement || self.peek_is_any(STATEMENT_STARTS) || self.peek_is_any(TYPE_SPECIFIERS)
+    }
+
+    /// Discards tokens until `recover_at` the next statement, so parsing can
+    /// resume after an error
+    fn synchronise(&mut self) {
+        // always make progress, the error may not have consumed anything
+        if self.advance().is_err() {
+            return;
+        }
+        while !self.at_end() && !self.recover_at() {
+            let _ = self.advance();
+        }
+    }
+
+    /// Parses a single standalone expression, which must make up the whole
+    /// input
+    pub fn parse_expression(&mut self) -> Result<Expr<'a>, ParserError> {
+        let expr = self.expression()?;
+        if !self.at_end() {
+            let found = self.peek().map(|t| t.token_type);
+            return Err(ParserError::ExpectedToken {
+                expected: TokenType::EOF,
+                found,
+                message: "Expect end of input after expression".to_string(),
+            });
+        }
+        Ok(expr)
     }
 
-    pub(crate) fn print(&self) {}
+    fn declaration(&mut self) -> Result<Statement<'a>, ParserError> {
+        // `struct tag {` defines a type rather than declaring a variable
+        if self.peek_is_any(&[TokenType::Struct, TokenType::Union])
+            && self.check_ahead(2, TokenType::LBrace)
+        {
+            return self.struct_declaration();
+        }
+        if self.peek_is(TokenType::Enum)
+            && (self.check_ahead(1, TokenType::LBrace) || self.check_ahead(2, TokenType::LBrace))
+        {
+            return self.enum_declaration();
+        }
+        if self.matches(&[TokenType::StaticAssert]) {
+            return self.static_assert();
+        }
+        self.attributes()?;
+        let start = self.current;
+        let (specifiers, register) = self.specifiers()?;
+        if self.peek_is_any(TYPE_SPECIFIERS) {
+            let ty = self.specified_type()?;
+            self.attributes()?;
+            let (ty, name) = self.declarator(ty, "Expect name after type specifier")?;
+            if self.matches(&[TokenType::LParen]) {
+                if let Some(register) = register {
+                    return Err(Self::invalid_storage_class(register));
+                }
+                return self.function(ty, name, false, specifiers);
+            }
+            let storage = self.storage_class(start, specifiers, register)?;
+            self.attributes()?;
+            return self.var_declaration(ty, name, storage);
+        }
+        if (specifiers.is_static || register.is_some())
+            && self.peek_is(TokenType::Identifier)
+            && !self.implicit_int_function()
+        {
+            return self.implicit_int_variable(start, specifiers, register);
+        }
+        if let Some(register) = register {
+            return Err(Self::invalid_storage_class(register));
+        }
+        if self.implicit_int_function() {
+            let name = self.advance()?;
+            if self.strict {
+                return Err(ParserError::ImplicitInt { line: name.line });
+            }
+            self.advance()?;
+            return self.function(Type::Int, name, true, specifiers);
+        }
+        if !specifiers.is_empty() {
+            return Err(self.misplaced_specifier(start));
+        }
+
+        self.statement()
+    }
+
+    /// Any `static`, `inline`, `_Noreturn` and `register` specifiers, in any
+    /// order, with the `register` apart as it can only be on a variable
+    fn specifiers(&mut self) -> Result<(FunctionSpecifiers, Option<&'a Token<'a>>), ParserError> {
+        let mut specifiers = FunctionSpecifiers::default();
+        let mut register = None;
+        while self.matches(&[
+            TokenType::Static,
+            TokenType::Inline,
+            TokenType::Noreturn,
+            TokenType::Register,
+        ]) {
+            let token = self.previous()?;
+            match token.token_type {
+                TokenType::Static => specifiers.is_static = true,
+                TokenType::Inline => specifiers.is_inline = true,
+                TokenType::Register => register = Some(token),
+                _ => specifiers.is_noreturn = true,
+            }
+        }
+        Ok((specifiers, register))
+    }
+
+    /// Storage class of a variable from the specifiers starting at token
+    /// `start`. Of the function specifiers only `static` can be on a variable,
+    /// and `register` only on one in a function.
+    fn storage_class(
+        &self,
+        start: usize,
+        specifiers: FunctionSpecifiers,
+        register: Option<&'a Token<'a>>,
+    ) -> Result<StorageClass, ParserError> {
+        if specifiers.is_inline || specifiers.is_noreturn {
+            return Err(self.misplaced_specifier(start));
+        }
+        match register {
+            Some(register) if specifiers.is_static || self.function.is_none() => {
+                Err(Self::invalid_storage_class(register))
+            }
+            Some(_) => Ok(StorageClass::Register),
+            None if specifiers.is_static => Ok(StorageClass::Static),
+            None => Ok(StorageClass::Unspecified),
+        }
+    }
+
+    fn invalid_storage_class(token: &Token) -> ParserError {
+        ParserError::InvalidStorageClass {
+            line: token.line,
+            specifier: token.token_type,
+        }
+    }
+
+    /// Error for function specifiers starting at token `start` that turned out
+    /// not to be on a function, at the first that can't be on anything else
+    fn misplaced_specifier(&self, start: usize) -> ParserError {
+        let specifiers = &self.tokens[start..self.current];
+        let token = specifiers
+            .iter()
+            .find(|t| matches!(t.token_type, TokenType::Inline | TokenType::Noreturn))
+            .unwrap_or(&self.tokens[start]);
+        ParserError::MisplacedFunctionSpecifier {
+            line: token.line,
+            specifier: token.token_type,
+        }
+    }
+
+    /// Parses the rest of a static assertion after its keyword. The condition
+    /// is only folded later, during analysis.
+    fn static_assert(&mut self) -> Result<Statement<'a>, ParserError> {
+        let keyword = self.previous()?;
+        self.consume(TokenType::LParen, "Expect '(' after '_Static_assert'")?;
+        let condition = self.constant_expression()?;
+        self.consume(
+            TokenType::Comma,
+            "Expect ',' after static assertion condition",
+        )?;
+        let token = self.consume(
+            TokenType::StringLiteral,
+            "Expect message string in static assertion",
+        )?;
+        let message = self.string(token)?;
+        self.consume(
+            TokenType::RParen,
+            "Expect ')' after static assertion message",
+        )?;
+        self.end_statement("Expect ';' after static assertion")?;
+        Ok(Statement::StaticAssert {
+            keyword,
+            condition,
+            message,
+        })
+    }
+
+    /// Skips any GNU `__attribute__((...))` annotations, when extensions are
+    /// allowed. Their contents aren't checked beyond the parentheses being
+    /// balanced.
+    fn attributes(&mut self) -> Result<(), ParserError> {
+        while self.allow_gnu_extensions
+            && self.peek().is_some_and(|t| {
+                t.token_type == TokenType::Identifier && t.literal == "__attribute__"
+            })
+        {
+            self.advance()?;
+            self.consume(TokenType::LParen, "Expect '((' after '__attribute__'")?;
+            self.consume(TokenType::LParen, "Expect '((' after '__attribute__'")?;
+            let mut depth = 2;
+            while depth > 0 {
+                if self.at_end() {
+                    return Err(ParserError::UnclosedParen);
+                }
+                match self.advance()?.token_type {
+                    TokenType::LParen => depth += 1,
+                    TokenType::RParen => depth -= 1,
+                    _ => (),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Variable declared with a storage class but no type, such as `static x;`,
+    /// which pre-C99 code may write for an `int`. The storage class must be
+    /// one the variable can have.
+    fn implicit_int_variable(
+        &mut self,
+        start: usize,
+        specifiers: FunctionSpecifiers,
+        register: Option<&'a Token<'a>>,
+    ) -> Result<Statement<'a>, ParserError> {
+        let name = self.advance()?;
+        if self.strict {
+            return Err(ParserError::ImplicitInt { line: name.line });
+        }
+        let storage = self.storage_class(start, specifiers, register)?;
+        self.attributes()?;
+        let mut declaration = self.var_declaration(Type::Int, name, storage)?;
+        if let Statement::VarDecl { implicit_int, .. } = &mut declaration {
+            *implicit_int = true;
+        }
+        Ok(declaration)
+    }
+
+    /// Whether the next tokens are a function definition without a return
+    /// type, `name(...) {`. Anything else starting with an identifier is left
+    /// to the statement rules.
+    fn implicit_int_function(&self) -> bool {
+        if !self.check_ahead(0, TokenType::Identifier) || !self.check_ahead(1, TokenType::LParen) {
+            return false;
+        }
+        let mut depth = 0;
+        for (offset, token) in self.tokens[self.current + 1..].iter().enumerate() {
+            match token.token_type {
+                TokenType::LParen => depth += 1,
+                TokenType::RParen => depth -= 1,
+                TokenType::EOF => return false,
+                _ => (),
+            }
+            if depth == 0 {
+                return self.check_ahead(offset + 2, TokenType::LBrace);
+            }
+        }
+        false
+    }
+
+    /// Parses a type specifier followed by any number of `*`s, each of which
+    /// may be `const`-qualified, e.g. `const char* const`
+    fn type_name(&mut self) -> Result<Type, ParserError> {
+        let ty = self.specified_type()?;
+        self.pointers(ty)
+    }
+
+    /// Parses a type specifier along with its qualifiers, e.g. `const int`
+    fn specified_type(&mut self) -> Result<Type, ParserError> {
+        let leading = self.qualifiers()?;
+        let specifier = self.consume_one_of(SPECIFIERS)?;
+        let ty = match specifier.token_type {
+            TokenType::Struct => {
+                let tag = self.consume(TokenType::Identifier, "Expect struct tag")?;
+                Type::Struct(tag.literal.to_string())
+            }
+            TokenType::Union => {
+                let tag = self.consume(TokenType::Identifier, "Expect union tag")?;
+                Type::Union(tag.literal.to_string())
+            }
+            TokenType::Void => Type::Void,
+            TokenType::Float => Type::Float,
+            _ => self.integer_type(specifier)?,
+        };
+        // `int const` is the same as `const int`
+        let qualifiers = leading.union(self.qualifiers()?);
+        if qualifiers.is_restrict {
+            return Err(ParserError::InvalidRestrict {
+                line: self.previous()?.line,
+            });
+        }
+        Ok(ty.qualified(qualifiers))
+    }
+
+    /// Integer type named by `first` and any of `char`, `short`, `int`,
+    /// `long`, `signed` and `unsigned` that follow it, in any order, e.g.
+    /// `unsigned long long` or `short int`
+    fn integer_type(&mut self, first: &'a Token<'a>) -> Result<Type, ParserError> {
+        let mut words = vec![first.token_type];
+        while self.matches(INTEGER_SPECIFIERS) {
+            words.push(self.previous()?.token_type);
+        }
+        let count = |word| words.iter().filter(|&&w| w == word).count();
+        let (signed, unsigned) = (count(TokenType::Signed), count(TokenType::Unsigned));
+        let (char, short, int, long) = (
+            count(TokenType::Char),
+            count(TokenType::Short),
+            count(TokenType::Int),
+            count(TokenType::Long),
+        );
+        let invalid = ParserError::InvalidTypeSpecifiers { line: first.line };
+        if signed + unsigned > 1 || char + int > 1 || (short > 0 && long > 0) || long > 2 {
+            return Err(invalid);
+        }
+
+        let ty = match (char, short, long) {
+            (1, 0, 0) if signed > 0 => Type::SignedChar,
+            (1, 0, 0) if unsigned > 0 => Type::UnsignedChar,
+            (1, 0, 0) => Type::Char,
+            (1, _, _) => return Err(invalid),
+            (0, 1, _) => Type::Short,
+            (0, 0, 1) => Type::Long,
+            (0, 0, 2) => Type::LongLong,
+            _ => Type::Int,
+        };
+        Ok(if unsigned > 0 { ty.to_unsigned() } else { ty })
+    }
+
+    /// Wraps `ty` in a pointer for each `*` that follows, along with the
+    /// qualifiers after it
+    fn pointers(&mut self, mut ty: Type) -> Result<Type, ParserError> {
+        while self.matches(&[TokenType::Star]) {
+            ty = Type::Pointer(Box::new(ty)).qualified(self.qualifiers()?);
+        }
+        Ok(ty)
+    }
+
+    /// Parses a declarator, the name being declared along with the `*`s,
+    /// brackets and parentheses around it that derive its type from `ty`.
+    /// So `*a[10]` declares an array of pointers, while `(*a)[10]` declares a
+    /// pointer to an array.
+    fn declarator(
+        &mut self,
+        ty: Type,
+        message: &str,
+    ) -> Result<(Type, &'a Token<'a>), ParserError> {
+        let ty = self.pointers(ty)?;
+        if !self.matches(&[TokenType::LParen]) {
+            let name = self.consume(TokenType::Identifier, message)?;
+            return Ok((self.array_suffixes(ty)?, name));
+        }
+
+        // the brackets after the parentheses apply before the declarator
+        // inside them does, so that's parsed afterwards, around their type
+        let inner = self.current;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.advance()?.token_type {
+                TokenType::LParen => depth += 1,
+                TokenType::RParen => depth -= 1,
+                TokenType::EOF => return Err(ParserError::UnexpectedEOF),
+                _ => (),
+            }
+        }
+        let ty = self.array_suffixes(ty)?;
+        if self.peek_is(TokenType::LParen) {
+            return Err(ParserError::ExpectedToken {
+                expected: TokenType::Semicolon,
+                found: Some(TokenType::LParen),
+                message: "Expect end of declarator, pointers to functions aren't supported"
+                    .to_string(),
+            });
+        }
+        let end = self.current;
+        self.current = inner;
+        let declared = self.declarator(ty, message)?;
+        self.consume(TokenType::RParen, "Expect ')' after declarator")?;
+        self.current = end;
+        Ok(declared)
+    }
+
+    /// Wraps `ty` in an array for each `[length]` or `[]` that follows, the
+    /// last innermost, as `int m[2][3]` is two arrays of three `int`s
+    fn array_suffixes(&mut self, ty: Type) -> Result<Type, ParserError> {
+        let mut lengths = vec![];
+        while self.matches(&[TokenType::LBracket]) {
+            let bracket = self.previous()?;
+            let length = if self.peek_is(TokenType::RBracket) {
+                None
+            } else {
+                let length = self.constant_expression()?;
+                match eval::evaluate_int(&length, &self.target, &self.enumerators, &self.layouts) {
+                    Ok(length) if length > 0 => Some(length as u64),
+                    Err(EvalError::NotConstant { .. }) => {
+                        return Err(ParserError::VariableLengthArray { line: bracket.line });
+                    }
+                    _ => return Err(ParserError::InvalidArrayLength { line: bracket.line }),
+                }
+            };
+            self.consume(TokenType::RBracket, "Expect ']' after array length")?;
+            lengths.push(length);
+        }
+        let ty = lengths
+            .into_iter()
+            .rev()
+            .fold(ty, |element, length| Type::Array(Box::new(element), length));
+        Ok(ty)
+    }
+
+    /// Consumes any type qualifiers. A repeated qualifier means the same as a
+    /// single one.
+    fn qualifiers(&mut self) -> Result<Qualifiers, ParserError> {
+        let mut qualifiers = Qualifiers::default();
+        while self.matches(QUALIFIERS) {
+            match self.previous()?.token_type {
+                TokenType::Const => qualifiers.is_const = true,
+                TokenType::Volatile => qualifiers.is_volatile = true,
+                TokenType::Restrict => qualifiers.is_restrict = true,
+                _ => qualifiers.is_atomic = true,
+            }
+        }
+        Ok(qualifiers)
+    }
+
+    fn function(
+        &mut self,
+        return_type: Type,
+        name: &'a Token<'a>,
+        implicit_int: bool,
+        specifiers: FunctionSpecifiers,
+    ) -> Result<Statement<'a>, ParserError> {
+        let mut params = vec![];
+        let mut variadic = false;
+        let has_prototype = !self.matches(&[TokenType::RParen]);
+        if has_prototype {
+            loop {
+                // `...` can only follow a named parameter, and is always last
+                if self.matches(&[TokenType::Ellipsis]) {
+                    if params.is_empty() {
+                        return Err(ParserError::LoneEllipsis {
+                            line: self.previous()?.line,
+                        });
+                    }
+                    variadic = true;
+                    self.consume(TokenType::RParen, "Expect ')' after '...'")?;
+                    break;
+                }
+                let ty = self.specified_type()?;
+                // `(void)` declares an empty parameter list, as `()` only does
+                // from C23, the type checker deciding which applies
+                if ty == Type::Void && params.is_empty() && self.matches(&[TokenType::RParen]) {
+                    break;
+                }
+                let (ty, name) = self.declarator(ty, "Expect parameter name")?;
+                self.attributes()?;
+                // an array parameter is really a pointer to its first element
+                let ty = ty.decayed();
+                params.push(Parameter { ty, name });
+                let next = self.consume_one_of(&[TokenType::Comma, TokenType::RParen])?;
+                if next.token_type == TokenType::RParen {
+                    break;
+                }
+            }
+        }
+        if !implicit_int && self.matches(&[TokenType::Semicolon]) {
+            return Ok(Statement::Prototype {
+                return_type,
+                name,
+                params,
+                variadic,
+                has_prototype,
+                specifiers,
+            });
+        }
+        self.consume(TokenType::LBrace, "Expect '{' before function body")?;
+        let enclosing = self.function.replace(name);
+        let body = self.block();
+        self.function = enclosing;
+        let body = body?;
+
+        Ok(Statement::Function {
+            return_type,
+            name,
+            params,
+            body,
+            variadic,
+            has_prototype,
+            implicit_int,
+            specifiers,
+        })
+    }
+
+    fn struct_declaration(&mut self) -> Result<Statement<'a>, ParserError> {
+        let keyword = self.consume_one_of(&[TokenType::Struct, TokenType::Union])?;
+        let is_union = keyword.token_type == TokenType::Union;
+        let name = self.consume(TokenType::Identifier, "Expect struct tag")?;
+        self.consume(TokenType::LBrace, "Expect '{' before struct members")?;
+
+        let mut members = vec![];
+        while !self.peek_is(TokenType::RBrace) && !self.at_end() {
+            let ty = self.specified_type()?;
+            // only a bit-field may be unnamed
+            let (ty, name) = if self.peek_is(TokenType::Colon) {
+                (self.pointers(ty)?, None)
+            } else {
+                let (ty, name) = self.declarator(ty, "Expect member name")?;
+                (ty, Some(name))
+            };
+            let width = if self.matches(&[TokenType::Colon]) {
+                if !ty.is_integer() {
+                    return Err(ParserError::InvalidBitField {
+                        line: self.previous()?.line,
+                        ty,
+                    });
+                }
+                Some(self.constant_expression()?)
+            } else {
+                None
+            };
+            self.consume(TokenType::Semicolon, "Expect ';' after struct member")?;
+            members.push(Member { ty, name, width });
+        }
+        self.consume(TokenType::RBrace, "Expect '}' after struct members")?;
+        self.consume(TokenType::Semicolon, "Expect ';' after struct definition")?;
+
+        // for `sizeof` in a later array length, any error being left to the
+        // analyses
+        let _ = self.layouts.define(
+            &name.literal,
+            &members,
+            is_union,
+            &self.target,
+            &self.enumerators,
+        );
+        Ok(Statement::Struct {
+            name,
+            members,
+            is_union,
+        })
+    }
+
+    fn enum_declaration(&mut self) -> Result<Statement<'a>, ParserError> {
+        let keyword = self.consume(TokenType::Enum, "Expect 'enum'")?;
+        let name = if self.peek_is(TokenType::Identifier) {
+            Some(self.advance()?)
+        } else {
+            None
+        };
+        self.consume(TokenType::LBrace, "Expect '{' before enumerators")?;
+
+        let mut enumerators = vec![];
+        // like an initialiser list, a trailing comma is allowed
+        while !self.peek_is(TokenType::RBrace) {
+            let name = self.consume(TokenType::Identifier, "Expect enumerator name")?;
+            let value = if self.matches(&[TokenType::Equal]) {
+                Some(self.constant_expression()?)
+            } else {
+                None
+            };
+            enumerators.push(Enumerator { name, value });
+            if !self.matches(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(TokenType::RBrace, "Expect '}' after enumerators")?;
+        self.consume(TokenType::Semicolon, "Expect ';' after enum definition")?;
+
+        // for later array lengths, again leaving errors to the analyses
+        let _ = self
+            .enumerators
+            .define(&enumerators, &self.target, &self.layouts);
+        Ok(Statement::Enum {
+            keyword,
+            name,
+            enumerators,
+        })
+    }
+
+    fn var_declaration(
+        &mut self,
+        ty: Type,
+        name: &'a Token<'a>,
+        storage: StorageClass,
+    ) -> Result<Statement<'a>, ParserError> {
+        let declaration = self.declarator_initialiser(ty, name, storage)?;
+        self.end_statement("Expect ';' after variable declaration")?;
+        Ok(declaration)
+    }
+
+    /// Declaration of `name` with the optional `= initialiser` that follows
+    fn declarator_initialiser(
+        &mut self,
+        ty: Type,
+        name: &'a Token<'a>,
+        storage: StorageClass,
+    ) -> Result<Statement<'a>, ParserError> {
+        let initialiser = if self.matches(&[TokenType::Equal]) {
+            Some(self.initialiser()?)
+        } else {
+            None
+        };
+        Ok(Statement::VarDecl {
+            ty,
+            name,
+            storage,
+            initialiser,
+            implicit_int: false,
+        })
+    }
+
+    fn initialiser(&mut self) -> Result<Initialiser<'a>, ParserError> {
+        if !self.matches(&[TokenType::LBrace]) {
+            return Ok(Initialiser::Expr(self.expression()?));
+        }
+        let brace = self.previous()?;
+
+        let mut items = vec![];
+        // a trailing comma is always allowed here, even in strict C
+        while !self.peek_is(TokenType::RBrace) {
+            items.push(self.init_item()?);
+            if !self.matches(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(TokenType::RBrace, "Expect '}' after initialiser list")?;
+        Ok(Initialiser::List { brace, items })
+    }
+
+    fn init_item(&mut self) -> Result<InitItem<'a>, ParserError> {
+        let designator = if self.matches(&[TokenType::Dot]) {
+            let member = self.consume(TokenType::Identifier, "Expect member name after '.'")?;
+            Some(Designator::Member(member))
+        } else if self.matches(&[TokenType::LBracket]) {
+            let index = self.expression()?;
+            self.consume(TokenType::RBracket, "Expect ']' after array index")?;
+            Some(Designator::Index(index))
+        } else {
+            None
+        };
+        if designator.is_some() {
+            self.consume(TokenType::Equal, "Expect '=' after designator")?;
+        }
+
+        Ok(InitItem {
+            designator,
+            value: self.initialiser()?,
+        })
+    }
+
+    /// Parses the declarations of a block, with the opening `{` already consumed
+    fn block(&mut self) -> Result<Vec<Statement<'a>>, ParserError> {
+        let mut statements = vec![];
+        while !self.peek_is(TokenType::RBrace) && !self.at_end() {
+            statements.push(self.declaration()?);
+        }
+        self.consume(TokenType::RBrace, "Expect '}' after block")?;
+        Ok(statements)
+    }
+
+    fn statement(&mut self) -> Result<Statement<'a>, ParserError> {
+        if self.matches(&[TokenType::Return]) {
+            return self.return_statement();
+        }
+        if self.matches(&[TokenType::If]) {
+            return self.if_statement();
+        }
+        if self.matches(&[TokenType::While]) {
+            return self.while_statement();
+        }
+        if self.matches(&[TokenType::For]) {
+            return self.for_statement();
+        }
+        if self.matches(&[TokenType::Switch]) {
+            let keyword = self.previous()?;
+            let condition = self.condition()?;
+            let body = Box::new(self.statement()?);
+            return Ok(Statement::Switch {
+                keyword,
+                condition,
+                body,
+            });
+        }
+        if self.matches(&[TokenType::Case]) {
+            let keyword = self.previous()?;
+            let value = self.constant_expression()?;
+            self.consume(TokenType::Colon, "Expect ':' after case value")?;
+            let body = Box::new(self.statement()?);
+            return Ok(Statement::Case {
+                keyword,
+                value,
+                body,
+            });
+        }
+        if self.matches(&[TokenType::Default]) {
+            let keyword = self.previous()?;
+            self.consume(TokenType::Colon, "Expect ':' after 'default'")?;
+            let body = Box::new(self.statement()?);
+            return Ok(Statement::Default { keyword, body });
+        }
+        if self.matches(&[TokenType::Break, TokenType::Continue]) {
+            let keyword = self.previous()?;
+            self.end_statement("Expect ';' after loop control")?;
+            if keyword.token_type == TokenType::Break {
+                return Ok(Statement::Break(keyword));
+            }
+            return Ok(Statement::Continue(keyword));
+        }
+        if self.matches(&[TokenType::Goto]) {
+            let keyword = self.previous()?;
+            let label = self.consume(TokenType::Identifier, "Expect label name after 'goto'")?;
+            self.end_statement("Expect ';' after goto")?;
+            return Ok(Statement::Goto { keyword, label });
+        }
+        if self.peek_is(TokenType::Identifier) && self.check_ahead(1, TokenType::Colon) {
+            let label = self.advance()?;
+            self.advance()?;
+            let body = Box::new(self.statement()?);
+            return Ok(Statement::Labeled { label, body });
+        }
+        if self.matches(&[TokenType::LBrace]) {
+            return Ok(Statement::Block(self.block()?));
+        }
+
+        let expr = self.expression()?;
+        self.end_statement("Expect ';' after expression")?;
+        Ok(Statement::Expression(expr))
+    }
+
+    fn if_statement(&mut self) -> Result<Statement<'a>, ParserError> {
+        let keyword = self.previous()?;
+        let condition = self.condition()?;
+        let then_branch = Box::new(self.statement()?);
+        let else_branch = if self.matches(&[TokenType::Else]) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+        Ok(Statement::If {
+            keyword,
+            condition,
+            then_branch,
+            else_branch,
+        })
+    }
+
+    fn while_statement(&mut self) -> Result<Statement<'a>, ParserError> {
+        let keyword = self.previous()?;
+        let condition = self.condition()?;
+        let body = Box::new(self.statement()?);
+        Ok(Statement::While {
+            keyword,
+            condition,
+            body,
+        })
+    }
+
+    fn for_statement(&mut self) -> Result<Statement<'a>, ParserError> {
+        let keyword = self.previous()?;
+        self.consume(TokenType::LParen, "Expect '(' after 'for'")?;
+
+        let initialiser = if self.matches(&[TokenType::Semicolon]) {
+            vec![]
+        } else if self.peek_is_any(TYPE_SPECIFIERS) {
+            // each declarator shares the specifier but has its own `*`s and
+            // brackets, as in `int i = 0, *p = 0`
+            let specified = self.specified_type()?;
+            let mut declarations = vec![];
+            loop {
+                let (ty, name) = self.declarator(specified.clone(), "Expect variable name")?;
+                declarations.push(self.declarator_initialiser(
+                    ty,
+                    name,
+                    StorageClass::Unspecified,
+                )?);
+                let next = self.consume_one_of(&[TokenType::Comma, TokenType::Semicolon])?;
+                if next.token_type == TokenType::Semicolon {
+                    break;
+                }
+            }
+            declarations
+        } else {
+            let expr = self.comma_expression()?;
+            self.consume(TokenType::Semicolon, "Expect ';' after loop initialiser")?;
+            vec![Statement::Expression(expr)]
+        };
+
+        let condition = if self.peek_is(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after loop condition")?;
+
+        let increment = if self.peek_is(TokenType::RParen) {
+            None
+        } else {
+            Some(self.comma_expression()?)
+        };
+        self.consume(TokenType::RParen, "Expect ')' after for clauses")?;
+
+        let body = Box::new(self.statement()?);
+        Ok(Statement::For {
+            keyword,
+            initialiser,
+            condition,
+            increment,
+            body,
+        })
+    }
+
+    /// Parenthesised condition of an `if` or `while`
+    fn condition(&mut self) -> Result<Expr<'a>, ParserError> {
+        self.consume(TokenType::LParen, "Expect '(' before condition")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RParen, "Expect ')' after condition")?;
+        Ok(condition)
+    }
+
+    fn return_statement(&mut self) -> Result<Statement<'a>, ParserError> {
+        let keyword = self.previous()?;
+        let value = if self.peek_is(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.end_statement("Expect ';' after return value")?;
+        Ok(Statement::Return { keyword, value })
+    }
+}
+
+#[cfg(test)]
+mod parser_tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    #[test]
+    fn parse_expression_respects_precedence() {
+        let mut lexer = Lexer::from_string("1 + 2 * 3");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+
+        let ExprKind::Binary {
+            left,
+            operator,
+            right,
+        } = expr.kind
+        else {
+            panic!("Expected a binary expression");
+        };
+        assert_eq!(operator.token_type, TokenType::Plus);
+        assert!(matches!(left.kind, ExprKind::Literal(Literal::Int(1))));
+        assert!(matches!(
+            right.kind,
+            ExprKind::Binary { operator, .. } if operator.token_type == TokenType::Star
+        ));
+    }
+
+    #[test]
+    fn parse_expression_rejects_trailing_tokens() {
+        let mut lexer = Lexer::from_string("1 + 2 foo");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let result = Parser::new(tokens).parse_expression();
+
+        assert!(matches!(
+            result,
+            Err(ParserError::ExpectedToken {
+                expected: TokenType::EOF,
+                found: Some(TokenType::Identifier),
+                ..
+            })
+        ));
+    }
+
+    fn parse_error(source: &str) -> ParserError {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        match Parser::new(tokens).parse() {
+            Ok(_) => panic!("Expected a syntax error"),
+            Err(error) => error,
+        }
+    }
+
+    /// Members of the single struct definition in `source`
+    fn struct_members(source: &str) -> Vec<(Option<String>, Type, Option<i64>)> {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let [Statement::Struct { members, .. }] = &program[..] else {
+            panic!("Expected a single struct definition");
+        };
+        members
+            .iter()
+            .map(|m| {
+                let width = m.width.as_ref().map(|width| match width.kind {
+                    ExprKind::Literal(Literal::Int(bits)) => bits,
+                    _ => panic!("Expected a literal width"),
+                });
+                (m.name.map(|n| n.literal.to_string()), m.ty.clone(), width)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bit_fields_record_their_width() {
+        let members = struct_members("struct flags { int ready : 1; char mode : 3; int x; };");
+        assert_eq!(
+            members,
+            [
+                (Some("ready".to_string()), Type::Int, Some(1)),
+                (Some("mode".to_string()), Type::Char, Some(3)),
+                (Some("x".to_string()), Type::Int, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn unnamed_bit_field_pads_layout() {
+        let members = struct_members("struct s { int a : 4; int : 0; int b : 4; };");
+        assert_eq!(members[1], (None, Type::Int, Some(0)));
+        assert_eq!(members.len(), 3);
+    }
+
+    #[test]
+    fn bit_field_must_have_integer_type() {
+        assert!(matches!(
+            parse_error("struct s { float f : 2; };"),
+            ParserError::InvalidBitField {
+                line: 1,
+                ty: Type::Float
+            }
+        ));
+        // only a bit-field may leave out its name
+        assert!(matches!(
+            parse_error("struct s { int; };"),
+            ParserError::ExpectedToken {
+                expected: TokenType::Identifier,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn declarator_list_error_lists_alternatives() {
+        let error = parse_error("void f(void) {\n  for (int i = 0, j = 1) {}\n}");
+        let ParserError::ExpectedOneOf {
+            line,
+            expected,
+            found,
+        } = &error
+        else {
+            panic!("Expected an ExpectedOneOf error, got {:?}", error);
+        };
+        assert_eq!(*line, 2);
+        assert_eq!(expected, &[TokenType::Comma, TokenType::Semicolon]);
+        assert_eq!(*found, Some(TokenType::RParen));
+        assert_eq!(
+            error.to_string(),
+            "On line 2, expected one of `,`, `;` but found `)`"
+        );
+    }
+
+    #[test]
+    fn type_specifier_error_lists_alternatives() {
+        let error = parse_error("int f(const x) { return 0; }");
+        assert!(matches!(
+            &error,
+            ParserError::ExpectedOneOf {
+                line: 1,
+                found: Some(TokenType::Identifier),
+                ..
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "On line 1, expected one of `int`, `float`, `char`, `short`, `long`, `signed`, `unsigned`, `void`, `struct`, `union` but found `identifier`"
+        );
+
+        // parameters are followed by `,` or `)`
+        let error = parse_error("int f(int x;");
+        assert_eq!(
+            error.to_string(),
+            "On line 1, expected one of `,`, `)` but found `;`"
+        );
+    }
+
+    #[test]
+    fn unclosed_grouping_is_an_error() {
+        let mut lexer = Lexer::from_string("(1 + 2");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let result = Parser::new(tokens).parse_expression();
+
+        assert!(matches!(
+            result,
+            Err(ParserError::ExpectedToken {
+                expected: TokenType::RParen,
+                found: Some(TokenType::EOF),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn braces_are_not_a_grouping() {
+        let mut lexer = Lexer::from_string("{1, 2}");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let result = Parser::new(tokens).parse_expression();
+        assert!(matches!(
+            result,
+            Err(ParserError::UnknownPrimaryToken {
+                token_type: TokenType::LBrace,
+                ..
+            })
+        ));
+
+        let mut lexer = Lexer::from_string("(1+2)");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+        assert!(matches!(expr.kind, ExprKind::Grouping(_)));
+    }
+
+    #[test]
+    fn braces_in_declaration_are_an_initialiser_list() {
+        let mut lexer = Lexer::from_string("int a = {1, {2, 3}};");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+
+        let [
+            Statement::VarDecl {
+                initialiser: Some(Initialiser::List { items, .. }),
+                ..
+            },
+        ] = program.as_slice()
+        else {
+            panic!("Expected a declaration with an initialiser list");
+        };
+        assert_eq!(items.len(), 2);
+        assert!(matches!(
+            &items[0].value,
+            Initialiser::Expr(Expr {
+                kind: ExprKind::Literal(Literal::Int(1)),
+                ..
+            })
+        ));
+        assert!(
+            matches!(&items[1].value, Initialiser::List { items: inner, .. } if inner.len() == 2)
+        );
+    }
+
+    /// Items of the initialiser list `source` initialises its variable with
+    fn init_list<T>(source: &str, f: impl FnOnce(&[InitItem]) -> T) -> T {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let [
+            Statement::VarDecl {
+                initialiser: Some(Initialiser::List { items, .. }),
+                ..
+            },
+        ] = program.as_slice()
+        else {
+            panic!("Expected a declaration with an initialiser list");
+        };
+        f(items)
+    }
+
+    /// Resolves array designators, which are integer literals in these tests
+    fn array_index(designator: &Designator) -> Result<usize, ()> {
+        designator.literal_index().ok_or(())
+    }
+
+    #[test]
+    fn struct_designated_initialiser() {
+        let members = ["x", "y"];
+        let positions = init_list("struct point p = { .y = 2, .x = 1 };", |items| {
+            assert!(matches!(
+                &items[0].designator,
+                Some(Designator::Member(member)) if member.literal == "y"
+            ));
+            init_positions(items, |designator| match designator {
+                Designator::Member(member) => {
+                    members.iter().position(|m| *m == member.literal).ok_or(())
+                }
+                Designator::Index(_) => Err(()),
+            })
+        });
+        assert_eq!(positions, Ok(vec![1, 0]));
+    }
+
+    #[test]
+    fn array_designated_initialiser() {
+        let positions = init_list("int a = { [3] = 1, [1] = {2} };", |items| {
+            assert!(
+                matches!(&items[1].value, Initialiser::List { items: inner, .. } if inner.len() == 1)
+            );
+            init_positions(items, array_index)
+        });
+        assert_eq!(positions, Ok(vec![3, 1]));
+    }
+
+    #[test]
+    fn positional_items_follow_designated_ones() {
+        let positions = init_list("int a = { 5, [4] = 1, 2, [1] = 3, 4 };", |items| {
+            init_positions(items, array_index)
+        });
+        assert_eq!(positions, Ok(vec![0, 4, 5, 1, 2]));
+    }
+
+    #[test]
+    fn nested_expression_span_covers_outer_operators() {
+        let mut lexer = Lexer::from_string("-(1 + 2) * 3");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+
+        // everything except the trailing EOF
+        assert_eq!(expr.span, Span { start: 0, end: 8 });
+        let ExprKind::Binary { left, right, .. } = expr.kind else {
+            panic!("Expected a binary expression");
+        };
+        assert_eq!(right.span, Span { start: 7, end: 8 });
+
+        let unary = left.span.tokens(tokens);
+        assert_eq!(unary.first().unwrap().token_type, TokenType::Minus);
+        assert_eq!(unary.last().unwrap().token_type, TokenType::RParen);
+        let ExprKind::Unary {
+            right: grouping, ..
+        } = left.kind
+        else {
+            panic!("Expected a unary expression");
+        };
+        let ExprKind::Grouping(inner) = grouping.kind else {
+            panic!("Expected a grouping");
+        };
+        assert_eq!(grouping.span, Span { start: 1, end: 6 });
+        assert_eq!(inner.span, Span { start: 2, end: 5 });
+    }
+
+    #[test]
+    fn statements_iterator_matches_parse() {
+        let source = "struct p { int x; };\nint a = 1;\nint main(void) { return a; }";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        let parsed = Parser::new(tokens).parse().expect("Should parse");
+        let iterated: Vec<_> = Parser::new(tokens)
+            .statements()
+            .collect::<Result<_, _>>()
+            .expect("Should parse");
+
+        assert_eq!(iterated.len(), parsed.len());
+        for (a, b) in iterated.iter().zip(&parsed) {
+            assert_eq!(a.print_tree(), b.print_tree());
+        }
+    }
+
+    #[test]
+    fn statements_iterator_recovers_from_errors() {
+        let mut lexer = Lexer::from_string("int a = ;\nint b = 2;");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        let results: Vec<_> = Parser::new(tokens).statements().collect();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+
+        let mut parser = Parser::new(tokens).recover_errors(true);
+        let results: Vec<_> = parser.statements().collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_err());
+        assert!(matches!(
+            &results[1],
+            Ok(Statement::VarDecl { name, .. }) if name.literal == "b"
+        ));
+    }
+
+    #[test]
+    fn recovery_resumes_at_next_statement() {
+        let source = "x = 1 + (2 * if (y) { return; } z;";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let mut parser = Parser::new(tokens);
+        let resync_points: Vec<_> = (0..tokens.len())
+            .filter(|&i| {
+                parser.current = i;
+                parser.recover_at()
+            })
+            .map(|i| tokens[i].lexeme())
+            .collect();
+        assert_eq!(resync_points, ["if", "{", "return", "}", "z", ""]);
+
+        // the error at `if` resumes at the `{` after it, not within `(y)`
+        let source = "int a = 1 + (2 * if (y) {} int b = 2;";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let mut parser = Parser::new(tokens).recover_errors(true);
+        let results: Vec<_> = parser.statements().collect();
+        assert!(matches!(
+            results[..],
+            [
+                Err(ParserError::UnknownPrimaryToken { .. }),
+                Ok(Statement::Block(_)),
+                Ok(Statement::VarDecl { name, .. }),
+            ] if name.literal == "b"
+        ));
+    }
+
+    #[test]
+    fn recovery_stops_at_max_errors() {
+        let source = "int a = ;\n".repeat(30);
+        let mut lexer = Lexer::from_string(&source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        let mut parser = Parser::new(tokens).recover_errors(true).max_errors(5);
+        let results: Vec<_> = parser.statements().collect();
+        assert_eq!(results.len(), 6);
+        assert!(
+            results[..5]
+                .iter()
+                .all(|r| matches!(r, Err(ParserError::UnknownPrimaryToken { .. })))
+        );
+        let Err(note @ ParserError::TooManyErrors { limit: 5 }) = &results[5] else {
+            panic!("Expected a too many errors note, got {:?}", results[5]);
+        };
+        assert_eq!(note.to_string(), "too many errors (5), stopping now");
+
+        // the default limit, and no note when the errors run out with the input
+        let mut parser = Parser::new(tokens).recover_errors(true);
+        assert_eq!(parser.statements().count(), 21);
+        let mut parser = Parser::new(tokens).recover_errors(true).max_errors(30);
+        assert_eq!(parser.statements().count(), 30);
+        let mut parser = Parser::new(tokens).recover_errors(true).max_errors(0);
+        assert_eq!(parser.statements().count(), 30);
+    }
+
+    #[test]
+    fn trailing_comma_in_call_depends_on_option() {
+        let mut lexer = Lexer::from_string("f(a, b,)");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        let result = Parser::new(tokens).parse_expression();
+        assert!(matches!(
+            result,
+            Err(ParserError::UnknownPrimaryToken {
+                token_type: TokenType::RParen,
+                ..
+            })
+        ));
+
+        let expr = Parser::new(tokens)
+            .allow_trailing_comma(true)
+            .parse_expression()
+            .expect("Should parse");
+        assert!(matches!(&expr.kind, ExprKind::Call { arguments, .. } if arguments.len() == 2));
+        assert_eq!(expr.span, Span { start: 0, end: 7 });
+
+        // a lone comma is never a valid argument list
+        let mut lexer = Lexer::from_string("f(,)");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let result = Parser::new(tokens)
+            .allow_trailing_comma(true)
+            .parse_expression();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn cloned_tree_is_independent() {
+        let mut lexer = Lexer::from_string("int main(void) { return 1; }");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+
+        let mut copy = program.clone();
+        let Statement::Function { body, .. } = &mut copy[0] else {
+            panic!("Expected a function");
+        };
+        let Statement::Return {
+            value: Some(value), ..
+        } = &mut body[0]
+        else {
+            panic!("Expected a return value");
+        };
+        value.kind = ExprKind::Literal(Literal::Int(2));
+        body.push(Statement::Block(vec![]));
+
+        let original = format!("{:?}", program);
+        assert!(original.contains("Int(1)") && !original.contains("Int(2)"));
+        assert!(!original.contains("Block"));
+        assert!(format!("{:?}", copy).contains("Int(2)"));
+    }
+
+    #[test]
+    fn static_assertion() {
+        let source = "static_assert(1, \"one\");\n_Static_assert(2 > 1, \"a\" \"b\");";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let messages: Vec<_> = program
+            .iter()
+            .map(|stmt| match stmt {
+                Statement::StaticAssert { message, .. } => &**message,
+                other => panic!("Expected a static assertion, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(messages, vec!["one", "ab"]);
+
+        let mut lexer = Lexer::from_string("_Static_assert(1);");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let error = Parser::new(tokens)
+            .parse()
+            .expect_err("Should need a message");
+        assert_eq!(error.to_string(), "Expected token ',', found ')'");
+    }
+
+    #[test]
+    fn function_specifiers() {
+        let source = "inline int f(void) { return 1; }\n\
+                      _Noreturn void g(void) { for (;;) {} }\n\
+                      _Noreturn inline void h(void);\n\
+                      inline static int i(void) { return 2; }";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let specifiers: Vec<_> = program
+            .iter()
+            .map(|stmt| match stmt {
+                Statement::Function { specifiers, .. }
+                | Statement::Prototype { specifiers, .. } => (
+                    specifiers.is_static,
+                    specifiers.is_inline,
+                    specifiers.is_noreturn,
+                ),
+                other => panic!("Expected a function, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            specifiers,
+            vec![
+                (false, true, false),
+                (false, false, true),
+                (false, true, true),
+                (true, true, false)
+            ]
+        );
+        assert!(
+            program[2]
+                .print_tree()
+                .contains("(inline _Noreturn void h())")
+        );
+        assert!(program[3].print_tree().contains("(static inline int i())"));
+
+        let mut lexer = Lexer::from_string("inline int x = 1;");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let error = Parser::new(tokens).parse().expect_err("Should fail");
+        assert_eq!(
+            error.to_string(),
+            "On line 1, 'inline' can only appear on functions"
+        );
+    }
+
+    #[test]
+    fn peek_predicates_leave_tokens_unconsumed() {
+        let mut lexer = Lexer::from_string("int x;");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let mut parser = Parser::new(tokens);
+
+        assert!(parser.peek_is(TokenType::Int));
+        assert!(!parser.peek_is(TokenType::Identifier));
+        assert!(parser.peek_is_any(&[TokenType::Char, TokenType::Int]));
+        assert!(!parser.peek_is_any(&[TokenType::Char, TokenType::Float]));
+        assert!(!parser.peek_is_any(&[]));
+        assert!(!parser.at_end());
+        assert_eq!(parser.current, 0);
+
+        parser.current = 2;
+        assert!(parser.peek_is(TokenType::Semicolon));
+        assert!(!parser.at_end());
+        parser.current = 3;
+        assert!(parser.at_end());
+        assert!(parser.peek_is(TokenType::EOF));
+        // past the end there's nothing to peek at
+        parser.current = 4;
+        assert!(!parser.peek_is(TokenType::EOF));
+        assert!(!parser.peek_is_any(&[TokenType::EOF, TokenType::Semicolon]));
+    }
+
+    #[test]
+    fn variables_record_storage_class() {
+        let source = "static int total;\nint f(void) {\n  static int counter;\n  int x;\n  \
+                      register int r;\n  return x;\n}";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let Statement::Function { body, .. } = &program[1] else {
+            panic!("Expected a function");
+        };
+        let storage: Vec<_> = program[..1]
+            .iter()
+            .chain(body)
+            .filter_map(|stmt| match stmt {
+                Statement::VarDecl { name, storage, .. } => Some((&*name.literal, *storage)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            storage,
+            [
+                ("total", StorageClass::Static),
+                ("counter", StorageClass::Static),
+                ("x", StorageClass::Unspecified),
+                ("r", StorageClass::Register),
+            ]
+        );
+        assert_eq!(
+            StorageClass::Static.duration(false),
+            StorageDuration::Static
+        );
+        assert_eq!(
+            StorageClass::Unspecified.duration(false),
+            StorageDuration::Automatic
+        );
+        assert!(body[0].print_tree().contains("(static int counter)"));
+
+        for (source, message) in [
+            (
+                "register int x;",
+                "On line 1, 'register' is not allowed here",
+            ),
+            (
+                "register int f(void) { return 0; }",
+                "On line 1, 'register' is not allowed here",
+            ),
+            (
+                "int f(void) { static register int x; return 0; }",
+                "On line 1, 'register' is not allowed here",
+            ),
+            (
+                "static inline int x;",
+                "On line 1, 'inline' can only appear on functions",
+            ),
+        ] {
+            let mut lexer = Lexer::from_string(source);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            let error = Parser::new(tokens).parse().expect_err("Should fail");
+            assert_eq!(error.to_string(), message, "{source}");
+        }
+    }
+
+    #[test]
+    fn variadic_prototype() {
+        let mut lexer = Lexer::from_string("int printf(const char *fmt, ...);");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let [
+            Statement::Prototype {
+                name,
+                params,
+                variadic: true,
+                ..
+            },
+        ] = &program[..]
+        else {
+            panic!("Expected a variadic prototype");
+        };
+        assert_eq!(name.literal, "printf");
+        assert_eq!(params.len(), 1);
+        assert_eq!(params[0].ty.to_string(), "const char*");
+    }
+
+    #[test]
+    fn empty_parentheses_give_no_prototype() {
+        let mut lexer = Lexer::from_string("int f();\nint g(void);\nint h() { return 0; }");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        assert!(matches!(
+            &program[..],
+            [
+                Statement::Prototype {
+                    has_prototype: false,
+                    ..
+                },
+                Statement::Prototype {
+                    has_prototype: true,
+                    ..
+                },
+                Statement::Function {
+                    has_prototype: false,
+                    ..
+                },
+            ]
+        ));
+    }
+
+    #[test]
+    fn ellipsis_must_be_last_after_a_parameter() {
+        assert!(matches!(
+            parse_error("int f(...);"),
+            ParserError::LoneEllipsis { line: 1 }
+        ));
+        assert!(matches!(
+            parse_error("int f(int a, ..., int b);"),
+            ParserError::ExpectedToken {
+                expected: TokenType::RParen,
+                found: Some(TokenType::Comma),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn missing_semicolon_is_inserted_when_allowed() {
+        let mut lexer = Lexer::from_string("int main(void) {\n  int x = 1\n  return 0\n}");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        let result = Parser::new(tokens).parse();
+        assert!(matches!(
+            result,
+            Err(ParserError::ExpectedToken {
+                expected: TokenType::Semicolon,
+                found: Some(TokenType::Return),
+                ..
+            })
+        ));
+
+        let mut parser = Parser::new(tokens).allow_missing_semicolons(true);
+        let program = parser.parse().expect("Should parse");
+        let [Statement::Function { body, .. }] = &program[..] else {
+            panic!("Expected a single function");
+        };
+        assert!(matches!(
+            &body[..],
+            [Statement::VarDecl { .. }, Statement::Return { .. }]
+        ));
+        let lines: Vec<_> = parser.warnings().iter().map(|w| w.line).collect();
+        assert_eq!(lines, [2, 3]);
+    }
+
+    #[test]
+    fn missing_semicolon_mid_line_is_still_an_error() {
+        let mut lexer = Lexer::from_string("int main(void) { return 0 1; }");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let mut parser = Parser::new(tokens).allow_missing_semicolons(true);
+        assert!(matches!(
+            parser.parse(),
+            Err(ParserError::ExpectedToken {
+                expected: TokenType::Semicolon,
+                found: Some(TokenType::Constant),
+                ..
+            })
+        ));
+        assert!(parser.warnings().is_empty());
+    }
+
+    #[test]
+    fn implicit_int_is_an_error_in_strict_mode() {
+        let mut lexer = Lexer::from_string("main(void) { return 0; }");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        assert!(matches!(
+            &program[..],
+            [Statement::Function {
+                return_type: Type::Int,
+                implicit_int: true,
+                ..
+            }]
+        ));
+
+        let result = Parser::new(tokens).strict(true).parse();
+        assert!(matches!(result, Err(ParserError::ImplicitInt { line: 1 })));
+
+        let mut lexer = Lexer::from_string("static x = 1;");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        assert!(matches!(
+            &program[..],
+            [Statement::VarDecl {
+                ty: Type::Int,
+                storage: StorageClass::Static,
+                implicit_int: true,
+                ..
+            }]
+        ));
+        let result = Parser::new(tokens).strict(true).parse();
+        assert!(matches!(result, Err(ParserError::ImplicitInt { line: 1 })));
+    }
+
+    #[test]
+    fn attributes_are_skipped_with_gnu_extensions() {
+        let source = "__attribute__((unused)) int f(int x __attribute__((unused))) { \
+                      int y __attribute__((aligned(8), unused)) = 1; return y; }";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        assert!(Parser::new(tokens).parse().is_err());
+
+        let program = Parser::new(tokens)
+            .allow_gnu_extensions(true)
+            .parse()
+            .expect("Should parse");
+        let [
+            Statement::Function {
+                name, params, body, ..
+            },
+        ] = &program[..]
+        else {
+            panic!("Expected a single function");
+        };
+        assert_eq!(name.literal, "f");
+        assert_eq!(params.len(), 1);
+        assert!(matches!(
+            &body[..],
+            [Statement::VarDecl { name, .. }, Statement::Return { .. }] if name.literal == "y"
+        ));
+    }
+
+    #[test]
+    fn statement_expression_depends_on_option() {
+        let mut lexer = Lexer::from_string("({ int y = 1; y + 2; })");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+        let result = Parser::new(tokens).parse_expression();
+        assert!(matches!(
+            result,
+            Err(ParserError::UnknownPrimaryToken {
+                token_type: TokenType::LBrace,
+                ..
+            })
+        ));
+
+        let expr = Parser::new(tokens)
+            .allow_gnu_extensions(true)
+            .parse_expression()
+            .expect("Should parse");
+        let ExprKind::StmtExpr(body) = &expr.kind else {
+            panic!("Expected a statement expression");
+        };
+        assert!(matches!(
+            &body[..],
+            [
+                Statement::VarDecl { .. },
+                Statement::Expression(Expr {
+                    kind: ExprKind::Binary { .. },
+                    ..
+                })
+            ]
+        ));
+        assert_eq!(expr.span, Span { start: 0, end: 13 });
+    }
+
+    #[test]
+    fn trailing_comma_in_initialiser_list() {
+        for allow in [false, true] {
+            let mut lexer = Lexer::from_string("int a = {1, 2,};");
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            let program = Parser::new(tokens)
+                .allow_trailing_comma(allow)
+                .parse()
+                .expect("Should parse");
+            assert!(matches!(
+                &program[0],
+                Statement::VarDecl {
+                    initialiser: Some(Initialiser::List { items, .. }),
+                    ..
+                } if items.len() == 2
+            ));
+        }
+    }
+
+    /// Type of the single variable `source` declares
+    fn declared_type(source: &str) -> Result<Type, ParserError> {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        match Parser::new(tokens).parse()?.as_slice() {
+            [Statement::VarDecl { ty, .. }] => Ok(ty.clone()),
+            _ => panic!("Expected a single declaration"),
+        }
+    }
+
+    #[test]
+    fn integer_specifiers_combine() {
+        let cases = [
+            ("unsigned x;", Type::UnsignedInt),
+            ("signed char x;", Type::SignedChar),
+            ("char unsigned x;", Type::UnsignedChar),
+            ("short int x;", Type::Short),
+            ("unsigned short x;", Type::UnsignedShort),
+            ("long x;", Type::Long),
+            ("long unsigned int x;", Type::UnsignedLong),
+            ("long long x;", Type::LongLong),
+            ("unsigned long long int x;", Type::UnsignedLongLong),
+            ("signed x;", Type::Int),
+        ];
+        for (source, ty) in cases {
+            assert_eq!(declared_type(source).unwrap(), ty, "{}", source);
+        }
+        assert_eq!(
+            declared_type("const unsigned long* p;")
+                .unwrap()
+                .to_string(),
+            "const unsigned long*"
+        );
+
+        for source in [
+            "short long x;",
+            "long long long x;",
+            "signed unsigned x;",
+            "long char x;",
+            "int int x;",
+        ] {
+            assert!(
+                matches!(
+                    declared_type(source),
+                    Err(ParserError::InvalidTypeSpecifiers { line: 1 })
+                ),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn restrict_and_atomic_qualifiers() {
+        let restrict = Qualifiers {
+            is_restrict: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            declared_type("int * restrict p;").unwrap(),
+            Type::Pointer(Box::new(Type::Int)).qualified(restrict)
+        );
+
+        let ty = declared_type("_Atomic int counter;").unwrap();
+        assert!(ty.qualifiers().is_atomic);
+        assert_eq!(ty.unqualified(), &Type::Int);
+
+        let ty = declared_type("const volatile char * const restrict s;").unwrap();
+        assert_eq!(ty.to_string(), "const volatile char* const restrict");
+    }
+
+    #[test]
+    fn restrict_on_non_pointer_is_an_error() {
+        assert!(matches!(
+            declared_type("restrict int x;"),
+            Err(ParserError::InvalidRestrict { line: 1 })
+        ));
+    }
+
+    #[test]
+    fn errors_name_tokens_by_spelling() {
+        let mut lexer = Lexer::from_string("int main(void) { return 1 }");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let error = Parser::new(tokens).parse().expect_err("Should fail");
+        assert_eq!(error.to_string(), "Expected token ';', found '}'");
+    }
+
+    /// Fully parenthesised rendering of a binary expression tree
+    fn grouped(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Binary {
+                left,
+                operator,
+                right,
+            } => format!(
+                "({} {} {})",
+                grouped(left),
+                operator.token_type,
+                grouped(right)
+            ),
+            ExprKind::Literal(Literal::Int(value)) => value.to_string(),
+            _ => panic!("Expected only binary operators and integers"),
+        }
+    }
+
+    #[test]
+    fn mixed_binary_operators_keep_precedence() {
+        let mut lexer = Lexer::from_string("1 + 2 * 3 - 4 < 5 / 6 == 7 > 8 * 9");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+        assert_eq!(
+            grouped(&expr),
+            "((((1 + (2 * 3)) - 4) < (5 / 6)) == (7 > (8 * 9)))"
+        );
+    }
+
+    #[test]
+    fn generic_selection_records_associations() {
+        let mut lexer = Lexer::from_string("_Generic(x, int: 1, char*: 2, default: 3)");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+
+        let ExprKind::Generic {
+            controlling,
+            assocs,
+            ..
+        } = expr.kind
+        else {
+            panic!("Expected a generic selection");
+        };
+        assert!(matches!(controlling.kind, ExprKind::Identifier(_)));
+        let types: Vec<_> = assocs.iter().map(|(ty, _)| ty.clone()).collect();
+        assert_eq!(
+            types,
+            vec![
+                Some(Type::Int),
+                Some(Type::Pointer(Box::new(Type::Char))),
+                None
+            ]
+        );
+        assert!(matches!(
+            assocs[2].1.kind,
+            ExprKind::Literal(Literal::Int(3))
+        ));
+        assert_eq!(expr.span, Span { start: 0, end: 17 });
+    }
+
+    #[test]
+    fn declarators_nest_pointers_and_arrays() {
+        let source = "int *a[10];\nint (*p)[10];\nint m[2][3];\nchar *names[];\n\
+                      int main(int argc, char *argv[]) { return 0; }";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+
+        let types: Vec<_> = program[..4]
+            .iter()
+            .map(|stmt| match stmt {
+                Statement::VarDecl { ty, .. } => ty.clone(),
+                _ => panic!("Expected a declaration"),
+            })
+            .collect();
+        let int_pointer = Type::Pointer(Box::new(Type::Int));
+        let char_pointer = Type::Pointer(Box::new(Type::Char));
+        let ints = |length| Type::Array(Box::new(Type::Int), Some(length));
+        assert_eq!(
+            types,
+            [
+                Type::Array(Box::new(int_pointer), Some(10)),
+                Type::Pointer(Box::new(ints(10))),
+                Type::Array(Box::new(ints(3)), Some(2)),
+                Type::Array(Box::new(char_pointer.clone()), None),
+            ]
+        );
+        let names: Vec<_> = types.iter().map(Type::to_string).collect();
+        assert_eq!(names, ["int*[10]", "int (*)[10]", "int[2][3]", "char*[]"]);
+
+        let Statement::Function { params, .. } = &program[4] else {
+            panic!("Expected a function");
+        };
+        // an array parameter is adjusted to a pointer
+        assert_eq!(params[1].ty, Type::Pointer(Box::new(char_pointer)));
+    }
+
+    #[test]
+    fn array_length_is_constant_expression() {
+        let source = "enum { N = 3 };\nstruct s { char c; int i; };\n\
+                      int a[3 + 1];\nint b[N];\nlong c[sizeof(int)];\nchar d[sizeof(struct s)][N * 2];";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let lengths: Vec<_> = program
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Statement::VarDecl { ty, .. } => Some(ty.to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lengths, ["int[4]", "int[3]", "long[4]", "char[8][6]"]);
+
+        // the pointer width of the target
+        let mut lexer = Lexer::from_string("int a[sizeof(long)];");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let windows = TargetConfig {
+            abi: crate::target::TargetAbi::Windows,
+            ..TargetConfig::default()
+        };
+        let program = Parser::new(tokens)
+            .target(windows)
+            .parse()
+            .expect("Should parse");
+        assert!(matches!(
+            &program[..],
+            [Statement::VarDecl {
+                ty: Type::Array(_, Some(4)),
+                ..
+            }]
+        ));
+
+        let mut lexer = Lexer::from_string("int a[n = 3];");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let error = Parser::new(tokens).parse().expect_err("Should fail");
+        assert!(matches!(
+            error,
+            ParserError::AssignmentInConstant { line: 1 }
+        ));
+    }
+
+    #[test]
+    fn array_length_must_be_positive_constant() {
+        for (source, line) in [
+            ("int a[1.5];", 1),
+            ("int a[0];", 1),
+            ("void f(void) {\n  int a[-1];\n}", 2),
+            ("enum { N };\nint a[N];", 2),
+        ] {
+            let mut lexer = Lexer::from_string(source);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            let error = Parser::new(tokens).parse().expect_err("Should fail");
+            assert!(
+                matches!(error, ParserError::InvalidArrayLength { line: l } if l == line),
+                "{source}: {error:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn variable_length_array_is_named() {
+        for (source, line) in [
+            ("void f(int n) {\n  int a[n];\n}", 2),
+            ("int g(void);\nint a[2][g()];", 2),
+        ] {
+            let mut lexer = Lexer::from_string(source);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            let error = Parser::new(tokens).parse().expect_err("Should fail");
+            assert!(
+                matches!(error, ParserError::VariableLengthArray { line: l } if l == line),
+                "{source}: {error:?}"
+            );
+            assert_eq!(
+                error.to_string(),
+                format!("On line {line}, variable-length arrays aren't supported")
+            );
+        }
+    }
+
+    #[test]
+    fn operator_accessors() {
+        let cases = [
+            ("a < b", Some(TokenType::Less), true),
+            ("a != b", Some(TokenType::BangEqual), true),
+            ("a + b", Some(TokenType::Plus), false),
+            ("-a", Some(TokenType::Minus), false),
+            ("a += 1", Some(TokenType::PlusEqual), false),
+            // only the node itself, not what's inside it
+            ("(a == b)", None, false),
+            ("f(a)", None, false),
+        ];
+        for (source, operator, is_comparison) in cases {
+            let mut lexer = Lexer::from_string(source);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            let expr = Parser::new(tokens)
+                .parse_expression()
+                .expect("Should parse");
+            assert_eq!(expr.operator_type(), operator, "{source}");
+            assert_eq!(expr.is_comparison(), is_comparison, "{source}");
+        }
+    }
+
+    #[test]
+    fn cast_binds_tighter_than_binary_operators() {
+        let mut lexer = Lexer::from_string("(unsigned char)x + (y)");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+
+        let ExprKind::Binary { left, right, .. } = expr.kind else {
+            panic!("Expected a binary expression");
+        };
+        let ExprKind::Cast { ty, expr, .. } = left.kind else {
+            panic!("Expected a cast");
+        };
+        assert_eq!(ty, Type::UnsignedChar);
+        assert!(matches!(expr.kind, ExprKind::Identifier(_)));
+        assert_eq!(left.span, Span { start: 0, end: 5 });
+        // a parenthesised expression isn't a type name
+        assert!(matches!(right.kind, ExprKind::Grouping(_)));
+    }
+
+    /// The single `for` loop in the body of the function `source`
+    fn parse_for<T>(source: &str, check: impl FnOnce(&Statement) -> T) -> T {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let [Statement::Function { body, .. }] = &program[..] else {
+            panic!("Expected a function");
+        };
+        let Some(for_loop @ Statement::For { .. }) = body.last() else {
+            panic!("Expected the function to end in a for loop");
+        };
+        check(for_loop)
+    }
+
+    #[test]
+    fn comma_operator_in_for_clauses() {
+        let source = "void f(int i, int j, int n) { for (i = 0, j = n; i < j; i += 1, j -= 1) {} }";
+        parse_for(source, |for_loop| {
+            let Statement::For {
+                initialiser,
+                condition,
+                increment,
+                ..
+            } = for_loop
+            else {
+                unreachable!();
+            };
+            let [Statement::Expression(init)] = &initialiser[..] else {
+                panic!("Expected an expression initialiser");
+            };
+            for expr in [init, increment.as_ref().unwrap()] {
+                let ExprKind::Binary {
+                    left,
+                    operator,
+                    right,
+                } = &expr.kind
+                else {
+                    panic!("Expected a comma expression");
+                };
+                assert_eq!(operator.token_type, TokenType::Comma);
+                assert!(matches!(left.kind, ExprKind::Assign { .. }));
+                assert!(matches!(right.kind, ExprKind::Assign { .. }));
+            }
+            // the condition isn't a comma expression
+            let condition = condition.as_ref().unwrap();
+            assert!(
+                matches!(&condition.kind, ExprKind::Binary { operator, .. } if operator.token_type == TokenType::Less)
+            );
+        });
+    }
+
+    #[test]
+    fn several_declarators_in_for_initialiser() {
+        let source = "void f(int n) { for (int i = 0, *p, j = n; i < j; i += 1) {} }";
+        parse_for(source, |for_loop| {
+            let Statement::For { initialiser, .. } = for_loop else {
+                unreachable!();
+            };
+            let declarations: Vec<_> = initialiser
+                .iter()
+                .map(|statement| match statement {
+                    Statement::VarDecl {
+                        ty,
+                        name,
+                        initialiser,
+                        ..
+                    } => (ty.clone(), &*name.literal, initialiser.is_some()),
+                    _ => panic!("Expected only declarations"),
+                })
+                .collect();
+            assert_eq!(
+                declarations,
+                [
+                    (Type::Int, "i", true),
+                    (Type::Pointer(Box::new(Type::Int)), "p", false),
+                    (Type::Int, "j", true),
+                ]
+            );
+        });
+    }
+
+    fn string_value(source: &str) -> String {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+        let ExprKind::Literal(Literal::Str(value)) = expr.kind else {
+            panic!("Expected a string literal");
+        };
+        value.into_owned()
+    }
+
+    #[test]
+    fn func_is_name_of_enclosing_function() {
+        let source = "char* foo(void) { return __func__; }";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let [Statement::Function { body, .. }] = program.as_slice() else {
+            panic!("Expected a single function");
+        };
+        let [
+            Statement::Return {
+                value: Some(value), ..
+            },
+        ] = body.as_slice()
+        else {
+            panic!("Expected a return with a value");
+        };
+        assert!(matches!(&value.kind, ExprKind::Literal(Literal::Str(name)) if name == "foo"));
+        assert_eq!(value.string_bytes(tokens), Some(b"foo".to_vec()));
+
+        // the name isn't in scope once the function ends
+        let source = "int f(void) { return 0; }\nchar* s = __func__;";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let error = Parser::new(tokens).parse().expect_err("Should fail");
+        assert_eq!(
+            error.to_string(),
+            "On line 2, '__func__' is not defined outside of function scope"
+        );
+    }
+
+    #[test]
+    fn hex_constants_have_their_value() {
+        for (source, expected) in [
+            ("0x1.8p3", Literal::Float(12.0)),
+            ("0x1p-2", Literal::Float(0.25)),
+            ("0xff", Literal::Int(255)),
+        ] {
+            let mut lexer = Lexer::from_string(source);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            let expr = Parser::new(tokens)
+                .parse_expression()
+                .expect("Should parse");
+            assert!(
+                matches!(&expr.kind, ExprKind::Literal(value) if *value == expected),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn string_bytes_join_adjacent_literals() {
+        let mut lexer = Lexer::from_string(r#""a\0" "\x62" + 1"#);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+        let ExprKind::Binary { left, right, .. } = &expr.kind else {
+            panic!("Expected a binary expression");
+        };
+        assert_eq!(left.string_bytes(tokens), Some(b"a\0b".to_vec()));
+        assert_eq!(right.string_bytes(tokens), None);
+    }
+
+    #[test]
+    fn adjacent_string_literals_are_concatenated() {
+        assert_eq!(string_value(r#""foo" "bar""#), "foobar");
+        assert_eq!(string_value("\"a\"\n  \"b\\n\" \"c\""), "ab\nc");
+    }
+
+    #[test]
+    fn separated_string_literals_stay_apart() {
+        let mut lexer = Lexer::from_string(r#"f("a", x, "b")"#);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+        let ExprKind::Call { arguments, .. } = expr.kind else {
+            panic!("Expected a call");
+        };
+        let strings: Vec<_> = arguments
+            .iter()
+            .filter_map(|argument| match &argument.kind {
+                ExprKind::Literal(Literal::Str(value)) => Some(&**value),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(strings, ["a", "b"]);
+    }
+
+    #[test]
+    fn assignment_is_right_associative() {
+        let mut lexer = Lexer::from_string("a = b += 1");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+
+        let ExprKind::Assign {
+            operator, value, ..
+        } = expr.kind
+        else {
+            panic!("Expected an assignment");
+        };
+        assert_eq!(operator.token_type, TokenType::Equal);
+        assert!(matches!(
+            value.kind,
+            ExprKind::Assign { operator, .. } if operator.token_type == TokenType::PlusEqual
+        ));
+
+        let mut lexer = Lexer::from_string("a + 1 = 2");
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let result = Parser::new(tokens).parse_expression();
+        assert!(matches!(
+            result,
+            Err(ParserError::InvalidAssignmentTarget { line: 1 })
+        ));
+    }
+
+    /// Shape of an expression of identifiers, assignments and conditionals,
+    /// e.g. `(= a (? b c d))`
+    fn shape(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Identifier(name) => name.literal.to_string(),
+            ExprKind::Assign {
+                target,
+                operator,
+                value,
+            } => format!("({} {} {})", operator.lexeme(), shape(target), shape(value)),
+            ExprKind::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => format!(
+                "(? {} {} {})",
+                shape(condition),
+                shape(then_branch),
+                shape(else_branch)
+            ),
+            ExprKind::Grouping(inner) => shape(inner),
+            kind => panic!("Unexpected expression {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn constant_expressions_reject_assignment() {
+        let parse = |source: &str| {
+            let mut lexer = Lexer::from_string(source);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            Parser::new(tokens)
+                .parse()
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        };
+        let assignment = Err("On line 1, assignment in a constant expression".to_string());
+        assert_eq!(
+            parse("int f(int n) { switch (n) { case n = 3: return 1; } }"),
+            assignment
+        );
+        assert_eq!(parse("enum { A, B = A += 1 };"), assignment);
+        assert_eq!(parse("struct s { int x : n = 3; };"), assignment);
+        assert_eq!(parse("_Static_assert(n = 1, \"n\");"), assignment);
+
+        assert_eq!(
+            parse("int f(int n) { switch (n) { case 3 + 1: return 1; } }"),
+            Ok(())
+        );
+        assert_eq!(parse("enum { A, B = A ? 2 : 3 };"), Ok(()));
+        assert_eq!(parse("struct s { int x : 3 + 1; };"), Ok(()));
+    }
+
+    #[test]
+    fn conditional_binds_tighter_than_assignment() {
+        let parse = |source| {
+            let mut lexer = Lexer::from_string(source);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            Parser::new(tokens)
+                .parse_expression()
+                .map(|expr| shape(&expr))
+                .map_err(|e| e.to_string())
+        };
+
+        assert_eq!(parse("a = b ? c : d"), Ok("(= a (? b c d))".to_string()));
+        // the middle operand is a full expression, so may assign
+        assert_eq!(parse("a ? b = c : d"), Ok("(? a (= b c) d)".to_string()));
+        assert_eq!(
+            parse("a = b ? c : d ? e : f"),
+            Ok("(= a (? b c (? d e f)))".to_string())
+        );
+        // the last can't, so this is `(a ? b : c) = d`
+        assert_eq!(
+            parse("a ? b : c = d"),
+            Err("On line 1, invalid assignment target".to_string())
+        );
+        assert_eq!(parse("a ? b : (c = d)"), Ok("(? a b (= c d))".to_string()));
+    }
+
+    #[test]
+    fn nesting_past_limit_is_an_error() {
+        let parse = |source: &str, depth: usize| {
+            let source = source.to_string();
+            // test threads get 2MiB, give the parser the main thread's 8MiB
+            std::thread::Builder::new()
+                .stack_size(8 << 20)
+                .spawn(move || {
+                    let mut lexer = Lexer::from_string(&source);
+                    let tokens = lexer.tokenise().expect("Should tokenise without errors");
+                    Parser::new(tokens)
+                        .max_depth(depth)
+                        .parse_expression()
+                        .map(|_| ())
+                })
+                .unwrap()
+                .join()
+                .unwrap()
+        };
+
+        let parens = format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000));
+        assert!(matches!(
+            parse(&parens, MAX_DEPTH),
+            Err(ParserError::NestingTooDeep { line: 1 })
+        ));
+        let negations = format!("{}x", "- ".repeat(10_000));
+        assert!(matches!(
+            parse(&negations, MAX_DEPTH),
+            Err(ParserError::NestingTooDeep { .. })
+        ));
+
+        // the whole expression counts as one level
+        assert!(parse("((1))", 3).is_ok());
+        assert!(parse("(((1)))", 3).is_err());
+        assert!(parse("- -x", 3).is_ok());
+        assert!(parse("- - -x", 3).is_err());
+        let deepest = MAX_DEPTH - 1;
+        let parens = format!("{}1{}", "(".repeat(deepest), ")".repeat(deepest));
+        assert!(parse(&parens, MAX_DEPTH).is_ok());
+    }
+
+    /// Random expression of at most `depth` levels, from the linear
+    /// congruential generator `state`
+    fn random_expression(state: &mut u64, depth: u32) -> String {
+        let mut next = |n: u64| {
+            *state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (*state >> 33) % n
+        };
+        if depth == 0 {
+            return ["a", "b", "c", "1", "2", "30"][next(6) as usize].to_string();
+        }
+        const OPERATORS: [&str; 10] = ["*", "/", "+", "-", "<", "<=", ">", ">=", "==", "!="];
+        let (choice, unary) = (next(8), ["-", "!", "(int)"][next(3) as usize]);
+        let operator = OPERATORS[next(10) as usize];
+        let mut operand = || random_expression(state, depth - 1);
+        match choice {
+            0 => format!("({})", operand()),
+            1 => format!("{unary} {}", operand()),
+            2 => format!("{} ? {} : {}", operand(), operand(), operand()),
+            3 => format!("f({}, {})", operand(), operand()),
+            4 => format!("(a = {})", operand()),
+            5 => format!("s.m {operator} {}", operand()),
+            _ => format!("{} {operator} {}", operand(), operand()),
+        }
+    }
+
+    /// Tree and spans of each node of `expr`, which is all the parsers can
+    /// differ in
+    fn tree_and_spans(expr: &Expr) -> (String, Vec<Span>) {
+        fn spans(expr: &Expr, output: &mut Vec<Span>) {
+            output.push(expr.span);
+            for child in ast::expr_children(expr) {
+                spans(child, output);
+            }
+        }
+        let mut output = vec![];
+        spans(expr, &mut output);
+        (expr.print_tree(), output)
+    }
+
+    #[test]
+    fn pratt_parser_matches_recursive_descent() {
+        let mut state = 0x5eed;
+        for _ in 0..500 {
+            let source = random_expression(&mut state, 5);
+            let mut lexer = Lexer::from_string(&source);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            let descent = Parser::new(tokens)
+                .parse_expression()
+                .expect("Should parse");
+            let pratt = Parser::new(tokens)
+                .pratt(true)
+                .parse_expression()
+                .expect("Should parse");
+            assert_eq!(tree_and_spans(&pratt), tree_and_spans(&descent), "{source}");
+        }
+    }
 }
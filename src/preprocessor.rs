@@ -0,0 +1,187 @@
+//! Minimal preprocessor handling `#include "file"` and `#pragma` directives.
+//! Any other directive is passed through untouched.
+
+use crate::diagnostic::Diagnostic;
+use core::fmt;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum PreprocessorError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    MalformedInclude {
+        path: PathBuf,
+        line: usize,
+    },
+    /// A file includes itself, directly or through other headers, without a
+    /// `#pragma once` to stop it
+    RecursiveInclude {
+        path: PathBuf,
+    },
+}
+
+impl fmt::Display for PreprocessorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreprocessorError::Io { path, error } => {
+                write!(f, "Couldn't read '{}': {}", path.display(), error)
+            }
+            PreprocessorError::MalformedInclude { path, line } => write!(
+                f,
+                "In '{}' on line {}, expected \"FILENAME\" after #include",
+                path.display(),
+                line
+            ),
+            PreprocessorError::RecursiveInclude { path } => {
+                write!(f, "'{}' includes itself recursively", path.display())
+            }
+        }
+    }
+}
+
+impl Error for PreprocessorError {}
+
+#[derive(Debug, Default)]
+pub struct Preprocessor {
+    /// Files which declared `#pragma once` and so are never included again
+    once: HashSet<PathBuf>,
+    /// Files currently being expanded, innermost last
+    include_stack: Vec<PathBuf>,
+    warnings: Vec<Diagnostic>,
+}
+
+impl Preprocessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Warnings collected so far, e.g. for ignored pragmas
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    /// Reads `path` and returns its contents with every include expanded
+    pub fn process_file(&mut self, path: &Path) -> Result<String, PreprocessorError> {
+        let io_error = |error| PreprocessorError::Io {
+            path: path.to_path_buf(),
+            error,
+        };
+        let path = fs::canonicalize(path).map_err(io_error)?;
+        if self.once.contains(&path) {
+            return Ok(String::new());
+        }
+        if self.include_stack.contains(&path) {
+            return Err(PreprocessorError::RecursiveInclude { path });
+        }
+        let source = fs::read_to_string(&path).map_err(io_error)?;
+
+        self.include_stack.push(path.clone());
+        let result = self.process_source(&path, &source);
+        self.include_stack.pop();
+        result
+    }
+
+    fn process_source(&mut self, path: &Path, source: &str) -> Result<String, PreprocessorError> {
+        let mut output = String::with_capacity(source.len());
+
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            };
+            let directive = directive.trim();
+
+            if let Some(rest) = directive.strip_prefix("include") {
+                let name = rest
+                    .trim()
+                    .strip_prefix('"')
+                    .and_then(|rest| rest.strip_suffix('"'))
+                    .ok_or_else(|| PreprocessorError::MalformedInclude {
+                        path: path.to_path_buf(),
+                        line: line_number,
+                    })?;
+                // quoted includes are relative to the including file
+                let header = path.parent().unwrap_or(Path::new(".")).join(name);
+                output.push_str(&self.process_file(&header)?);
+            } else if let Some(rest) = directive.strip_prefix("pragma") {
+                self.pragma(path, line_number, rest.trim());
+            } else {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+
+        Ok(output)
+    }
+
+    fn pragma(&mut self, path: &Path, line: usize, pragma: &str) {
+        if pragma == "once" {
+            self.once.insert(path.to_path_buf());
+            return;
+        }
+        self.warnings.push(Diagnostic::warning(
+            line,
+            format!(
+                "ignoring unknown pragma '{}' in '{}'",
+                pragma,
+                path.display()
+            ),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod preprocessor_tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn pragma_once_header_is_included_once() {
+        let dir = TempDir::with_files(
+            "once",
+            &[
+                ("header.h", "#pragma once\nint shared;\n"),
+                (
+                    "main.c",
+                    "#include \"header.h\"\n#include \"header.h\"\nint main(void) { return 0; }\n",
+                ),
+            ],
+        );
+
+        let mut preprocessor = Preprocessor::new();
+        let output = preprocessor.process_file(&dir.join("main.c")).unwrap();
+        assert_eq!(output.matches("int shared;").count(), 1);
+        assert!(output.contains("int main(void)"));
+        assert!(preprocessor.warnings().is_empty());
+    }
+
+    #[test]
+    fn unknown_pragma_is_dropped_with_warning() {
+        let dir = TempDir::with_files("unknown", &[("main.c", "#pragma pack(1)\nint x;\n")]);
+
+        let mut preprocessor = Preprocessor::new();
+        let output = preprocessor.process_file(&dir.join("main.c")).unwrap();
+        assert_eq!(output, "int x;\n");
+        assert_eq!(preprocessor.warnings().len(), 1);
+        assert_eq!(preprocessor.warnings()[0].line, 1);
+    }
+
+    #[test]
+    fn recursive_include_without_pragma_once_is_an_error() {
+        let dir = TempDir::with_files("recursive", &[("loop.h", "#include \"loop.h\"\n")]);
+
+        let result = Preprocessor::new().process_file(&dir.join("loop.h"));
+        assert!(matches!(
+            result,
+            Err(PreprocessorError::RecursiveInclude { .. })
+        ));
+    }
+}
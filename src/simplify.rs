@@ -0,0 +1,253 @@
+//! Simplification of expressions by algebraic identities that hold for
+//! integers, such as `x + 0` to `x`, beyond what folding constants can do
+
+use crate::ast;
+use crate::fold::{self, Fold};
+use crate::lexer::TokenType;
+use crate::parser::{Expr, ExprKind, Literal, Statement};
+use crate::types::Type;
+use std::collections::HashSet;
+
+/// Simplifies `expr`, and every expression inside it, with
+/// * `x + 0`, `0 + x`, `x - 0`, `x * 1` and `1 * x` to `x`
+/// * `x * 0` and `0 * x` to `0`
+/// * `x - x` to `0`
+///
+/// Only operands built from identifiers, integer literals and operators are
+/// simplified, so a call, assignment or statement expression, which could
+/// have side effects, is always kept. Without declarations to go on, every
+/// identifier is taken to be a plain integer variable, see
+/// [`simplify_program`].
+pub fn simplify(expr: Expr) -> Expr {
+    Simplify::default().fold_expr(expr)
+}
+
+/// Simplifies every expression in `program` as [`simplify`] does, except that
+/// identifiers not declared with an integer type are left alone. That keeps
+/// each read of a `volatile` or `_Atomic` variable, and `x + 0` as it is for a
+/// `float`, where it turns `-0.0` into `0.0`.
+pub fn simplify_program<'a>(program: Vec<Statement<'a>>) -> Vec<Statement<'a>> {
+    let mut simplify = Simplify::default();
+    for stmt in &program {
+        simplify.declare(stmt);
+    }
+    fold::fold_program(&mut simplify, program)
+}
+
+#[derive(Default)]
+struct Simplify<'a> {
+    /// Names declared, in any scope, other than as a plain integer. A name is
+    /// opaque if any of its declarations is, which is cautious but doesn't
+    /// need the scopes of each.
+    opaque: HashSet<&'a str>,
+}
+
+impl<'a> Simplify<'a> {
+    /// Records the names of file scope variables and of the parameters and
+    /// local variables of a function
+    fn declare(&mut self, stmt: &Statement<'a>) {
+        match stmt {
+            Statement::VarDecl { ty, name, .. } => self.declare_name(ty, &name.literal),
+            Statement::Function { params, body, .. } => {
+                for param in params {
+                    self.declare_name(&param.ty, &param.name.literal);
+                }
+                for stmt in body {
+                    self.declare_locals(stmt);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn declare_locals(&mut self, stmt: &Statement<'a>) {
+        if let Statement::VarDecl { ty, name, .. } = stmt {
+            self.declare_name(ty, &name.literal);
+        }
+        let (statements, exprs) = ast::statement_children(stmt);
+        for child in statements
+            .into_iter()
+            .chain(exprs.into_iter().flat_map(ast::expr_statements))
+        {
+            self.declare_locals(child);
+        }
+    }
+
+    fn declare_name(&mut self, ty: &Type, name: &'a str) {
+        if !ty.is_integer() || ty.is_volatile() || ty.qualifiers().is_atomic {
+            self.opaque.insert(name);
+        }
+    }
+
+    /// Whether `expr` is an integer with no side effects, which can be dropped
+    /// or evaluated once instead of twice
+    fn is_simple(&self, expr: &Expr) -> bool {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Int(_)) => true,
+            ExprKind::Identifier(name) => !self.opaque.contains(&*name.literal),
+            ExprKind::Unary { right, .. } | ExprKind::Grouping(right) => self.is_simple(right),
+            ExprKind::Binary { left, right, .. } => self.is_simple(left) && self.is_simple(right),
+            _ => false,
+        }
+    }
+}
+
+impl<'a> Fold<'a> for Simplify<'a> {
+    fn fold_expr(&mut self, expr: Expr<'a>) -> Expr<'a> {
+        let expr = fold::fold_expr(self, expr);
+        let ExprKind::Binary {
+            left,
+            operator,
+            right,
+        } = expr.kind
+        else {
+            return expr;
+        };
+        let zero = Expr {
+            kind: ExprKind::Literal(Literal::Int(0)),
+            span: expr.span,
+        };
+        let (left_value, right_value) = (int_value(&left), int_value(&right));
+        if self.is_simple(&left) && self.is_simple(&right) {
+            match (operator.token_type, left_value, right_value) {
+                (TokenType::Plus, _, Some(0))
+                | (TokenType::Minus, _, Some(0))
+                | (TokenType::Star, _, Some(1)) => return *left,
+                (TokenType::Plus, Some(0), _) | (TokenType::Star, Some(1), _) => return *right,
+                (TokenType::Star, Some(0), _) | (TokenType::Star, _, Some(0)) => return zero,
+                (TokenType::Minus, _, _) if same(&left, &right) => return zero,
+                _ => (),
+            }
+        }
+        Expr {
+            kind: ExprKind::Binary {
+                left,
+                operator,
+                right,
+            },
+            span: expr.span,
+        }
+    }
+}
+
+/// Value of an integer literal, looking through parentheses
+fn int_value(expr: &Expr) -> Option<i64> {
+    match &expr.kind {
+        ExprKind::Literal(Literal::Int(value)) => Some(*value),
+        ExprKind::Grouping(inner) => int_value(inner),
+        _ => None,
+    }
+}
+
+/// Whether two simple expressions are written the same, ignoring parentheses,
+/// so always have the same value
+fn same(a: &Expr, b: &Expr) -> bool {
+    match (&a.kind, &b.kind) {
+        (ExprKind::Grouping(a), _) => same(a, b),
+        (_, ExprKind::Grouping(b)) => same(a, b),
+        (ExprKind::Literal(Literal::Int(a)), ExprKind::Literal(Literal::Int(b))) => a == b,
+        (ExprKind::Identifier(a), ExprKind::Identifier(b)) => a.literal == b.literal,
+        (ExprKind::Unary { right: a_right, .. }, ExprKind::Unary { right: b_right, .. }) => {
+            a.operator_type() == b.operator_type() && same(a_right, b_right)
+        }
+        (
+            ExprKind::Binary {
+                left: a_left,
+                right: a_right,
+                ..
+            },
+            ExprKind::Binary {
+                left: b_left,
+                right: b_right,
+                ..
+            },
+        ) => {
+            a.operator_type() == b.operator_type() && same(a_left, b_left) && same(a_right, b_right)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod simplify_tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn simplified_hash(source: &str) -> u64 {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+        simplify(expr).structural_hash()
+    }
+
+    fn parsed_hash(source: &str) -> u64 {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let expr = Parser::new(tokens)
+            .parse_expression()
+            .expect("Should parse");
+        expr.structural_hash()
+    }
+
+    #[test]
+    fn identities_simplify() {
+        let cases = [
+            ("x + 0", "x"),
+            ("0 + x", "x"),
+            ("x - 0", "x"),
+            ("x * 1", "x"),
+            ("1 * x", "x"),
+            ("x * 0", "0"),
+            ("0 * (x + y)", "0"),
+            ("x - x", "0"),
+            ("(a + -b) - (a + -(b))", "0"),
+            // inner expressions first, so the outer one sees their result
+            ("(x * 1 + 0) * y", "(x) * y"),
+            ("f(x - x, y + 0)", "f(0, y)"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(
+                simplified_hash(source),
+                parsed_hash(expected),
+                "{source} should be {expected}"
+            );
+        }
+        // different operands aren't the same
+        assert_eq!(simplified_hash("x - y"), parsed_hash("x - y"));
+        assert_eq!(simplified_hash("x - (y + 0)"), parsed_hash("x - (y)"));
+    }
+
+    #[test]
+    fn side_effects_block_simplification() {
+        for source in [
+            "f() * 0",
+            "f() - f()",
+            "(x = 1) * 0",
+            "f() + 0",
+            "0 * (y = 2)",
+        ] {
+            assert_eq!(simplified_hash(source), parsed_hash(source), "{source}");
+        }
+    }
+
+    #[test]
+    fn only_plain_integer_variables_simplify_in_program() {
+        let source = "int g(float f, volatile int v, int i) {\n  \
+                      return (f + 0) + (v * 0) + (v - v) + (i * 1);\n}";
+        let expected = "int g(float f, volatile int v, int i) {\n  \
+                        return (f + 0) + (v * 0) + (v - v) + (i);\n}";
+        let hash = |source: &str, simplify: bool| {
+            let mut lexer = Lexer::from_string(source);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            let mut program = Parser::new(tokens).parse().expect("Should parse");
+            if simplify {
+                program = simplify_program(program);
+            }
+            program[0].structural_hash()
+        };
+        assert_eq!(hash(source, true), hash(expected, false));
+    }
+}
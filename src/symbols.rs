@@ -0,0 +1,360 @@
+//! Table of the names declared in each scope of a program, for seeing how
+//! declarations nest and shadow each other
+
+use crate::ast;
+use crate::parser::{Expr, ExprKind, Statement, StorageDuration};
+use crate::types::{Qualifiers, Type};
+use std::fmt;
+
+/// What a name was declared as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Parameter,
+    Function,
+    Enumerator,
+    /// Tag of a structure or union
+    Tag,
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            SymbolKind::Variable => "variable",
+            SymbolKind::Parameter => "parameter",
+            SymbolKind::Function => "function",
+            SymbolKind::Enumerator => "enumerator",
+            SymbolKind::Tag => "tag",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol<'a> {
+    pub name: &'a str,
+    /// Return type of a function, or the type the tag names
+    pub ty: Type,
+    pub kind: SymbolKind,
+    /// How long a variable or parameter lasts
+    pub duration: Option<StorageDuration>,
+}
+
+/// What opened a scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind<'a> {
+    File,
+    /// Parameters and body of the named function
+    Function(&'a str),
+    Block,
+    /// Declarations in the initialiser of a `for`, around its body
+    For,
+    StmtExpr,
+}
+
+/// A symbol or a nested scope, in the order they appear in the source
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry<'a> {
+    Symbol(Symbol<'a>),
+    Scope(Scope<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scope<'a> {
+    pub kind: ScopeKind<'a>,
+    pub entries: Vec<Entry<'a>>,
+}
+
+impl<'a> Scope<'a> {
+    fn new(kind: ScopeKind<'a>) -> Self {
+        Self {
+            kind,
+            entries: vec![],
+        }
+    }
+
+    /// Symbols declared directly in this scope, not those of nested scopes
+    pub fn symbols(&self) -> impl Iterator<Item = &Symbol<'a>> {
+        self.entries.iter().filter_map(|entry| match entry {
+            Entry::Symbol(symbol) => Some(symbol),
+            Entry::Scope(_) => None,
+        })
+    }
+
+    /// Scopes nested directly in this one
+    pub fn scopes(&self) -> impl Iterator<Item = &Scope<'a>> {
+        self.entries.iter().filter_map(|entry| match entry {
+            Entry::Scope(scope) => Some(scope),
+            Entry::Symbol(_) => None,
+        })
+    }
+
+    fn write_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+        match self.kind {
+            ScopeKind::File => writeln!(f, "{}file scope", indent)?,
+            ScopeKind::Function(name) => writeln!(f, "{}function '{}'", indent, name)?,
+            ScopeKind::Block => writeln!(f, "{}block", indent)?,
+            ScopeKind::For => writeln!(f, "{}for loop", indent)?,
+            ScopeKind::StmtExpr => writeln!(f, "{}statement expression", indent)?,
+        }
+        for entry in &self.entries {
+            match entry {
+                // every variable at file scope is static, so only those in a
+                // function say so
+                Entry::Symbol(symbol)
+                    if self.kind != ScopeKind::File
+                        && symbol.duration == Some(StorageDuration::Static) =>
+                {
+                    writeln!(
+                        f,
+                        "{}  {}: {} (static {})",
+                        indent, symbol.name, symbol.ty, symbol.kind
+                    )?
+                }
+                Entry::Symbol(symbol) => writeln!(
+                    f,
+                    "{}  {}: {} ({})",
+                    indent, symbol.name, symbol.ty, symbol.kind
+                )?,
+                Entry::Scope(scope) => scope.write_indented(f, depth + 1)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One line per scope and per symbol, each nested scope indented under the
+/// one it's in
+impl fmt::Display for Scope<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+/// Collects the symbols of `program`, scoped as the type checker scopes them
+pub fn collect<'a>(program: &[Statement<'a>]) -> Scope<'a> {
+    let mut table = Table {
+        scopes: vec![Scope::new(ScopeKind::File)],
+    };
+    for stmt in program {
+        table.statement(stmt);
+    }
+    table.scopes.pop().expect("file scope is never closed")
+}
+
+/// Scopes open at the point reached in the walk, innermost last
+struct Table<'a> {
+    scopes: Vec<Scope<'a>>,
+}
+
+impl<'a> Table<'a> {
+    fn declare(&mut self, name: &'a str, ty: Type, kind: SymbolKind) {
+        let duration = (kind == SymbolKind::Parameter).then_some(StorageDuration::Automatic);
+        self.declare_with(Symbol {
+            name,
+            ty,
+            kind,
+            duration,
+        });
+    }
+
+    fn declare_with(&mut self, symbol: Symbol<'a>) {
+        let scope = self.scopes.last_mut().expect("file scope is always open");
+        scope.entries.push(Entry::Symbol(symbol));
+    }
+
+    fn open(&mut self, kind: ScopeKind<'a>) {
+        self.scopes.push(Scope::new(kind));
+    }
+
+    fn close(&mut self) {
+        let scope = self.scopes.pop().expect("Closed more scopes than opened");
+        self.scopes
+            .last_mut()
+            .expect("file scope is never closed")
+            .entries
+            .push(Entry::Scope(scope));
+    }
+
+    fn statement(&mut self, stmt: &Statement<'a>) {
+        match stmt {
+            Statement::Block(body) => {
+                self.open(ScopeKind::Block);
+                self.body(body);
+                self.close();
+            }
+            Statement::Function {
+                return_type,
+                name,
+                params,
+                body,
+                ..
+            } => {
+                self.declare(&name.literal, return_type.clone(), SymbolKind::Function);
+                self.open(ScopeKind::Function(&name.literal));
+                for param in params {
+                    self.declare(&param.name.literal, param.ty.clone(), SymbolKind::Parameter);
+                }
+                self.body(body);
+                self.close();
+            }
+            Statement::Prototype {
+                return_type, name, ..
+            } => self.declare(&name.literal, return_type.clone(), SymbolKind::Function),
+            Statement::For {
+                initialiser,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                self.open(ScopeKind::For);
+                self.body(initialiser);
+                for expr in condition.iter().chain(increment) {
+                    self.expr(expr);
+                }
+                self.statement(body);
+                self.close();
+            }
+            Statement::Struct { name, is_union, .. } => {
+                let tag = name.literal.to_string();
+                let ty = if *is_union {
+                    Type::Union(tag)
+                } else {
+                    Type::Struct(tag)
+                };
+                self.declare(&name.literal, ty, SymbolKind::Tag);
+            }
+            _ => {
+                // an initialiser is in scope before the name it initialises
+                let (statements, exprs) = ast::statement_children(stmt);
+                for expr in exprs {
+                    self.expr(expr);
+                }
+                self.body(statements);
+                match stmt {
+                    Statement::VarDecl {
+                        ty, name, storage, ..
+                    } => {
+                        let file_scope = self.scopes.len() == 1;
+                        self.declare_with(Symbol {
+                            name: &name.literal,
+                            ty: ty.clone(),
+                            kind: SymbolKind::Variable,
+                            duration: Some(storage.duration(file_scope)),
+                        })
+                    }
+                    Statement::Enum { enumerators, .. } => {
+                        for enumerator in enumerators {
+                            let ty = Type::Int.qualified(Qualifiers::CONST);
+                            self.declare(&enumerator.name.literal, ty, SymbolKind::Enumerator);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    fn body<'s>(&mut self, body: impl IntoIterator<Item = &'s Statement<'a>>)
+    where
+        'a: 's,
+    {
+        for stmt in body {
+            self.statement(stmt);
+        }
+    }
+
+    /// Finds the scopes of any statement expressions inside `expr`
+    fn expr(&mut self, expr: &Expr<'a>) {
+        if let ExprKind::StmtExpr(body) = &expr.kind {
+            self.open(ScopeKind::StmtExpr);
+            self.body(body);
+            self.close();
+            return;
+        }
+        for child in ast::expr_children(expr) {
+            self.expr(child);
+        }
+    }
+}
+
+#[cfg(test)]
+mod symbols_tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn inner_declaration_shadows_in_nested_scope() {
+        let source = "enum { A };\nint x;\nint f(int x) {\n  for (int i = 0; i < x; i) {\n    \
+                      char x = 1;\n  }\n  return x;\n}";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let file = collect(&program);
+
+        let names = |scope: &Scope| -> Vec<(String, SymbolKind)> {
+            scope
+                .symbols()
+                .map(|s| (s.name.to_string(), s.kind))
+                .collect()
+        };
+        assert_eq!(
+            names(&file),
+            [
+                ("A".to_string(), SymbolKind::Enumerator),
+                ("x".to_string(), SymbolKind::Variable),
+                ("f".to_string(), SymbolKind::Function),
+            ]
+        );
+        let [function] = &file.scopes().collect::<Vec<_>>()[..] else {
+            panic!("Expected a function scope");
+        };
+        assert_eq!(function.kind, ScopeKind::Function("f"));
+        assert_eq!(names(function), [("x".to_string(), SymbolKind::Parameter)]);
+        let for_loop = function.scopes().next().expect("Expected a for loop");
+        assert_eq!(names(for_loop), [("i".to_string(), SymbolKind::Variable)]);
+        let block = for_loop.scopes().next().expect("Expected its body");
+        assert_eq!(block.kind, ScopeKind::Block);
+        assert_eq!(block.symbols().next().map(|s| &s.ty), Some(&Type::Char));
+    }
+
+    #[test]
+    fn local_static_has_static_duration() {
+        let source = "int total;\nint f(int n) {\n  static int counter;\n  int x;\n  return x;\n}";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        let file = collect(&program);
+
+        let durations = |scope: &Scope| -> Vec<(String, Option<StorageDuration>)> {
+            scope
+                .symbols()
+                .map(|s| (s.name.to_string(), s.duration))
+                .collect()
+        };
+        assert_eq!(
+            durations(&file),
+            [
+                ("total".to_string(), Some(StorageDuration::Static)),
+                ("f".to_string(), None),
+            ]
+        );
+        let function = file.scopes().next().expect("Expected a function scope");
+        assert_eq!(
+            durations(function),
+            [
+                ("n".to_string(), Some(StorageDuration::Automatic)),
+                ("counter".to_string(), Some(StorageDuration::Static)),
+                ("x".to_string(), Some(StorageDuration::Automatic)),
+            ]
+        );
+        assert!(file.to_string().contains("  total: int (variable)\n"));
+        assert!(
+            file.to_string()
+                .contains("    counter: int (static variable)\n")
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use crate::types::Type;
+
+/// Properties of the machine being compiled for that change the meaning of a
+/// program, such as how wide an `int` is. More may be added, so set the ones
+/// that differ and take the rest with `..TargetConfig::default()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetConfig {
+    /// Width of `int` in bits
+    pub int_bits: u32,
+    /// Whether plain `char` is signed
+    pub char_signed: bool,
+    /// Calling convention of generated code
+    pub abi: TargetAbi,
+}
+
+/// Calling convention for x86-64, deciding where a function finds its
+/// arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TargetAbi {
+    /// Used by Linux, macOS and the BSDs
+    #[default]
+    SystemV,
+    /// Microsoft x64, used by Windows
+    Windows,
+}
+
+impl TargetAbi {
+    /// Registers the leading integer and pointer arguments are passed in, in
+    /// order. Any further arguments are passed on the stack.
+    pub fn argument_registers(self) -> &'static [&'static str] {
+        match self {
+            TargetAbi::SystemV => &["rdi", "rsi", "rdx", "rcx", "r8", "r9"],
+            TargetAbi::Windows => &["rcx", "rdx", "r8", "r9"],
+        }
+    }
+
+    /// Register the argument at `index` is passed in, or `None` if it's
+    /// passed on the stack
+    pub fn argument_register(self, index: usize) -> Option<&'static str> {
+        self.argument_registers().get(index).copied()
+    }
+
+    /// Alignment of the stack pointer at a call instruction, in bytes
+    pub fn stack_alignment(self) -> u32 {
+        16
+    }
+
+    /// Bytes the caller reserves above the return address for the callee to
+    /// spill its register arguments into
+    pub fn shadow_space(self) -> u32 {
+        match self {
+            TargetAbi::SystemV => 0,
+            TargetAbi::Windows => 32,
+        }
+    }
+}
+
+impl Default for TargetConfig {
+    /// x86-64 System V
+    fn default() -> Self {
+        Self {
+            int_bits: 32,
+            char_signed: true,
+            abi: TargetAbi::SystemV,
+        }
+    }
+}
+
+impl TargetConfig {
+    /// Size of a pointer in bytes, the same under either ABI
+    pub fn pointer_size(&self) -> u64 {
+        8
+    }
+
+    /// Size of a `long` in bytes. Windows keeps it at 4 so only pointers grow
+    /// on 64-bit.
+    pub fn long_size(&self) -> u64 {
+        match self.abi {
+            TargetAbi::SystemV => 8,
+            TargetAbi::Windows => 4,
+        }
+    }
+
+    /// The unsigned integer type `size_t` is, as wide as a pointer
+    pub fn size_type(&self) -> Type {
+        match self.abi {
+            TargetAbi::SystemV => Type::UnsignedLong,
+            TargetAbi::Windows => Type::UnsignedLongLong,
+        }
+    }
+
+    /// The signed integer type `ptrdiff_t` is, the difference of two pointers
+    pub fn ptrdiff_type(&self) -> Type {
+        match self.abi {
+            TargetAbi::SystemV => Type::Long,
+            TargetAbi::Windows => Type::LongLong,
+        }
+    }
+
+    /// Wraps `value` to a signed `int` of the configured width
+    pub fn wrap_int(&self, value: i64) -> i64 {
+        if self.int_bits >= i64::BITS {
+            return value;
+        }
+        let shift = i64::BITS - self.int_bits;
+        (value << shift) >> shift
+    }
+}
+
+#[cfg(test)]
+mod target_tests {
+    use super::*;
+
+    #[test]
+    fn abi_argument_registers() {
+        assert_eq!(TargetConfig::default().abi, TargetAbi::SystemV);
+        let first_two = |abi: TargetAbi| [abi.argument_register(0), abi.argument_register(1)];
+        assert_eq!(first_two(TargetAbi::SystemV), [Some("rdi"), Some("rsi")]);
+        assert_eq!(first_two(TargetAbi::Windows), [Some("rcx"), Some("rdx")]);
+        // Windows passes its fifth argument on the stack, System V its seventh
+        assert_eq!(TargetAbi::Windows.argument_register(4), None);
+        assert_eq!(TargetAbi::SystemV.argument_register(6), None);
+        assert_eq!(TargetAbi::Windows.shadow_space(), 32);
+    }
+}
@@ -0,0 +1,32 @@
+//! Directories of source files for the tests that read them from disk
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Fresh directory under the system temp dir, removed with everything in it
+/// when dropped
+pub(crate) struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Directory for the test `test`, holding each of `files` by name
+    pub(crate) fn with_files(test: &str, files: &[(&str, &str)]) -> Self {
+        let path = std::env::temp_dir().join(format!("c-comp-rs-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+        for (name, contents) in files {
+            fs::write(path.join(name), contents).unwrap();
+        }
+        TempDir { path }
+    }
+
+    pub(crate) fn join(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,1564 @@
+use crate::diagnostic::Diagnostic;
+use crate::eval::{self, Enumerators, Value};
+use crate::format;
+use crate::layout::Layouts;
+use crate::lexer::{LanguageStandard, Token, TokenType};
+use crate::parser::{Designator, Expr, ExprKind, Initialiser, Literal, Statement};
+use crate::target::TargetConfig;
+use crate::types::{Qualifiers, Type};
+use core::fmt;
+use std::collections::HashMap;
+use std::error::Error;
+
+#[derive(Debug)]
+pub enum TypeError {
+    UndeclaredIdentifier {
+        line: usize,
+        name: String,
+    },
+    InvalidOperand {
+        line: usize,
+        operator: TokenType,
+        found: Type,
+    },
+    NonScalarCondition {
+        line: usize,
+        found: Type,
+    },
+    IncompatibleBranches {
+        line: usize,
+        then_type: Type,
+        else_type: Type,
+    },
+    IncompatibleAssignment {
+        line: usize,
+        target: Type,
+        value: Type,
+    },
+    /// No association of a `_Generic` matches its controlling expression and
+    /// there's no `default`
+    NoGenericAssociation {
+        line: usize,
+        found: Type,
+    },
+    /// Assignment to a `const` object, directly or through a pointer
+    ConstAssignment {
+        line: usize,
+        found: Type,
+    },
+    /// `->` applied to something other than a pointer
+    ArrowOnNonPointer {
+        line: usize,
+        found: Type,
+    },
+    /// `.` applied to a pointer, where `->` was probably meant
+    DotOnPointer {
+        line: usize,
+        found: Type,
+    },
+    NotAStruct {
+        line: usize,
+        found: Type,
+    },
+    NoSuchMember {
+        line: usize,
+        member: String,
+        found: Type,
+    },
+    /// A second declaration of a name within the same block scope
+    Redeclaration {
+        line: usize,
+        name: String,
+    },
+    NotAFunction {
+        line: usize,
+        found: Type,
+    },
+    /// Call with the wrong number of arguments. A variadic function only
+    /// needs at least `expected`.
+    ArgumentCount {
+        line: usize,
+        expected: usize,
+        found: usize,
+        variadic: bool,
+    },
+    /// A function declared again with a different number of parameters, or
+    /// variadic in one declaration but not the other
+    ConflictingDeclaration {
+        line: usize,
+        name: String,
+    },
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeError::UndeclaredIdentifier { name, .. } => {
+                write!(f, "use of undeclared identifier '{}'", name)
+            }
+            TypeError::InvalidOperand {
+                operator, found, ..
+            } => write!(f, "invalid operand of type '{}' to '{}'", found, operator),
+            TypeError::NonScalarCondition { found, .. } => {
+                write!(f, "condition has non-scalar type '{}'", found)
+            }
+            TypeError::IncompatibleBranches {
+                then_type,
+                else_type,
+                ..
+            } => write!(
+                f,
+                "incompatible operand types '{}' and '{}' in conditional expression",
+                then_type, else_type
+            ),
+            TypeError::IncompatibleAssignment { target, value, .. } => write!(
+                f,
+                "assigning to '{}' from incompatible type '{}'",
+                target, value
+            ),
+            TypeError::NoGenericAssociation { found, .. } => write!(
+                f,
+                "controlling expression type '{}' not compatible with any generic association type",
+                found
+            ),
+            TypeError::ConstAssignment { found, .. } => write!(
+                f,
+                "cannot assign to value of const-qualified type '{}'",
+                found
+            ),
+            TypeError::ArrowOnNonPointer { found, .. } => {
+                write!(f, "invalid use of `->` on non-pointer type '{}'", found)
+            }
+            TypeError::DotOnPointer { found, .. } => {
+                write!(f, "invalid use of `.` on pointer type '{}'", found)
+            }
+            TypeError::NotAStruct { found, .. } => {
+                write!(f, "member access into non-struct type '{}'", found)
+            }
+            TypeError::NoSuchMember { member, found, .. } => {
+                write!(f, "no member named '{}' in '{}'", member, found)
+            }
+            TypeError::Redeclaration { name, .. } => write!(f, "redefinition of '{}'", name),
+            TypeError::NotAFunction { found, .. } => {
+                write!(f, "called object of type '{}' is not a function", found)
+            }
+            TypeError::ArgumentCount {
+                expected,
+                found,
+                variadic,
+                ..
+            } => write!(
+                f,
+                "function expects {}{} argument(s) but {} were given",
+                if *variadic { "at least " } else { "" },
+                expected,
+                found
+            ),
+            TypeError::ConflictingDeclaration { name, .. } => {
+                write!(f, "conflicting types for '{}'", name)
+            }
+        }
+    }
+}
+
+impl Error for TypeError {}
+
+impl TypeError {
+    /// Source line the error was found on
+    pub fn line(&self) -> usize {
+        match self {
+            TypeError::UndeclaredIdentifier { line, .. }
+            | TypeError::InvalidOperand { line, .. }
+            | TypeError::NonScalarCondition { line, .. }
+            | TypeError::IncompatibleBranches { line, .. }
+            | TypeError::IncompatibleAssignment { line, .. }
+            | TypeError::ConstAssignment { line, .. }
+            | TypeError::NoGenericAssociation { line, .. }
+            | TypeError::ArrowOnNonPointer { line, .. }
+            | TypeError::DotOnPointer { line, .. }
+            | TypeError::NotAStruct { line, .. }
+            | TypeError::NoSuchMember { line, .. }
+            | TypeError::Redeclaration { line, .. }
+            | TypeError::NotAFunction { line, .. }
+            | TypeError::ArgumentCount { line, .. }
+            | TypeError::ConflictingDeclaration { line, .. } => *line,
+        }
+    }
+}
+
+/// Walks a parsed program, assigning a type to every expression and
+/// collecting any type errors found along the way.
+pub struct TypeChecker<'a> {
+    scopes: Vec<HashMap<&'a str, Type>>,
+    /// Members of every struct defined so far, keyed by tag
+    structs: HashMap<&'a str, Vec<(&'a str, Type)>>,
+    /// Signature of every function declared or defined so far
+    functions: HashMap<&'a str, Signature>,
+    /// Values and layouts of the enumerators and structures defined so far,
+    /// to tell a null pointer constant such as `N - N` from other integers
+    enumerators: Enumerators<'a>,
+    layouts: Layouts<'a>,
+    errors: Vec<TypeError>,
+    /// Sizes of the integer types, which decide the result of mixing them
+    target: TargetConfig,
+    /// Revision of C checked against, which decides what `int f()` declares
+    standard: LanguageStandard,
+    /// Whether to check calls to `printf` and friends against their format
+    /// strings, with any problems going in `warnings`
+    lint_formats: bool,
+    warnings: Vec<Diagnostic>,
+}
+
+/// Return and parameter types of a function
+struct Signature {
+    return_type: Type,
+    params: Vec<Type>,
+    /// Whether it takes further arguments after `params`, from a `...`
+    variadic: bool,
+    /// Whether `params` were declared, rather than left unspecified by `()`
+    has_prototype: bool,
+}
+
+impl<'a> Default for TypeChecker<'a> {
+    fn default() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            structs: HashMap::new(),
+            functions: HashMap::new(),
+            enumerators: Enumerators::default(),
+            layouts: Layouts::default(),
+            errors: vec![],
+            target: TargetConfig::default(),
+            standard: LanguageStandard::default(),
+            lint_formats: false,
+            warnings: vec![],
+        }
+    }
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checker for a program built for `target`
+    pub fn for_target(target: TargetConfig) -> Self {
+        Self {
+            target,
+            ..Self::default()
+        }
+    }
+
+    /// Check for the C `standard`. Before C23, a function declared with empty
+    /// parentheses, as in `int f();`, can be called with any arguments.
+    pub fn standard(mut self, standard: LanguageStandard) -> Self {
+        self.standard = standard;
+        self
+    }
+
+    /// Warn about calls to `printf` and friends whose arguments don't match
+    /// the conversions of their format string, see [`format::check_call`]
+    pub fn lint_formats(mut self, lint: bool) -> Self {
+        self.lint_formats = lint;
+        self
+    }
+
+    /// Problems found that don't stop the program compiling, such as a
+    /// mismatched format string
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    pub fn check(&mut self, program: &[Statement<'a>]) -> Result<(), Vec<TypeError>> {
+        for statement in program {
+            self.statement(statement);
+        }
+
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        Err(std::mem::take(&mut self.errors))
+    }
+
+    fn statement(&mut self, statement: &Statement<'a>) {
+        match statement {
+            Statement::Expression(expr) => self.record(expr),
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    self.record(expr);
+                }
+            }
+            Statement::VarDecl {
+                ty,
+                name,
+                initialiser,
+                ..
+            } => {
+                match initialiser {
+                    // a scalar is initialised as if assigned its value
+                    Some(Initialiser::Expr(value)) if ty.is_scalar() => {
+                        match self.expr_type(value) {
+                            Ok(found) if !assignable(ty, &found, self.is_null_pointer(value)) => {
+                                self.errors.push(TypeError::IncompatibleAssignment {
+                                    line: name.line,
+                                    target: ty.clone(),
+                                    value: found,
+                                })
+                            }
+                            Ok(_) => (),
+                            Err(e) => self.errors.push(e),
+                        }
+                    }
+                    Some(init) => self.initialiser(init),
+                    None => (),
+                }
+                self.declare(name, ty.clone());
+            }
+            Statement::Struct {
+                name,
+                members,
+                is_union,
+            } => {
+                // any error in the layout is for the analyses to report
+                let _ = self.layouts.define(
+                    &name.literal,
+                    members,
+                    *is_union,
+                    &self.target,
+                    &self.enumerators,
+                );
+                let members = members
+                    .iter()
+                    .filter_map(|m| Some((&*m.name?.literal, m.ty.clone())))
+                    .collect();
+                self.structs.insert(&name.literal, members);
+            }
+            // enumerators are constants of type `int`
+            Statement::Enum { enumerators, .. } => {
+                for enumerator in enumerators {
+                    if let Some(value) = &enumerator.value {
+                        self.record(value);
+                    }
+                    self.declare(enumerator.name, Type::Int.qualified(Qualifiers::CONST));
+                }
+                let _ = self
+                    .enumerators
+                    .define(enumerators, &self.target, &self.layouts);
+            }
+            Statement::Prototype {
+                return_type,
+                name,
+                params,
+                variadic,
+                has_prototype,
+                ..
+            } => {
+                let signature = Signature {
+                    return_type: return_type.clone(),
+                    params: params.iter().map(|p| p.ty.clone()).collect(),
+                    variadic: *variadic,
+                    has_prototype: *has_prototype,
+                };
+                self.declare_function(name, signature);
+            }
+            Statement::Function {
+                return_type,
+                name,
+                params,
+                body,
+                variadic,
+                has_prototype,
+                ..
+            } => {
+                // declared before the body is checked so it can recurse
+                let signature = Signature {
+                    return_type: return_type.clone(),
+                    params: params.iter().map(|p| p.ty.clone()).collect(),
+                    variadic: *variadic,
+                    has_prototype: *has_prototype,
+                };
+                self.declare_function(name, signature);
+
+                self.scopes.push(HashMap::new());
+                for param in params {
+                    self.declare(param.name, param.ty.clone());
+                }
+                for statement in body {
+                    self.statement(statement);
+                }
+                self.scopes.pop();
+            }
+            Statement::Block(body) => {
+                self.scopes.push(HashMap::new());
+                for statement in body {
+                    self.statement(statement);
+                }
+                self.scopes.pop();
+            }
+            Statement::If {
+                keyword,
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.condition(keyword, condition);
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            Statement::While {
+                keyword,
+                condition,
+                body,
+            } => {
+                self.condition(keyword, condition);
+                self.statement(body);
+            }
+            Statement::For {
+                keyword,
+                initialiser,
+                condition,
+                increment,
+                body,
+            } => {
+                // a declaration in the initialiser is scoped to the loop
+                self.scopes.push(HashMap::new());
+                for statement in initialiser {
+                    self.statement(statement);
+                }
+                if let Some(condition) = condition {
+                    self.condition(keyword, condition);
+                }
+                if let Some(increment) = increment {
+                    self.record(increment);
+                }
+                self.statement(body);
+                self.scopes.pop();
+            }
+            Statement::Switch {
+                keyword,
+                condition,
+                body,
+            } => {
+                self.condition(keyword, condition);
+                self.statement(body);
+            }
+            Statement::Case { value, body, .. } => {
+                self.record(value);
+                self.statement(body);
+            }
+            Statement::StaticAssert { condition, .. } => self.record(condition),
+            Statement::Default { body, .. } | Statement::Labeled { body, .. } => {
+                self.statement(body)
+            }
+            Statement::Break(_) | Statement::Continue(_) | Statement::Goto { .. } => (),
+        }
+    }
+
+    /// Checks the controlling expression of an `if` or loop
+    fn condition(&mut self, keyword: &Token<'a>, condition: &Expr<'a>) {
+        match self.expr_type(condition) {
+            Ok(ty) if !ty.is_scalar() => self.errors.push(TypeError::NonScalarCondition {
+                line: keyword.line,
+                found: ty,
+            }),
+            Ok(_) => (),
+            Err(e) => self.errors.push(e),
+        }
+    }
+
+    fn initialiser(&mut self, init: &Initialiser<'a>) {
+        match init {
+            Initialiser::Expr(expr) => self.record(expr),
+            Initialiser::List { items, .. } => {
+                for item in items {
+                    if let Some(Designator::Index(index)) = &item.designator {
+                        self.record(index);
+                    }
+                    self.initialiser(&item.value);
+                }
+            }
+        }
+    }
+
+    /// Declares `name` in the innermost scope, shadowing any outer declaration
+    fn declare(&mut self, name: &'a Token<'a>, ty: Type) {
+        // file scope allows repeated (tentative) declarations
+        let is_file_scope = self.scopes.len() == 1;
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+        if scope.insert(&name.literal, ty).is_some() && !is_file_scope {
+            self.errors.push(TypeError::Redeclaration {
+                line: name.line,
+                name: name.literal.to_string(),
+            });
+        }
+    }
+
+    /// Records the signature of a function, unless it was already declared
+    /// with a prototype. A declaration that disagrees with the earlier one on
+    /// the parameters it takes is an error, and calls are still checked
+    /// against the earlier one.
+    fn declare_function(&mut self, name: &'a Token<'a>, signature: Signature) {
+        let replaces = match self.functions.get(&*name.literal) {
+            Some(earlier) if !self.prototyped(earlier) => true,
+            Some(earlier) => {
+                let conflicts = self.prototyped(&signature)
+                    && (earlier.params.len() != signature.params.len()
+                        || earlier.variadic != signature.variadic);
+                if conflicts {
+                    self.errors.push(TypeError::ConflictingDeclaration {
+                        line: name.line,
+                        name: name.literal.to_string(),
+                    });
+                }
+                false
+            }
+            None => true,
+        };
+        if replaces {
+            self.functions.insert(&name.literal, signature);
+        }
+    }
+
+    /// Whether calls to a function of `signature` can be checked against its
+    /// parameters, which `()` only declares to be none from C23
+    fn prototyped(&self, signature: &Signature) -> bool {
+        signature.has_prototype || self.standard >= LanguageStandard::C23
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Type> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn record(&mut self, expr: &Expr<'a>) {
+        if let Err(e) = self.expr_type(expr) {
+            self.errors.push(e);
+        }
+    }
+
+    /// Whether `expr` is a null pointer constant, an integer constant
+    /// expression equal to 0 or one cast to `void*`
+    fn is_null_pointer(&self, expr: &Expr) -> bool {
+        match &expr.kind {
+            ExprKind::Grouping(inner) => self.is_null_pointer(inner),
+            ExprKind::Cast { ty, expr, .. } if matches!(ty.unqualified(), Type::Pointer(to) if *to.unqualified() == Type::Void) => {
+                self.is_null_pointer(expr)
+            }
+            _ => matches!(
+                eval::evaluate(expr, &self.target, &self.enumerators, &self.layouts),
+                Ok(Value::Int(0))
+            ),
+        }
+    }
+
+    /// Computes the type of an expression, stopping at the first error
+    pub fn expr_type(&mut self, expr: &Expr<'a>) -> Result<Type, TypeError> {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Int(_)) => Ok(Type::Int),
+            ExprKind::Literal(Literal::Float(_)) => Ok(Type::Float),
+            ExprKind::Literal(Literal::Str(_)) => Ok(Type::Pointer(Box::new(Type::Char))),
+            ExprKind::SizeOf { .. } => Ok(self.target.size_type()),
+            ExprKind::Identifier(token) => self
+                .lookup(&token.literal)
+                .map(Type::decayed)
+                .ok_or_else(|| TypeError::UndeclaredIdentifier {
+                    line: token.line,
+                    name: token.literal.to_string(),
+                }),
+            ExprKind::Grouping(inner) => self.expr_type(inner),
+            ExprKind::Cast { paren, ty, expr } => {
+                let found = self.expr_type(expr)?;
+                // anything can be cast to `void`, to discard it
+                if *ty.unqualified() != Type::Void && !(ty.is_scalar() && found.is_scalar()) {
+                    return Err(TypeError::InvalidOperand {
+                        line: paren.line,
+                        operator: paren.token_type,
+                        found,
+                    });
+                }
+                Ok(ty.clone())
+            }
+            ExprKind::Unary { operator, right } => {
+                let right = self.expr_type(right)?;
+                match operator.token_type {
+                    TokenType::Bang if right.is_scalar() => Ok(Type::Int),
+                    TokenType::Minus if right.is_arithmetic() => Ok(promote(&right, &self.target)),
+                    _ => Err(invalid_operand(operator, right)),
+                }
+            }
+            ExprKind::Member {
+                object,
+                operator,
+                member,
+            } => {
+                let found = self.expr_type(object)?;
+                let object = match (operator.token_type, found.unqualified()) {
+                    (TokenType::Arrow, Type::Pointer(inner)) => (**inner).clone(),
+                    (TokenType::Arrow, _) => {
+                        return Err(TypeError::ArrowOnNonPointer {
+                            line: operator.line,
+                            found,
+                        });
+                    }
+                    (_, Type::Pointer(_)) => {
+                        return Err(TypeError::DotOnPointer {
+                            line: operator.line,
+                            found,
+                        });
+                    }
+                    // keeps any qualifier, which the member inherits
+                    _ => found,
+                };
+                self.member_type(member, object)
+            }
+            ExprKind::Call {
+                callee,
+                paren,
+                arguments,
+            } => {
+                let types = arguments
+                    .iter()
+                    .map(|argument| self.expr_type(argument))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if self.lint_formats {
+                    self.check_format(callee, paren, arguments, &types);
+                }
+                if let ExprKind::Identifier(name) = &callee.kind
+                    && let Some(signature) = self.functions.get(&*name.literal)
+                {
+                    let expected = signature.params.len();
+                    let arity_differs = arguments.len() < expected
+                        || (arguments.len() > expected && !signature.variadic);
+                    if arity_differs && self.prototyped(signature) {
+                        return Err(TypeError::ArgumentCount {
+                            line: paren.line,
+                            expected,
+                            found: arguments.len(),
+                            variadic: signature.variadic,
+                        });
+                    }
+                    return Ok(signature.return_type.clone());
+                }
+                let found = self.expr_type(callee)?;
+                Err(TypeError::NotAFunction {
+                    line: paren.line,
+                    found,
+                })
+            }
+            ExprKind::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let nulls = (self.is_null_pointer(left), self.is_null_pointer(right));
+                let left = self.expr_type(left)?;
+                let right = self.expr_type(right)?;
+                self.binary_type(operator, left, right, nulls)
+            }
+            ExprKind::Assign {
+                target,
+                operator,
+                value,
+            } => {
+                let target = self.expr_type(target)?;
+                if target.is_const() {
+                    return Err(TypeError::ConstAssignment {
+                        line: operator.line,
+                        found: target,
+                    });
+                }
+                let null_pointer = self.is_null_pointer(value);
+                let value = self.expr_type(value)?;
+                // compound assignments are arithmetic, `a += b` acts as `a = a + b`,
+                // except that a pointer can be moved by an integer
+                let moves_pointer = matches!(
+                    operator.token_type,
+                    TokenType::PlusEqual | TokenType::MinusEqual
+                ) && matches!(target.unqualified(), Type::Pointer(_))
+                    && value.is_integer();
+                if operator.token_type != TokenType::Equal && !moves_pointer {
+                    if !target.is_arithmetic() {
+                        return Err(invalid_operand(operator, target));
+                    }
+                    if !value.is_arithmetic() {
+                        return Err(invalid_operand(operator, value));
+                    }
+                } else if !assignable(&target, &value, null_pointer) {
+                    return Err(TypeError::IncompatibleAssignment {
+                        line: operator.line,
+                        target,
+                        value,
+                    });
+                }
+                Ok(target.unqualified().clone())
+            }
+            ExprKind::Generic {
+                keyword,
+                controlling,
+                assocs,
+            } => {
+                // the controlling expression is used as a value, so loses its qualifiers
+                let found = self.expr_type(controlling)?.unqualified().clone();
+                let chosen = assocs
+                    .iter()
+                    .find(|(ty, _)| ty.as_ref() == Some(&found))
+                    .or_else(|| assocs.iter().find(|(ty, _)| ty.is_none()));
+                match chosen {
+                    Some((_, expr)) => self.expr_type(expr),
+                    None => Err(TypeError::NoGenericAssociation {
+                        line: keyword.line,
+                        found,
+                    }),
+                }
+            }
+            // the value is that of the final expression statement, if there is one
+            ExprKind::StmtExpr(body) => {
+                self.scopes.push(HashMap::new());
+                let (value, rest) = match body.split_last() {
+                    Some((Statement::Expression(value), rest)) => (Some(value), rest),
+                    _ => (None, &body[..]),
+                };
+                for statement in rest {
+                    self.statement(statement);
+                }
+                let ty = value.map_or(Ok(Type::Void), |value| self.expr_type(value));
+                self.scopes.pop();
+                ty
+            }
+            ExprKind::Ternary {
+                condition,
+                question,
+                then_branch,
+                else_branch,
+            } => {
+                let condition = self.expr_type(condition)?;
+                if !condition.is_scalar() {
+                    return Err(TypeError::NonScalarCondition {
+                        line: question.line,
+                        found: condition,
+                    });
+                }
+
+                let nulls = (
+                    self.is_null_pointer(then_branch),
+                    self.is_null_pointer(else_branch),
+                );
+                let then_type = self.expr_type(then_branch)?;
+                let else_type = self.expr_type(else_branch)?;
+                common_type(&then_type, &else_type, &self.target)
+                    .or_else(|| pointer_common_type(&then_type, &else_type, nulls))
+                    .ok_or(TypeError::IncompatibleBranches {
+                        line: question.line,
+                        then_type,
+                        else_type,
+                    })
+            }
+        }
+    }
+
+    /// Type of `member` within `object`, which must be a defined struct or
+    /// union
+    fn member_type(&self, member: &Token<'a>, object: Type) -> Result<Type, TypeError> {
+        let (Type::Struct(tag) | Type::Union(tag)) = object.unqualified() else {
+            return Err(TypeError::NotAStruct {
+                line: member.line,
+                found: object,
+            });
+        };
+        self.structs
+            .get(tag.as_str())
+            .and_then(|members| members.iter().find(|(name, _)| *name == member.literal))
+            // members of a const or volatile struct are const or volatile too
+            .map(|(_, ty)| ty.clone().qualified(object.qualifiers()))
+            .ok_or_else(|| TypeError::NoSuchMember {
+                line: member.line,
+                member: member.literal.to_string(),
+                found: object.clone(),
+            })
+    }
+
+    /// Warns about the arguments of a call to a known format function that
+    /// don't match its format string, when that's a literal
+    fn check_format(&mut self, callee: &Expr, paren: &Token, arguments: &[Expr], types: &[Type]) {
+        let ExprKind::Identifier(name) = &callee.kind else {
+            return;
+        };
+        let Some((family, index)) = format::format_function(&name.literal) else {
+            return;
+        };
+        let Some(Expr {
+            kind: ExprKind::Literal(Literal::Str(format)),
+            ..
+        }) = arguments.get(index)
+        else {
+            return;
+        };
+        for problem in format::check_call(format, family, index + 2, &types[index + 1..]) {
+            self.warnings.push(Diagnostic::warning(paren.line, problem));
+        }
+    }
+
+    /// Result type of a binary operator, given whether each operand is a null
+    /// pointer constant
+    fn binary_type(
+        &mut self,
+        operator: &Token<'a>,
+        left: Type,
+        right: Type,
+        nulls: (bool, bool),
+    ) -> Result<Type, TypeError> {
+        let operand_error = |left: Type, right: Type| {
+            // blame whichever side is at fault, preferring the left
+            let found = if left.is_arithmetic() { right } else { left };
+            invalid_operand(operator, found)
+        };
+
+        match operator.token_type {
+            // the left operand is only evaluated for its side effects
+            TokenType::Comma => Ok(right.unqualified().clone()),
+            TokenType::Plus | TokenType::Minus
+                if matches!(left.unqualified(), Type::Pointer(_))
+                    || matches!(right.unqualified(), Type::Pointer(_)) =>
+            {
+                pointer_arithmetic(operator, left, right, &self.target)
+            }
+            TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Slash => {
+                if left.is_arithmetic() && right.is_arithmetic() {
+                    Ok(arithmetic_result(&left, &right, &self.target))
+                } else {
+                    Err(operand_error(left, right))
+                }
+            }
+            // comparisons always yield `int`, provided the operands can be
+            // compared, and a pointer can be tested for equality with null or
+            // `void*` as well
+            comparison if comparison.is_comparison() => {
+                let equality = matches!(comparison, TokenType::EqualEqual | TokenType::BangEqual);
+                match common_type(&left, &right, &self.target) {
+                    Some(ty) if ty.is_scalar() => Ok(Type::Int),
+                    _ if equality && pointer_common_type(&left, &right, nulls).is_some() => {
+                        Ok(Type::Int)
+                    }
+                    _ => Err(operand_error(left, right)),
+                }
+            }
+            _ => Err(invalid_operand(operator, left)),
+        }
+    }
+}
+
+/// Result type of `+` or `-` with a pointer operand. Adding or subtracting an
+/// integer moves the pointer by whole elements, so keeps its type, and two
+/// pointers to the same type subtract to the number of elements between them,
+/// a `ptrdiff_t`. Adding two pointers means nothing.
+fn pointer_arithmetic(
+    operator: &Token,
+    left: Type,
+    right: Type,
+    target: &TargetConfig,
+) -> Result<Type, TypeError> {
+    match (operator.token_type, left.unqualified(), right.unqualified()) {
+        (_, Type::Pointer(_), offset) if offset.is_integer() => Ok(left.unqualified().clone()),
+        (TokenType::Plus, offset, Type::Pointer(_)) if offset.is_integer() => {
+            Ok(right.unqualified().clone())
+        }
+        (TokenType::Minus, Type::Pointer(a), Type::Pointer(b))
+            if a.unqualified() == b.unqualified() =>
+        {
+            Ok(target.ptrdiff_type())
+        }
+        // blame the operand that can't go with the other, preferring the right
+        _ if matches!(left.unqualified(), Type::Pointer(_)) || left.is_integer() => {
+            Err(invalid_operand(operator, right))
+        }
+        _ => Err(invalid_operand(operator, left)),
+    }
+}
+
+fn invalid_operand(operator: &Token, found: Type) -> TypeError {
+    TypeError::InvalidOperand {
+        line: operator.line,
+        operator: operator.token_type,
+        found,
+    }
+}
+
+/// Integer promotion, anything of lower rank than `int` is widened to `int`,
+/// or to `unsigned int` if `int` can't hold all its values on `target`
+fn promote(ty: &Type, target: &TargetConfig) -> Type {
+    let int = Type::Int;
+    match ty.integer_rank() {
+        Some(rank) if rank < int.integer_rank().unwrap() => {
+            let fits = ty.integer_size(target) < int.integer_size(target)
+                || (!ty.is_unsigned(target) && ty.integer_size(target) == int.integer_size(target));
+            if fits { int } else { Type::UnsignedInt }
+        }
+        _ => ty.unqualified().clone(),
+    }
+}
+
+/// Result type of an arithmetic operation on two arithmetic operands, by the
+/// usual arithmetic conversions. Both integer operands are promoted, then the
+/// one of lower rank converted to the type of the other. When one is signed
+/// and the other unsigned, the unsigned type wins unless it has the lower
+/// rank and the signed type can hold all its values, in which case the
+/// signed type does. If the signed type is wider in rank but not in size,
+/// both become its unsigned counterpart.
+fn arithmetic_result(left: &Type, right: &Type, target: &TargetConfig) -> Type {
+    if *left.unqualified() == Type::Float || *right.unqualified() == Type::Float {
+        return Type::Float;
+    }
+    let (left, right) = (promote(left, target), promote(right, target));
+    if left == right {
+        return left;
+    }
+    let higher = |a: Type, b: Type| {
+        if a.integer_rank() >= b.integer_rank() {
+            a
+        } else {
+            b
+        }
+    };
+    let (unsigned, signed) = match (left.is_unsigned(target), right.is_unsigned(target)) {
+        (true, false) => (left, right),
+        (false, true) => (right, left),
+        _ => return higher(left, right),
+    };
+    if unsigned.integer_rank() >= signed.integer_rank() {
+        unsigned
+    } else if signed.integer_size(target) > unsigned.integer_size(target) {
+        signed
+    } else {
+        signed.to_unsigned()
+    }
+}
+
+/// Type two operands are brought to when they meet, e.g. in the branches of a
+/// ternary. `None` when the types are incompatible.
+fn common_type(left: &Type, right: &Type, target: &TargetConfig) -> Option<Type> {
+    if left.is_arithmetic() && right.is_arithmetic() {
+        return Some(arithmetic_result(left, right, target));
+    }
+    if left.unqualified() == right.unqualified() {
+        return Some(left.unqualified().clone());
+    }
+    None
+}
+
+/// Type a pointer and an operand of another type meet at, in the branches of
+/// a ternary or either side of `==` and `!=`, given whether each is a null
+/// pointer constant. Null takes the pointer's type, and an object pointer
+/// meeting `void*` becomes the `void*`.
+fn pointer_common_type(left: &Type, right: &Type, nulls: (bool, bool)) -> Option<Type> {
+    match (left.unqualified(), right.unqualified()) {
+        (Type::Pointer(_), _) if nulls.1 => Some(left.unqualified().clone()),
+        (_, Type::Pointer(_)) if nulls.0 => Some(right.unqualified().clone()),
+        (Type::Pointer(to), Type::Pointer(_)) if *to.unqualified() == Type::Void => {
+            Some(left.unqualified().clone())
+        }
+        (Type::Pointer(_), Type::Pointer(to)) if *to.unqualified() == Type::Void => {
+            Some(right.unqualified().clone())
+        }
+        _ => None,
+    }
+}
+
+/// Whether a `value` can be assigned to a `target`, given whether it's a null
+/// pointer constant, which any pointer can be set to. A pointer may gain a
+/// `const` on what it points to, but not lose one, and converts to and from
+/// `void*` freely otherwise.
+fn assignable(target: &Type, value: &Type, null_pointer: bool) -> bool {
+    match (target.unqualified(), value.unqualified()) {
+        (Type::Pointer(to), Type::Pointer(from)) => {
+            let void = *to.unqualified() == Type::Void || *from.unqualified() == Type::Void;
+            (to.unqualified() == from.unqualified() || void) && (to.is_const() || !from.is_const())
+        }
+        (Type::Pointer(_), value) => value.is_integer() && null_pointer,
+        (target, value) => (target.is_arithmetic() && value.is_arithmetic()) || target == value,
+    }
+}
+
+#[cfg(test)]
+mod typeck_tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::target::TargetAbi;
+
+    /// Type of the value returned by the last statement of `main`
+    fn return_type(source: &str) -> Result<Type, TypeError> {
+        return_type_for(source, TargetConfig::default())
+    }
+
+    /// [`return_type`], checked for `target`
+    fn return_type_for(source: &str, target: TargetConfig) -> Result<Type, TypeError> {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens)
+            .allow_gnu_extensions(true)
+            .parse()
+            .expect("Should parse");
+
+        let Some((Statement::Function { params, body, .. }, globals)) = program.split_last() else {
+            panic!("Expected a function");
+        };
+        let mut checker = TypeChecker::for_target(target);
+        for statement in globals {
+            checker.statement(statement);
+        }
+        checker.scopes.push(HashMap::new());
+        for param in params {
+            checker.declare(param.name, param.ty.clone());
+        }
+        let (
+            Statement::Return {
+                value: Some(expr), ..
+            },
+            rest,
+        ) = body.split_last().unwrap()
+        else {
+            panic!("Expected function to end in a return");
+        };
+        for statement in rest {
+            checker.statement(statement);
+        }
+        checker.expr_type(expr)
+    }
+
+    #[test]
+    fn ternary_with_matching_branches() {
+        let ty = return_type("int main(int c) { return c ? 1 : 2; }");
+        assert_eq!(ty.unwrap(), Type::Int);
+    }
+
+    #[test]
+    fn ternary_promotes_to_common_arithmetic_type() {
+        let ty = return_type("int main(int c) { float f = 1.5; return c ? 1 : f; }");
+        assert_eq!(ty.unwrap(), Type::Float);
+
+        let ty = return_type("int main(char* s) { return s ? \"yes\" : \"no\"; }");
+        assert_eq!(ty.unwrap(), Type::Pointer(Box::new(Type::Char)));
+    }
+
+    #[test]
+    fn ternary_with_incompatible_branches() {
+        let ty = return_type("int main(int c) { return c ? 1 : \"str\"; }");
+        assert!(matches!(
+            ty,
+            Err(TypeError::IncompatibleBranches {
+                line: 1,
+                then_type: Type::Int,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn ternary_with_null_or_void_pointer_branch() {
+        let int_pointer = Type::Pointer(Box::new(Type::Int));
+        let ty = return_type("int main(int c, int* p) { return c ? p : 0; }");
+        assert_eq!(ty.unwrap(), int_pointer);
+        let ty = return_type("int main(int c, int* p) { return c ? (void*)0 : p; }");
+        assert_eq!(ty.unwrap(), int_pointer);
+
+        let ty = return_type("int main(int c, int* p, void* v) { return c ? p : v; }");
+        assert_eq!(ty.unwrap(), Type::Pointer(Box::new(Type::Void)));
+
+        let ty = return_type("int main(int c, int* p) { return c ? p : 1; }");
+        assert!(matches!(ty, Err(TypeError::IncompatibleBranches { .. })));
+    }
+
+    #[test]
+    fn pointer_compares_equal_to_null_or_void_pointer() {
+        let source = "int main(int* p, void* v) {\n  if (p == 0) return 1;\n  \
+                      if (0 != p) return 2;\n  if (p == (void*)0) return 3;\n  \
+                      return p == v;\n}";
+        assert!(check_source(source).is_ok());
+
+        // ordering a pointer against null means nothing, nor does equality
+        // with any other integer
+        let int_pointer = Type::Pointer(Box::new(Type::Int));
+        let ty = return_type("int main(int* p) { return p < 0; }");
+        assert!(matches!(ty, Err(TypeError::InvalidOperand { found, .. }) if found == int_pointer));
+        let ty = return_type("int main(int* p) { return p == 1; }");
+        assert!(matches!(ty, Err(TypeError::InvalidOperand { found, .. }) if found == int_pointer));
+    }
+
+    const POINT: &str = "struct point { int x; float y; };\n";
+
+    #[test]
+    fn member_access_through_pointer_and_value() {
+        let ty = return_type(&format!(
+            "{POINT}int main(struct point* p) {{ return p->x; }}"
+        ));
+        assert_eq!(ty.unwrap(), Type::Int);
+
+        let ty = return_type(&format!(
+            "{POINT}int main(struct point s) {{ return s.y; }}"
+        ));
+        assert_eq!(ty.unwrap(), Type::Float);
+    }
+
+    #[test]
+    fn member_inherits_qualifiers_of_object() {
+        let volatile = Qualifiers {
+            is_volatile: true,
+            ..Default::default()
+        };
+        let ty = return_type(&format!(
+            "{POINT}int main(volatile struct point* p) {{ return p->x; }}"
+        ));
+        assert_eq!(ty.unwrap(), Type::Int.qualified(volatile));
+
+        let ty = return_type(&format!(
+            "{POINT}int main(void) {{ const volatile struct point s; return s.y; }}"
+        ))
+        .unwrap();
+        assert!(ty.is_const() && ty.is_volatile());
+    }
+
+    #[test]
+    fn member_access_on_union() {
+        let ty = return_type(
+            "union number { int i; float f; };\nint main(union number* n) { return n->f; }",
+        );
+        assert_eq!(ty.unwrap(), Type::Float);
+    }
+
+    #[test]
+    fn arrow_on_non_pointer_is_an_error() {
+        let ty = return_type(&format!(
+            "{POINT}int main(struct point s) {{ return s->x; }}"
+        ));
+        let err = ty.unwrap_err();
+        assert!(matches!(err, TypeError::ArrowOnNonPointer { line: 2, .. }));
+        assert_eq!(
+            err.to_string(),
+            "invalid use of `->` on non-pointer type 'struct point'"
+        );
+    }
+
+    #[test]
+    fn dot_on_pointer_is_an_error() {
+        let ty = return_type(&format!(
+            "{POINT}int main(struct point* p) {{ return p.x; }}"
+        ));
+        let err = ty.unwrap_err();
+        assert!(matches!(err, TypeError::DotOnPointer { line: 2, .. }));
+        assert_eq!(
+            err.to_string(),
+            "invalid use of `.` on pointer type 'struct point*'"
+        );
+    }
+
+    fn check_source(source: &str) -> Result<(), Vec<TypeError>> {
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().expect("Should tokenise without errors");
+        let program = Parser::new(tokens).parse().expect("Should parse");
+        TypeChecker::new().check(&program)
+    }
+
+    #[test]
+    fn inner_declaration_shadows_outer() {
+        let ty = return_type("int main(void) { int x = 1; { char* x = \"s\"; } return x; }");
+        assert_eq!(ty.unwrap(), Type::Int);
+
+        let source = "int main(void) {\n  int x = 1;\n  { char* x = \"s\"; -x; }\n  return -x;\n}";
+        let errors = check_source(source).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            TypeError::InvalidOperand { line: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn use_after_block_ends_is_undeclared() {
+        let errors =
+            check_source("int main(void) {\n  { int y = 1; }\n  return y;\n}").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            TypeError::UndeclaredIdentifier { line: 3, name } if name == "y"
+        ));
+    }
+
+    #[test]
+    fn parameters_are_in_function_scope() {
+        assert!(check_source("int f(int a) { { return a; } }\nint g(void) { return 0; }").is_ok());
+
+        let errors =
+            check_source("int f(int a) { return 0; }\nint g(void) { return a; }").unwrap_err();
+        assert!(matches!(
+            errors[0],
+            TypeError::UndeclaredIdentifier { line: 2, .. }
+        ));
+
+        // the body shares the parameters' scope, so can't redeclare them
+        let errors = check_source("int f(int a) { int a = 1; return a; }").unwrap_err();
+        assert!(matches!(
+            errors[0],
+            TypeError::Redeclaration { line: 1, .. }
+        ));
+        assert!(check_source("int f(int a) { { int a = 1; } return a; }").is_ok());
+    }
+
+    #[test]
+    fn prototype_declares_arity() {
+        let ty = return_type("int twice(int x);\nint main(void) { return twice(2); }");
+        assert_eq!(ty.unwrap(), Type::Int);
+
+        let errors =
+            check_source("int twice(int x);\nint main(void) { return twice(); }").unwrap_err();
+        assert!(matches!(
+            errors[0],
+            TypeError::ArgumentCount {
+                expected: 1,
+                found: 0,
+                variadic: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn conflicting_prototype_is_reported() {
+        let source = "int f(int a);\nint f(int a, int b) { return a; }\n\
+                      int main(void) { return f(1); }";
+        let errors = check_source(source).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            TypeError::ConflictingDeclaration { line: 2, name } if name == "f"
+        ));
+        assert_eq!(errors[0].to_string(), "conflicting types for 'f'");
+
+        assert!(check_source("int f(char* s, ...);\nint f(char* s);").is_err());
+        // repeating a matching prototype is fine
+        assert!(check_source("int f(int a);\nint f(int a);\nint f(int a) { return a; }").is_ok());
+    }
+
+    #[test]
+    fn variadic_call_accepts_extra_arguments() {
+        let ty = return_type(
+            "int printf(char* format, ...);\nint main(void) { return printf(\"%d %d\", 1, 2); }",
+        );
+        assert_eq!(ty.unwrap(), Type::Int);
+
+        // the named parameters are still required
+        let errors =
+            check_source("int printf(char* format, ...);\nint main(void) { return printf(); }")
+                .unwrap_err();
+        assert!(matches!(
+            errors[0],
+            TypeError::ArgumentCount {
+                expected: 1,
+                found: 0,
+                variadic: true,
+                ..
+            }
+        ));
+        assert_eq!(
+            errors[0].to_string(),
+            "function expects at least 1 argument(s) but 0 were given"
+        );
+    }
+
+    #[test]
+    fn empty_parentheses_leave_arity_unchecked_before_c23() {
+        let check = |source, standard| {
+            let mut lexer = Lexer::from_string(source);
+            let tokens = lexer.tokenise().expect("Should tokenise without errors");
+            let program = Parser::new(tokens).parse().expect("Should parse");
+            TypeChecker::new().standard(standard).check(&program)
+        };
+        let source = "int f();\nint main(void) { return f(1) + f(); }";
+        assert!(check(source, LanguageStandard::C11).is_ok());
+        let errors = check(source, LanguageStandard::C23).unwrap_err();
+        assert!(matches!(
+            errors.as_slice(),
+            [TypeError::ArgumentCount {
+                expected: 0,
+                found: 1,
+                ..
+            }]
+        ));
+
+        // a later prototype is checked whatever the standard, and doesn't
+        // conflict with the declaration that gave none
+        let source = "int f();\nint f(int a);\nint main(void) { return f(); }";
+        let errors = check(source, LanguageStandard::C99).unwrap_err();
+        assert!(matches!(
+            errors.as_slice(),
+            [TypeError::ArgumentCount {
+                expected: 1,
+                found: 0,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn call_has_return_type_of_function() {
+        let ty = return_type(
+            "float half(int x) { return x / 2.0; }\nint main(void) { return half(3); }",
+        );
+        assert_eq!(ty.unwrap(), Type::Float);
+
+        let errors = check_source("int f(int a) { return f(a, a); }").unwrap_err();
+        assert!(matches!(
+            errors[0],
+            TypeError::ArgumentCount {
+                expected: 1,
+                found: 2,
+                ..
+            }
+        ));
+        let errors = check_source("int f(int a) {\n  return f();\n}").unwrap_err();
+        assert!(matches!(
+            errors[0],
+            TypeError::ArgumentCount {
+                line: 2,
+                expected: 1,
+                found: 0,
+                ..
+            }
+        ));
+        let errors = check_source("int f(int a) { return a(); }").unwrap_err();
+        assert!(matches!(
+            errors[0],
+            TypeError::NotAFunction {
+                found: Type::Int,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn check_collects_errors_from_every_function() {
+        let source = "int f(int c) { return c ? 1 : \"a\"; }\nint g(void) { return x; }";
+        let mut lexer = Lexer::from_string(source);
+        let tokens = lexer.tokenise().unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+
+        let errors = TypeChecker::new().check(&program).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[0],
+            TypeError::IncompatibleBranches { line: 1, .. }
+        ));
+        assert!(matches!(
+            errors[1],
+            TypeError::UndeclaredIdentifier { line: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn assignment_to_const_is_an_error() {
+        let source = "int main(void) {\n  const int x = 1;\n  x = 2;\n  return x;\n}";
+        let errors = check_source(source).unwrap_err();
+        assert!(matches!(
+            errors.as_slice(),
+            [TypeError::ConstAssignment { line: 3, found }] if found.to_string() == "const int"
+        ));
+
+        // the pointer itself can change, just not what it points to
+        let source = format!(
+            "{POINT}int main(const struct point* p, struct point* q) {{\n  p = q;\n  p->x = 1;\n  return 0;\n}}"
+        );
+        let errors = check_source(&source).unwrap_err();
+        assert!(matches!(
+            errors.as_slice(),
+            [TypeError::ConstAssignment { line: 4, .. }]
+        ));
+
+        let source = "int main(int* const p, int* q) {\n  p = q;\n  return 0;\n}";
+        let errors = check_source(source).unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "cannot assign to value of const-qualified type 'int* const'"
+        );
+    }
+
+    #[test]
+    fn assignment_to_non_const_is_allowed() {
+        let source = "int main(void) {\n  int x = 1;\n  x = 2;\n  x += 1.5;\n  return x;\n}";
+        assert!(check_source(source).is_ok());
+
+        let ty = return_type("int main(const int x) { int y = 0; return y = x; }");
+        assert_eq!(ty.unwrap(), Type::Int);
+    }
+
+    #[test]
+    fn pointer_takes_null_pointer_constant_or_void_pointer() {
+        let source = "void* get(void);\nenum { NONE };\nint main(void) {\n  int* p = 0;\n  \
+                      int* q = get();\n  p = 0;\n  p = (void*)0;\n  p = (NONE);\n  \
+                      q = (void*)(1 - 1);\n  void* v = p;\n  const void* c = q;\n  return 0;\n}";
+        assert!(check_source(source).is_ok());
+
+        // any other integer is no pointer, and `void*` doesn't drop a `const`
+        let source = "int main(int n, const int* c) {\n  int* p = 1;\n  p = n;\n  void* v = c;\n  return 0;\n}";
+        let errors = check_source(source).unwrap_err();
+        assert!(matches!(
+            errors.as_slice(),
+            [
+                TypeError::IncompatibleAssignment { line: 2, .. },
+                TypeError::IncompatibleAssignment { line: 3, .. },
+                TypeError::IncompatibleAssignment { line: 4, .. },
+            ]
+        ));
+    }
+
+    #[test]
+    fn generic_selects_association_by_type() {
+        let source =
+            "int main(const float f) { return _Generic(f, int: 1, float: \"f\", default: 2); }";
+        assert_eq!(
+            return_type(source).unwrap(),
+            Type::Pointer(Box::new(Type::Char))
+        );
+
+        let ty = return_type("int main(char c) { return _Generic(c, int: \"i\", default: 2); }");
+        assert_eq!(ty.unwrap(), Type::Int);
+
+        let ty = return_type("int main(char c) { return _Generic(c, int: 1); }");
+        assert!(matches!(
+            ty,
+            Err(TypeError::NoGenericAssociation {
+                found: Type::Char,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn statement_expression_has_type_of_last_expression() {
+        let ty = return_type("int main(void) { return ({ float f = 1.5; f; }); }");
+        assert_eq!(ty.unwrap(), Type::Float);
+
+        let ty = return_type("int main(void) { return ({ int i = 1; }); }");
+        assert_eq!(ty.unwrap(), Type::Void);
+
+        // declarations inside don't leak out
+        let ty = return_type("int main(void) { int x = ({ int i = 1; i; }); return i; }");
+        assert!(matches!(ty, Err(TypeError::UndeclaredIdentifier { .. })));
+    }
+
+    /// Type of `a + b` with `a` and `b` declared as `left` and `right`
+    fn sum_type(left: &str, right: &str, target: TargetConfig) -> Type {
+        let source = format!("int main({} a, {} b) {{ return a + b; }}", left, right);
+        return_type_for(&source, target).unwrap()
+    }
+
+    #[test]
+    fn usual_arithmetic_conversions() {
+        let sysv = TargetConfig::default();
+        let cases = [
+            ("char", "char", Type::Int),
+            ("short", "unsigned char", Type::Int),
+            ("int", "unsigned", Type::UnsignedInt),
+            ("int", "long", Type::Long),
+            ("unsigned", "long", Type::Long),
+            ("unsigned long", "long long", Type::UnsignedLongLong),
+            ("long long", "unsigned char", Type::LongLong),
+            ("const unsigned short", "int", Type::Int),
+            ("long", "float", Type::Float),
+        ];
+        for (left, right, ty) in cases {
+            assert_eq!(sum_type(left, right, sysv), ty, "{} + {}", left, right);
+            assert_eq!(sum_type(right, left, sysv), ty, "{} + {}", right, left);
+        }
+
+        // a 32-bit `long` can't hold every `unsigned int`
+        let windows = TargetConfig {
+            abi: TargetAbi::Windows,
+            ..sysv
+        };
+        assert_eq!(sum_type("unsigned", "long", windows), Type::UnsignedLong);
+        // nor a 16-bit `int` every `unsigned short`
+        let int16 = TargetConfig {
+            int_bits: 16,
+            ..sysv
+        };
+        assert_eq!(sum_type("unsigned short", "char", int16), Type::UnsignedInt);
+
+        let ty = return_type("int main(unsigned char c) { return -c; }");
+        assert_eq!(ty.unwrap(), Type::Int);
+        let ty = return_type("int main(void) { return sizeof(int) * 2; }");
+        assert_eq!(ty.unwrap(), Type::UnsignedLong);
+    }
+
+    #[test]
+    fn array_decays_to_pointer() {
+        let ty = return_type("int f(void) { char* names[2]; return names; }");
+        assert_eq!(
+            ty.unwrap(),
+            Type::Pointer(Box::new(Type::Pointer(Box::new(Type::Char))))
+        );
+    }
+
+    #[test]
+    fn pointer_arithmetic_keeps_pointer_type() {
+        let int_pointer = Type::Pointer(Box::new(Type::Int));
+        let ty = return_type("int f(int* p) { return p + 1; }");
+        assert_eq!(ty.unwrap(), int_pointer);
+        let ty = return_type("int f(int* const p, char c) { return c + p; }");
+        assert_eq!(ty.unwrap(), int_pointer);
+        let ty = return_type("int f(int* p) { p -= 2; return p - 1; }");
+        assert_eq!(ty.unwrap(), int_pointer);
+
+        let ty = return_type("int f(int* p, const int* q) { return p - q; }");
+        assert_eq!(ty.unwrap(), Type::Long);
+        let windows = TargetConfig {
+            abi: TargetAbi::Windows,
+            ..TargetConfig::default()
+        };
+        let ty = return_type_for("int f(int* p, int* q) { return p - q; }", windows);
+        assert_eq!(ty.unwrap(), Type::LongLong);
+
+        let error = |source| match return_type(source) {
+            Err(TypeError::InvalidOperand {
+                operator, found, ..
+            }) => (operator, found),
+            other => panic!("Expected an invalid operand, got {:?}", other),
+        };
+        assert_eq!(
+            error("int f(int* p, int* q) { return p + q; }"),
+            (TokenType::Plus, int_pointer.clone())
+        );
+        assert_eq!(
+            error("int f(int* p, char* q) { return p - q; }"),
+            (TokenType::Minus, Type::Pointer(Box::new(Type::Char)))
+        );
+        assert_eq!(
+            error("int f(int* p) { return 1 - p; }"),
+            (TokenType::Minus, int_pointer.clone())
+        );
+        assert_eq!(
+            error("int f(int* p, float x) { return p + x; }"),
+            (TokenType::Plus, Type::Float)
+        );
+        assert_eq!(
+            error("int f(int* p) { return p * 2; }"),
+            (TokenType::Star, int_pointer)
+        );
+    }
+
+    #[test]
+    fn comma_has_type_of_right_operand() {
+        let source =
+            "int main(void) { float f = 1.5; int i; for (i = 0, f; i; i = 1, f) {} return f; }";
+        assert!(check_source(source).is_ok());
+
+        // the discarded left operand is still checked
+        let source =
+            "int main(int i) { char* s; int n; for (n = 0, s = 1.5; i; i = 1) {} return n; }";
+        assert!(matches!(
+            &check_source(source).unwrap_err()[..],
+            [TypeError::IncompatibleAssignment { .. }]
+        ));
+    }
+}
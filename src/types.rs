@@ -0,0 +1,256 @@
+use crate::target::TargetConfig;
+use std::fmt;
+
+/// C types as far as the front end currently understands them
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub enum Type {
+    Void,
+    /// Plain `char`, signed or not depending on the target, but a different
+    /// type from both `signed char` and `unsigned char`
+    Char,
+    SignedChar,
+    UnsignedChar,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
+    Float,
+    Pointer(Box<Type>),
+    /// Array of the element type, with its length unless that's left out, as
+    /// in `char* argv[]`
+    Array(Box<Type>, Option<u64>),
+    /// Structure type, identified by its tag
+    Struct(String),
+    /// Union type, identified by its tag, which no structure shares
+    Union(String),
+    /// Type with one or more qualifiers, e.g. `const int`. Never nested, and
+    /// never with an empty set of qualifiers.
+    Qualified(Box<Type>, Qualifiers),
+}
+
+/// Type qualifiers that can be applied to a type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Qualifiers {
+    /// `const`, the object can't be assigned to
+    pub is_const: bool,
+    pub is_volatile: bool,
+    /// `restrict`, only valid on pointers
+    pub is_restrict: bool,
+    /// `_Atomic`
+    pub is_atomic: bool,
+}
+
+impl Qualifiers {
+    pub const CONST: Qualifiers = Qualifiers {
+        is_const: true,
+        is_volatile: false,
+        is_restrict: false,
+        is_atomic: false,
+    };
+
+    pub fn is_empty(self) -> bool {
+        self == Qualifiers::default()
+    }
+
+    /// Every qualifier in either `self` or `other`
+    pub fn union(self, other: Qualifiers) -> Qualifiers {
+        Qualifiers {
+            is_const: self.is_const || other.is_const,
+            is_volatile: self.is_volatile || other.is_volatile,
+            is_restrict: self.is_restrict || other.is_restrict,
+            is_atomic: self.is_atomic || other.is_atomic,
+        }
+    }
+}
+
+impl Type {
+    pub fn is_integer(&self) -> bool {
+        self.integer_rank().is_some()
+    }
+
+    pub fn is_arithmetic(&self) -> bool {
+        self.is_integer() || *self.unqualified() == Type::Float
+    }
+
+    /// Integer conversion rank, the same for the signed and unsigned types
+    /// of a size and greater for wider types. `None` for non-integers.
+    pub fn integer_rank(&self) -> Option<u8> {
+        let rank = match self.unqualified() {
+            Type::Char | Type::SignedChar | Type::UnsignedChar => 1,
+            Type::Short | Type::UnsignedShort => 2,
+            Type::Int | Type::UnsignedInt => 3,
+            Type::Long | Type::UnsignedLong => 4,
+            Type::LongLong | Type::UnsignedLongLong => 5,
+            _ => return None,
+        };
+        Some(rank)
+    }
+
+    /// Whether the type is an unsigned integer type on `target`
+    pub fn is_unsigned(&self, target: &TargetConfig) -> bool {
+        match self.unqualified() {
+            Type::Char => !target.char_signed,
+            Type::UnsignedChar
+            | Type::UnsignedShort
+            | Type::UnsignedInt
+            | Type::UnsignedLong
+            | Type::UnsignedLongLong => true,
+            _ => false,
+        }
+    }
+
+    /// Size in bytes of an integer type on `target`
+    pub fn integer_size(&self, target: &TargetConfig) -> Option<u64> {
+        let size = match self.integer_rank()? {
+            1 => 1,
+            2 => 2,
+            3 => u64::from(target.int_bits / 8),
+            4 => target.long_size(),
+            _ => 8,
+        };
+        Some(size)
+    }
+
+    /// The unsigned integer type of the same rank, unqualified
+    pub fn to_unsigned(&self) -> Type {
+        match self.unqualified() {
+            Type::Char | Type::SignedChar => Type::UnsignedChar,
+            Type::Short => Type::UnsignedShort,
+            Type::Int => Type::UnsignedInt,
+            Type::Long => Type::UnsignedLong,
+            Type::LongLong => Type::UnsignedLongLong,
+            other => other.clone(),
+        }
+    }
+
+    /// Arithmetic and pointer types, i.e. anything usable as a condition
+    pub fn is_scalar(&self) -> bool {
+        self.is_arithmetic() || matches!(self.unqualified(), Type::Pointer(_))
+    }
+
+    /// Whether the type itself is `const`, e.g. `int* const` but not the
+    /// pointer to a constant `const int*`
+    pub fn is_const(&self) -> bool {
+        self.qualifiers().is_const
+    }
+
+    /// Whether the type itself is `volatile`, so every read and write of an
+    /// object of the type has to happen, as written
+    pub fn is_volatile(&self) -> bool {
+        self.qualifiers().is_volatile
+    }
+
+    /// Qualifiers on the type itself, rather than on anything it points to
+    pub fn qualifiers(&self) -> Qualifiers {
+        match self {
+            Type::Qualified(_, qualifiers) => *qualifiers,
+            _ => Qualifiers::default(),
+        }
+    }
+
+    /// The type with any qualifiers removed
+    pub fn unqualified(&self) -> &Type {
+        match self {
+            Type::Qualified(inner, _) => inner,
+            other => other,
+        }
+    }
+
+    /// The type of a value of this type in an expression, where an array
+    /// stands for a pointer to its first element
+    pub fn decayed(&self) -> Type {
+        match self.unqualified() {
+            Type::Array(element, _) => Type::Pointer(element.clone()),
+            _ => self.clone(),
+        }
+    }
+
+    /// The type at the bottom of the pointers and arrays making up `self`,
+    /// along with the abstract declarator deriving `self` from it, placed
+    /// around `inner`. So for `int (*)[10]`, `int` and `(*)[10]`.
+    fn declarator(&self, inner: String) -> (&Type, String) {
+        match self {
+            Type::Pointer(pointee) => pointee.declarator(format!("*{}", inner)),
+            // qualifiers on a pointer go after the `*`
+            Type::Qualified(pointer, qualifiers) if matches!(**pointer, Type::Pointer(_)) => {
+                let Type::Pointer(pointee) = &**pointer else {
+                    unreachable!("matched a pointer")
+                };
+                pointee.declarator(format!("* {}{}", qualifiers, inner))
+            }
+            Type::Array(element, length) => {
+                // `[]` binds tighter than `*`, so a pointer inside needs parentheses
+                let inner = if inner.starts_with('*') {
+                    format!("({})", inner)
+                } else {
+                    inner
+                };
+                let length = length.map_or(String::new(), |length| length.to_string());
+                element.declarator(format!("{}[{}]", inner, length))
+            }
+            base => (base, inner),
+        }
+    }
+
+    /// The type with `qualifiers` added to any it already has
+    pub fn qualified(self, qualifiers: Qualifiers) -> Type {
+        if qualifiers.is_empty() {
+            return self;
+        }
+        match self {
+            Type::Qualified(inner, existing) => Type::Qualified(inner, existing.union(qualifiers)),
+            other => Type::Qualified(Box::new(other), qualifiers),
+        }
+    }
+}
+
+impl fmt::Display for Qualifiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            (self.is_const, "const"),
+            (self.is_volatile, "volatile"),
+            (self.is_restrict, "restrict"),
+            (self.is_atomic, "_Atomic"),
+        ];
+        let names: Vec<_> = names
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, name)| *name)
+            .collect();
+        write!(f, "{}", names.join(" "))
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (base, declarator) = self.declarator(String::new());
+        if !declarator.is_empty() {
+            // spaced like `int (*)[10]` when parenthesised, else `char*`
+            let space = if declarator.starts_with('(') { " " } else { "" };
+            return write!(f, "{}{}{}", base, space, declarator);
+        }
+        match self {
+            Type::Void => write!(f, "void"),
+            Type::Char => write!(f, "char"),
+            Type::SignedChar => write!(f, "signed char"),
+            Type::UnsignedChar => write!(f, "unsigned char"),
+            Type::Short => write!(f, "short"),
+            Type::UnsignedShort => write!(f, "unsigned short"),
+            Type::Int => write!(f, "int"),
+            Type::UnsignedInt => write!(f, "unsigned int"),
+            Type::Long => write!(f, "long"),
+            Type::UnsignedLong => write!(f, "unsigned long"),
+            Type::LongLong => write!(f, "long long"),
+            Type::UnsignedLongLong => write!(f, "unsigned long long"),
+            Type::Float => write!(f, "float"),
+            Type::Struct(tag) => write!(f, "struct {}", tag),
+            Type::Union(tag) => write!(f, "union {}", tag),
+            Type::Qualified(inner, qualifiers) => write!(f, "{} {}", qualifiers, inner),
+            Type::Pointer(_) | Type::Array(..) => unreachable!("derived types have a declarator"),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Compares the tree printed for each `.c` file in `tests/fixtures/ast` with
+//! the `.expected` file beside it. Run with `UPDATE_SNAPSHOTS=1` to write the
+//! current trees out as the expected ones instead.
+
+use c_comp_rs::lexer::Lexer;
+use c_comp_rs::parser::Parser;
+use std::fs;
+use std::path::Path;
+
+const FIXTURES: &str = "tests/fixtures/ast";
+
+/// Trees of every top-level declaration in `source`, as the compiler prints
+/// them
+fn print_trees(path: &Path) -> String {
+    let source = fs::read(path).unwrap();
+    let mut lexer = Lexer::from_bytes(&source);
+    let tokens = lexer
+        .tokenise()
+        .unwrap_or_else(|e| panic!("{} should tokenise: {:?}", path.display(), e));
+    let program = Parser::new(tokens)
+        .parse()
+        .unwrap_or_else(|e| panic!("{} should parse: {}", path.display(), e));
+    program
+        .iter()
+        .map(|stmt| stmt.print_tree() + "\n")
+        .collect()
+}
+
+/// The lines that differ between `expected` and `actual`, marked `-` and `+`
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<_> = expected.lines().collect();
+    let actual: Vec<_> = actual.lines().collect();
+    let mut diff = String::new();
+    for i in 0..expected.len().max(actual.len()) {
+        let (old, new) = (expected.get(i), actual.get(i));
+        if old == new {
+            continue;
+        }
+        if let Some(old) = old {
+            diff.push_str(&format!("{:>4} - {}\n", i + 1, old));
+        }
+        if let Some(new) = new {
+            diff.push_str(&format!("{:>4} + {}\n", i + 1, new));
+        }
+    }
+    diff
+}
+
+#[test]
+fn trees_match_snapshots() {
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some_and(|v| v == "1");
+    let mut fixtures: Vec<_> = fs::read_dir(FIXTURES)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "c"))
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "No fixtures in {}", FIXTURES);
+
+    let mut failures = vec![];
+    for fixture in &fixtures {
+        let actual = print_trees(fixture);
+        let snapshot = fixture.with_extension("expected");
+        if update {
+            fs::write(&snapshot, &actual).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&snapshot) {
+            Ok(expected) if expected == actual => (),
+            Ok(expected) => failures.push(format!(
+                "{} doesn't match {}:\n{}",
+                fixture.display(),
+                snapshot.display(),
+                diff(&expected, &actual)
+            )),
+            Err(_) => failures.push(format!("{} has no snapshot", fixture.display())),
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\nRun with UPDATE_SNAPSHOTS=1 to accept the new trees",
+        failures.join("\n")
+    );
+}
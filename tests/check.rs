@@ -0,0 +1,112 @@
+use std::process::Command;
+
+fn run_check(fixture: &str) -> (bool, Vec<String>) {
+    let output = Command::new(env!("CARGO_BIN_EXE_c-comp-rs"))
+        .args(["--check", &format!("tests/fixtures/{}", fixture)])
+        .output()
+        .expect("Should run compiler");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    (
+        output.status.success(),
+        stderr.lines().map(str::to_string).collect(),
+    )
+}
+
+#[test]
+fn check_reports_every_problem() {
+    let (success, diagnostics) = run_check("check_problems.c");
+    assert!(!success);
+
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.contains(": error: "))
+        .count();
+    let warnings = diagnostics
+        .iter()
+        .filter(|d| d.contains(": warning: "))
+        .count();
+    assert_eq!(diagnostics.len(), 7, "{:#?}", diagnostics);
+    assert_eq!((errors, warnings), (3, 4));
+    assert!(diagnostics.contains(
+        &"tests/fixtures/check_problems.c:14: error: 'continue' statement not in loop".to_string()
+    ));
+    assert!(
+        diagnostics.contains(
+            &"tests/fixtures/check_problems.c:9: warning: unused variable 'b'".to_string()
+        )
+    );
+}
+
+#[test]
+fn check_passes_clean_file() {
+    let (success, diagnostics) = run_check("check_clean.c");
+    assert!(success);
+    assert!(diagnostics.is_empty(), "{:#?}", diagnostics);
+}
+
+/// Exit code and stdout of `--fsyntax-only` on `fixture`, with any extra `args`
+fn run_syntax_only(fixture: &str, args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_c-comp-rs"))
+        .arg("--fsyntax-only")
+        .args(args)
+        .arg(format!("tests/fixtures/{}", fixture))
+        .output()
+        .expect("Should run compiler");
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn syntax_only_exit_codes() {
+    assert_eq!(
+        run_syntax_only("check_clean.c", &[]),
+        (Some(0), String::new())
+    );
+    assert_eq!(
+        run_syntax_only("syntax_error.c", &[]),
+        (Some(1), String::new())
+    );
+}
+
+#[test]
+fn warnings_fail_only_with_werror() {
+    assert_eq!(
+        run_syntax_only("check_warnings.c", &[]),
+        (Some(0), String::new())
+    );
+    assert_eq!(
+        run_syntax_only("check_warnings.c", &["-Werror"]),
+        (Some(1), String::new())
+    );
+}
+
+#[test]
+fn no_color_keeps_diagnostics_plain() {
+    let output = Command::new(env!("CARGO_BIN_EXE_c-comp-rs"))
+        .args(["--check", "--no-color", "tests/fixtures/check_problems.c"])
+        .output()
+        .expect("Should run compiler");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains(": error: "), "{}", stderr);
+    assert!(!stderr.contains('\x1b'), "{}", stderr);
+}
+
+#[test]
+fn wformat_checks_format_strings() {
+    assert_eq!(
+        run_syntax_only("check_formats.c", &["-Werror"]),
+        (Some(0), String::new())
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_c-comp-rs"))
+        .args(["--check", "-Wformat", "tests/fixtures/check_formats.c"])
+        .output()
+        .expect("Should run compiler");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "tests/fixtures/check_formats.c:6: warning: \
+         format '%d' expects an integer, but argument 2 has type 'char*'\n"
+    );
+}
@@ -0,0 +1,100 @@
+use c_comp_rs::lexer::Lexer;
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn emit_tokens_json_has_one_object_per_token() {
+    let path = "tests/fixtures/check_clean.c";
+    let output = Command::new(env!("CARGO_BIN_EXE_c-comp-rs"))
+        .args(["--emit=tokens-json", path])
+        .output()
+        .expect("Should run compiler");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    let source = fs::read(path).unwrap();
+    let mut lexer = Lexer::from_bytes(&source);
+    let tokens = lexer.tokenise().expect("Should tokenise without errors");
+
+    let trimmed = stdout.trim();
+    assert!(trimmed.starts_with('[') && trimmed.ends_with(']'));
+    let objects: Vec<_> = trimmed
+        .lines()
+        .filter(|line| line.trim_start().starts_with('{'))
+        .collect();
+    assert_eq!(objects.len(), tokens.len());
+    assert_eq!(
+        objects[0].trim().trim_end_matches(','),
+        r#"{"type": "Int", "lexeme": "int", "line": 1, "column": 1}"#
+    );
+}
+
+#[test]
+fn dump_symbols_lists_each_scope() {
+    let output = Command::new(env!("CARGO_BIN_EXE_c-comp-rs"))
+        .args(["--dump-symbols", "tests/fixtures/nested_scopes.c"])
+        .output()
+        .expect("Should run compiler");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    // every `count` is declared in a scope of its own, shadowing the one outside
+    let expected = "\
+file scope
+  point: struct point (tag)
+  count: int (variable)
+  area: int (function)
+  function 'area'
+    width: int (parameter)
+    height: int (parameter)
+    count: int (variable)
+    block
+      count: char (variable)
+      block
+        inner: int (variable)
+    for loop
+      i: int (variable)
+      block
+        count: float (variable)
+";
+    assert_eq!(stdout, expected);
+}
+
+#[test]
+fn ast_json_is_compact_or_pretty() {
+    let emit = |mode: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_c-comp-rs"))
+            .args([mode, "tests/fixtures/check_clean.c"])
+            .output()
+            .expect("Should run compiler");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let compact = emit("--ast=json");
+    assert_eq!(compact.lines().count(), 1);
+    assert!(compact.starts_with(r#"[{"node":"#));
+
+    let pretty = emit("--ast=json-pretty");
+    assert!(pretty.starts_with("[\n  {\n    \"node\": "));
+    assert!(pretty.lines().count() > 1);
+    // the same document, only laid out differently
+    let strip = |json: &str| -> String {
+        let mut in_string = false;
+        let mut escaped = false;
+        json.chars()
+            .filter(|&c| {
+                let keep = in_string || !c.is_whitespace();
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = in_string;
+                } else if c == '"' {
+                    in_string = !in_string;
+                }
+                keep
+            })
+            .collect()
+    };
+    assert_eq!(strip(&pretty), strip(&compact));
+}
@@ -0,0 +1,8 @@
+int sign(int x) {
+    if (x < 0) {
+        return -1;
+    } else if (x == 0) {
+        return 0;
+    } else
+        return 1;
+}
@@ -0,0 +1,11 @@
+int count(int n) {
+    int total = 0;
+    for (int i = 0; i < n; i = i + 1) {
+        if (i == 3)
+            continue;
+        total += i;
+    }
+    while (total > 10)
+        total -= 10;
+    return total;
+}
@@ -0,0 +1,14 @@
+int helper(int x) {
+    if (x) {
+        return x;
+    }
+    return 0;
+}
+
+int main(void) {
+    int a = 1;
+    while (a) {
+        break;
+    }
+    return a ? 2 : 3;
+}
@@ -0,0 +1,8 @@
+int printf(const char* format, ...);
+
+int main(void) {
+    char* name = "world";
+    printf("hello, %s\n", name);
+    printf("%d\n", name);
+    return 0;
+}
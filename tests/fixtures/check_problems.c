@@ -0,0 +1,16 @@
+int helper(int x) {
+    if (x) {
+        return x;
+    }
+}
+
+int main(void) {
+    int a = 1;
+    int b = a ? 2 : "two";
+    while (a) {
+        break;
+        a;
+    }
+    if (a) continue;
+    return c;
+}
@@ -0,0 +1,17 @@
+struct point { int x; int y; };
+
+int count;
+
+int area(int width, int height) {
+    int count = width * height;
+    {
+        char count = 'c';
+        {
+            int inner = 1;
+        }
+    }
+    for (int i = 0; i < height; i) {
+        float count = 1.0;
+    }
+    return count;
+}
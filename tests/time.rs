@@ -0,0 +1,54 @@
+use std::process::Command;
+
+#[test]
+fn time_reports_each_phase() {
+    let output = Command::new(env!("CARGO_BIN_EXE_c-comp-rs"))
+        .args(["--time", "tests/fixtures/check_clean.c"])
+        .output()
+        .expect("Should run compiler");
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    let phases: Vec<_> = stderr
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .collect();
+    assert_eq!(phases, ["phase", "lex", "parse", "emit"], "{}", stderr);
+    // the timings don't get mixed into the tree on stdout
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("parse"));
+}
+
+#[test]
+fn time_reports_each_analysis_of_a_check() {
+    let output = Command::new(env!("CARGO_BIN_EXE_c-comp-rs"))
+        .args(["--check", "--time", "tests/fixtures/check_clean.c"])
+        .output()
+        .expect("Should run compiler");
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    let phases: Vec<_> = stderr
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .collect();
+    assert_eq!(
+        phases[..4],
+        ["phase", "lex", "parse", "types"],
+        "{}",
+        stderr
+    );
+    for analysis in [
+        "unused-variables",
+        "self-assignment",
+        "no-effect",
+        "division-by-zero",
+        "goto-labels",
+        "call-graph",
+        "unused-functions",
+    ] {
+        assert!(phases.contains(&analysis), "{}", stderr);
+    }
+    // the analyses are timed one by one, not all together
+    assert!(!phases.contains(&"check"), "{}", stderr);
+}